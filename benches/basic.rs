@@ -2,10 +2,16 @@
 extern crate criterion;
 
 mod no_dependencies;
+mod pooled_resources;
 
 criterion_group!(
     no_dependencies,
     no_dependencies::tonks,
     no_dependencies::shred
 );
-criterion_main!(no_dependencies);
+criterion_group!(
+    pooled_resources,
+    pooled_resources::unpooled,
+    pooled_resources::pooled
+);
+criterion_main!(no_dependencies, pooled_resources);
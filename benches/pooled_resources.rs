@@ -0,0 +1,48 @@
+use criterion::{BenchmarkId, Criterion};
+use tonks::Resources;
+
+/// Number of elements pushed to the per-frame buffer.
+const BUFFER_LENS: [usize; 3] = [1024, 16384, 262144];
+
+/// Frames simulated in each iteration.
+const FRAMES: usize = 16;
+
+pub fn unpooled(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pooled_resources/unpooled");
+
+    for len in BUFFER_LENS.iter() {
+        let mut resources = Resources::new();
+
+        group.bench_with_input(BenchmarkId::from_parameter(len), len, |b, len| {
+            b.iter(|| {
+                for _ in 0..FRAMES {
+                    // Allocates a new buffer every frame.
+                    resources.insert(Vec::<u64>::new());
+                    resources.get_mut::<Vec<u64>>().extend(0..*len as u64);
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+pub fn pooled(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pooled_resources/pooled");
+
+    for len in BUFFER_LENS.iter() {
+        let mut resources = Resources::new();
+
+        group.bench_with_input(BenchmarkId::from_parameter(len), len, |b, len| {
+            b.iter(|| {
+                for _ in 0..FRAMES {
+                    // Reuses the buffer removed in the previous frame.
+                    resources.insert_pooled::<Vec<u64>>().extend(0..*len as u64);
+                    resources.remove_pooled::<Vec<u64>>();
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
@@ -5,7 +5,10 @@ extern crate syn;
 #[macro_use]
 extern crate quote;
 
-use syn::{FnArg, ItemFn, Pat, Type, DeriveInput, Ident};
+use syn::{AttributeArgs, Data, DeriveInput, Expr, ExprMethodCall, Fields, FnArg, Ident, ItemFn, Meta, NestedMeta, Pat, Type};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
 use proc_macro2::{TokenStream};
 
 #[proc_macro_derive(Resource)]
@@ -27,13 +30,162 @@ pub fn derive_resource(input: proc_macro::TokenStream) -> proc_macro::TokenStrea
     result.into()
 }
 
+/// Derives `SystemData` for a struct whose fields are all `SystemData`,
+/// allowing a bundle of accessors to be reused across systems.
+///
+/// For a struct `Foo`, a struct named `FooOutput` is generated alongside, containing
+/// the output of each field with the same visibility; this is what systems receive.
+/// The bundle may be passed to `#[system]` functions as `&Foo`.
+///
+/// Generic parameters are supported. The lifetime `'__data` is reserved
+/// for the generated implementations.
+#[proc_macro_derive(SystemData)]
+pub fn derive_system_data(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: DeriveInput = parse_macro_input!(input as DeriveInput);
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => panic!("system data bundles must have named fields"),
+        },
+        _ => panic!("system data can only be derived for structs"),
+    };
+
+    let visibility = &input.vis;
+    let ident = &input.ident;
+    let output_ident = format_ident!("{}Output", ident);
+
+    let field_visibilities: Vec<_> = fields.iter().map(|field| &field.vis).collect();
+    let field_idents: Vec<_> = fields.iter().map(|field| field.ident.as_ref().unwrap()).collect();
+    let field_types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    let field_count = field_idents.len();
+
+    let (impl_generics, ty_generics, _) = input.generics.split_for_impl();
+
+    // Generics of the `SystemData<'__data>` impl and the output struct.
+    let mut data_generics = input.generics.clone();
+    data_generics.params.insert(0, parse_quote!('__data));
+    {
+        let where_clause = data_generics.make_where_clause();
+        where_clause.predicates.push(parse_quote!(#ident #ty_generics: '__data));
+        for ty in &field_types {
+            where_clause.predicates.push(parse_quote!(#ty: tonks::SystemData<'__data>));
+        }
+    }
+    let (data_impl_generics, data_ty_generics, data_where_clause) = data_generics.split_for_impl();
+
+    // Generics of the `MacroData` impl.
+    let mut macro_generics = input.generics.clone();
+    macro_generics.make_where_clause().predicates.push(parse_quote!(
+        #ident #ty_generics: for<'__data> tonks::SystemData<'__data> + 'static
+    ));
+    let macro_where_clause = &macro_generics.where_clause;
+
+    let result = quote! {
+        #visibility struct #output_ident #data_impl_generics #data_where_clause {
+            #(#field_visibilities #field_idents: <#field_types as tonks::SystemData<'__data>>::Output,)*
+        }
+
+        impl #data_impl_generics tonks::SystemData<'__data> for #ident #ty_generics #data_where_clause {
+            type Output = #output_ident #data_ty_generics;
+
+            unsafe fn load_from_resources(
+                resources: &mut tonks::Resources,
+                ctx: tonks::SystemCtx,
+                world: &tonks::legion::world::World,
+            ) -> Self {
+                Self {
+                    #(#field_idents: <#field_types as tonks::SystemData<'__data>>::load_from_resources(resources, ctx.clone(), world),)*
+                }
+            }
+
+            fn init(
+                &mut self,
+                resources: &mut tonks::Resources,
+                component_reads: &[tonks::legion::storage::ComponentTypeId],
+                component_writes: &[tonks::legion::storage::ComponentTypeId],
+            ) {
+                #(<#field_types as tonks::SystemData<'__data>>::init(&mut self.#field_idents, resources, component_reads, component_writes);)*
+            }
+
+            fn resource_reads() -> Vec<tonks::ResourceId> {
+                let mut res = vec![];
+                #(res.append(&mut <#field_types as tonks::SystemData<'__data>>::resource_reads());)*
+                res
+            }
+
+            fn resource_writes() -> Vec<tonks::ResourceId> {
+                let mut res = vec![];
+                #(res.append(&mut <#field_types as tonks::SystemData<'__data>>::resource_writes());)*
+                res
+            }
+
+            fn component_reads() -> Vec<tonks::legion::storage::ComponentTypeId> {
+                let mut res = vec![];
+                #(res.append(&mut <#field_types as tonks::SystemData<'__data>>::component_reads());)*
+                res
+            }
+
+            fn component_writes() -> Vec<tonks::legion::storage::ComponentTypeId> {
+                let mut res = vec![];
+                #(res.append(&mut <#field_types as tonks::SystemData<'__data>>::component_writes());)*
+                res
+            }
+
+            fn before_execution(&'__data mut self) -> Self::Output {
+                let Self { #(#field_idents,)* } = self;
+                #output_ident {
+                    #(#field_idents: <#field_types as tonks::SystemData<'__data>>::before_execution(#field_idents),)*
+                }
+            }
+
+            fn after_execution(&mut self) {
+                let mut phases: [i32; #field_count] = [#(<#field_types as tonks::SystemData<'__data>>::flush_phase()),*];
+                phases.sort_unstable();
+
+                for (index, phase) in phases.iter().enumerate() {
+                    if index > 0 && phases[index - 1] == *phase {
+                        continue;
+                    }
+                    #(
+                        if <#field_types as tonks::SystemData<'__data>>::flush_phase() == *phase {
+                            <#field_types as tonks::SystemData<'__data>>::after_execution(&mut self.#field_idents);
+                        }
+                    )*
+                }
+            }
+        }
+
+        impl #data_impl_generics tonks::SystemDataOutput<'__data> for #output_ident #data_ty_generics #data_where_clause {
+            type SystemData = #ident #ty_generics;
+        }
+
+        impl #impl_generics tonks::MacroData for &'static #ident #ty_generics #macro_where_clause {
+            type SystemData = #ident #ty_generics;
+        }
+    };
+
+    result.into()
+}
+
 #[proc_macro_attribute]
 pub fn system(
-    _args: proc_macro::TokenStream,
+    args: proc_macro::TokenStream,
     input: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+    let args: AttributeArgs = parse_macro_input!(args as AttributeArgs);
     let input: ItemFn = parse_macro_input!(input as ItemFn);
 
+    let mut debug_print = false;
+    let mut unsafe_raw = false;
+    for arg in &args {
+        match arg {
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("debug_print") => debug_print = true,
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("unsafe_raw") => unsafe_raw = true,
+            _ => panic!("unknown system attribute argument; expected `debug_print` or `unsafe_raw`"),
+        }
+    }
+
     let visibility = input.vis;
 
     let sig = &input.sig;
@@ -43,30 +195,87 @@ pub fn system(
     );
 
     let (resource_idents, resource_types) = find_resource_accesses(&sig.inputs);
+    let (read_names, write_names) = find_access_names(&sig.inputs);
 
     let block = &*input.block;
     let ident = &sig.ident;
     let name = ident.to_string();
 
-    let register = if cfg!(feature = "system-registry") {
+    let suspected_writes = find_interior_writes(&sig.inputs, block);
+    let suspected_writes = if suspected_writes.is_empty() {
+        None
+    } else {
         Some(quote! {
-            tonks::inventory::submit!(tonks::SystemRegistration(tonks::parking_lot::Mutex::new(Some(Box::new(tonks::CachedSystem::new(#ident, #name))))));
+            fn suspected_writes(&self) -> Vec<tonks::ResourceId> {
+                vec![#(tonks::resource_id_for::<#suspected_writes>()),*]
+            }
+        })
+    };
+
+    // Prints each parameter after the system body runs in debug builds.
+    let debug_print = if debug_print {
+        let param_names = resource_idents.iter().map(|ident| ident.to_string());
+        Some(quote! {
+            if cfg!(debug_assertions) {
+                #(println!("[{}] {} = {}", #name, #param_names, tonks::MaybeDebug::maybe_debug(&#resource_idents));)*
+            }
         })
     } else {
         None
     };
 
-    let res = quote! {
-        #[allow(non_camel_case_types)]
-        #visibility struct #ident;
+    if unsafe_raw {
+        return raw_system(&visibility, ident, &name, &sig.inputs, &read_names, &write_names, block, debug_print, suspected_writes).into();
+    }
+
+    // With `debug_print`, the body runs in a closure so that
+    // an early `return` from it does not skip the output.
+    let body = match &debug_print {
+        Some(debug_print) => quote! {
+            #[allow(clippy::redundant_closure_call)]
+            (|| #block)();
+            #debug_print
+        },
+        None => quote! { #block },
+    };
 
+    // Spanned on the parameters, so that errors for parameters which cannot
+    // be passed to systems, such as `&mut` references to types which are not
+    // `Send + Sync`, point at them rather than at the attribute.
+    let system_impl = quote_spanned! {sig.inputs.span()=>
         impl tonks::System for #ident {
             type SystemData = (#(#resource_types ,)*);
 
             fn run(&mut self, (#(#resource_idents ,)*): <Self::SystemData as tonks::SystemData>::Output) {
-                #block
+                #body
             }
+
+            #suspected_writes
         }
+    };
+
+    let register = if cfg!(feature = "system-registry") {
+        Some(quote! {
+            tonks::inventory::submit!(tonks::SystemRegistration(tonks::parking_lot::Mutex::new(Some(Box::new(tonks::CachedSystem::new(#ident, #name))))));
+        })
+    } else {
+        None
+    };
+
+    let res = quote! {
+        #[allow(non_camel_case_types)]
+        #visibility struct #ident;
+
+        impl #ident {
+            /// Names of the types which this system accesses immutably,
+            /// as written in the parameters of the system function.
+            pub const READS: &'static [&'static str] = &[#(#read_names),*];
+            /// Names of the types which this system accesses mutably,
+            /// as written in the parameters of the system function.
+            pub const WRITES: &'static [&'static str] = &[#(#write_names),*];
+        }
+
+        #system_impl
 
         #register
     };
@@ -168,6 +377,157 @@ pub fn event_handler(
     res.into()
 }
 
+/// Generates a system which implements `RawSystem` directly,
+/// for `#[system(unsafe_raw)]`.
+///
+/// The system is not wrapped in a `CachedSystem` and has no `SystemData`:
+/// each parameter must be a reference to a resource, which is fetched from
+/// `Resources` without borrow checking in `execute_raw()`. Versions of the
+/// resources written are not incremented. It is constructed with `new()`.
+#[allow(clippy::too_many_arguments)]
+fn raw_system(
+    visibility: &syn::Visibility,
+    ident: &Ident,
+    name: &str,
+    inputs: &Punctuated<FnArg, Token![,]>,
+    read_names: &[String],
+    write_names: &[String],
+    block: &syn::Block,
+    debug_print: Option<TokenStream>,
+    suspected_writes: Option<TokenStream>,
+) -> TokenStream {
+    let mut fetches = vec![];
+    let mut args = vec![];
+    let mut reads = vec![];
+    let mut writes = vec![];
+
+    for arg in inputs {
+        let pat_ty = match arg {
+            FnArg::Typed(ty) => ty,
+            _ => panic!("system cannot take `self` parameter"),
+        };
+        let ident = match &*pat_ty.pat {
+            Pat::Ident(ident) => ident.ident.clone(),
+            _ => panic!("parameter pattern not an ident"),
+        };
+        let (ty, mutable) = match &*pat_ty.ty {
+            Type::Reference(r) => (&*r.elem, r.mutability.is_some()),
+            _ => panic!("`unsafe_raw` systems may only take resources by reference"),
+        };
+
+        if mutable {
+            fetches.push(quote! {
+                let #ident: &mut #ty = resources.get_mut_unchecked(tonks::resource_id_for::<#ty>());
+            });
+            args.push(quote! { &mut *#ident });
+            writes.push(ty);
+        } else {
+            fetches.push(quote! {
+                let #ident: &#ty = resources.get_unchecked(tonks::resource_id_for::<#ty>());
+            });
+            args.push(quote! { #ident });
+            reads.push(ty);
+        }
+    }
+
+    let register = if cfg!(feature = "system-registry") {
+        Some(quote! {
+            tonks::inventory::submit!(tonks::SystemRegistration(tonks::parking_lot::Mutex::new(Some(Box::new(#ident::new())))));
+        })
+    } else {
+        None
+    };
+
+    quote! {
+        #[allow(non_camel_case_types)]
+        #visibility struct #ident {
+            id: tonks::SystemId,
+            resource_reads: Vec<tonks::ResourceId>,
+            resource_writes: Vec<tonks::ResourceId>,
+        }
+
+        impl #ident {
+            /// Names of the types which this system accesses immutably,
+            /// as written in the parameters of the system function.
+            pub const READS: &'static [&'static str] = &[#(#read_names),*];
+            /// Names of the types which this system accesses mutably,
+            /// as written in the parameters of the system function.
+            pub const WRITES: &'static [&'static str] = &[#(#write_names),*];
+
+            /// Creates the system with a new `SystemId`.
+            pub fn new() -> Self {
+                Self {
+                    id: tonks::alloc_system_id(),
+                    resource_reads: vec![#(tonks::resource_id_for::<#reads>()),*],
+                    resource_writes: vec![#(tonks::resource_id_for::<#writes>()),*],
+                }
+            }
+        }
+
+        impl Default for #ident {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        #[allow(unsafe_code)]
+        impl tonks::RawSystem for #ident {
+            fn id(&self) -> tonks::SystemId {
+                self.id
+            }
+
+            fn name(&self) -> &'static str {
+                #name
+            }
+
+            fn resource_reads(&self) -> &[tonks::ResourceId] {
+                &self.resource_reads
+            }
+
+            fn resource_writes(&self) -> &[tonks::ResourceId] {
+                &self.resource_writes
+            }
+
+            fn component_reads(&self) -> &[tonks::legion::storage::ComponentTypeId] {
+                &[]
+            }
+
+            fn component_writes(&self) -> &[tonks::legion::storage::ComponentTypeId] {
+                &[]
+            }
+
+            fn init(&mut self, resources: &mut tonks::Resources, _ctx: tonks::SystemCtx, _world: &tonks::legion::world::World) {
+                #(
+                    if let Some(default) = <#reads as tonks::TryDefault>::try_default() {
+                        resources.insert_if_absent(default);
+                    }
+                )*
+                #(
+                    if let Some(default) = <#writes as tonks::TryDefault>::try_default() {
+                        resources.insert_if_absent(default);
+                    }
+                )*
+            }
+
+            unsafe fn execute_raw(&mut self, resources: &tonks::Resources, _ctx: tonks::SystemCtx, _world: &tonks::legion::world::World) {
+                fn run(#inputs) #block
+
+                #(#fetches)*
+                run(#(#args),*);
+                #debug_print
+            }
+
+            #suspected_writes
+
+            fn system_type_id(&self) -> Option<std::any::TypeId> {
+                Some(std::any::TypeId::of::<Self>())
+            }
+        }
+
+        #register
+    }
+}
+
 fn find_resource_accesses<'a>(inputs: impl IntoIterator<Item=&'a FnArg>) -> (Vec<Ident>, Vec<TokenStream>) {
     let mut resource_idents = vec![];
     let mut resource_types = vec![];
@@ -193,7 +553,17 @@ fn find_resource_accesses<'a>(inputs: impl IntoIterator<Item=&'a FnArg>) -> (Vec
                     <&'static #mutability #ty as tonks::MacroData>::SystemData
                 }
             },
-            _ty => panic!("only references may be passed to systems"),
+            // Other types, such as `Marker<T>`, are passed by value.
+            ty => quote! {
+                <#ty as tonks::MacroData>::SystemData
+            },
+        };
+
+        // `#[changed]` wraps the system data in `Changed<T>`.
+        let ty = if pat_ty.attrs.iter().any(|attr| attr.path.is_ident("changed")) {
+            quote! { tonks::Changed<#ty> }
+        } else {
+            ty
         };
 
         resource_idents.push(ident);
@@ -202,3 +572,115 @@ fn find_resource_accesses<'a>(inputs: impl IntoIterator<Item=&'a FnArg>) -> (Vec
 
     (resource_idents, resource_types)
 }
+
+/// Methods which commonly modify their receiver through a shared
+/// reference, using interior mutability.
+const INTERIOR_MUT_METHODS: &[&str] = &[
+    "set", "replace", "take", "borrow_mut", "lock", "try_lock", "write", "try_write",
+    "store", "swap", "fetch_add", "fetch_sub", "fetch_and", "fetch_or", "fetch_xor",
+    "fetch_update", "compare_exchange", "compare_and_swap", "push", "send",
+];
+
+/// Returns the types of immutably referenced parameters of a system function
+/// which the function body appears to modify through interior mutability.
+fn find_interior_writes<'a>(inputs: impl IntoIterator<Item=&'a FnArg>, block: &syn::Block) -> Vec<Type> {
+    let mut reads = vec![];
+    for arg in inputs.into_iter() {
+        if let FnArg::Typed(pat_ty) = arg {
+            if let (Pat::Ident(ident), Type::Reference(r)) = (&*pat_ty.pat, &*pat_ty.ty) {
+                if r.mutability.is_none() {
+                    reads.push((ident.ident.clone(), (*r.elem).clone()));
+                }
+            }
+        }
+    }
+
+    let mut finder = InteriorWrites { reads: &reads, found: vec![] };
+    finder.visit_block(block);
+
+    reads
+        .iter()
+        .filter(|(ident, _)| finder.found.contains(ident))
+        .map(|(_, ty)| ty.clone())
+        .collect()
+}
+
+struct InteriorWrites<'a> {
+    reads: &'a [(Ident, Type)],
+    found: Vec<Ident>,
+}
+
+impl<'a, 'ast> Visit<'ast> for InteriorWrites<'a> {
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        if INTERIOR_MUT_METHODS.contains(&call.method.to_string().as_str()) {
+            if let Some(root) = receiver_root(&call.receiver) {
+                if self.reads.iter().any(|(ident, _)| ident == root) && !self.found.contains(root) {
+                    self.found.push(root.clone());
+                }
+            }
+        }
+
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// Returns the variable at the root of a receiver
+/// expression such as `a.b.c`.
+fn receiver_root(expr: &Expr) -> Option<&Ident> {
+    match expr {
+        Expr::Path(path) => path.path.get_ident(),
+        Expr::Field(field) => receiver_root(&field.base),
+        Expr::Paren(paren) => receiver_root(&paren.expr),
+        Expr::Reference(reference) => receiver_root(&reference.expr),
+        _ => None,
+    }
+}
+
+/// Returns the names of the types referenced by the parameters of
+/// a system function, split into immutable and mutable references.
+fn find_access_names<'a>(inputs: impl IntoIterator<Item=&'a FnArg>) -> (Vec<String>, Vec<String>) {
+    let mut reads = vec![];
+    let mut writes = vec![];
+
+    for arg in inputs.into_iter() {
+        if let FnArg::Typed(pat_ty) = arg {
+            if let Type::Reference(r) = &*pat_ty.ty {
+                let ty = type_name(&r.elem);
+                if r.mutability.is_some() {
+                    writes.push(ty);
+                } else {
+                    reads.push(ty);
+                }
+            }
+        }
+    }
+
+    (reads, writes)
+}
+
+/// Formats a type as it would usually be written, e.g. `Trigger<Ev>`
+/// rather than the `Trigger < Ev >` produced by `TokenStream`'s `Display`.
+fn type_name(ty: &Type) -> String {
+    let tokens = quote!(#ty).to_string();
+    let chars: Vec<char> = tokens.chars().collect();
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '\'';
+
+    let mut name = String::with_capacity(tokens.len());
+    for (index, c) in chars.iter().copied().enumerate() {
+        if c == ' ' {
+            let prev = name.chars().last();
+            let next = chars.get(index + 1).copied();
+            let keep = match (prev, next) {
+                (Some(','), _) => true,
+                (Some(prev), Some(next)) => is_ident(prev) && is_ident(next),
+                _ => false,
+            };
+            if !keep {
+                continue;
+            }
+        }
+        name.push(c);
+    }
+
+    name
+}
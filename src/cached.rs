@@ -0,0 +1,239 @@
+//! Resources derived from other resources, recomputed
+//! only when the resources they depend on change.
+
+use crate::resources::Resource;
+use crate::{
+    resource_id_for, MacroData, ResourceId, Resources, SystemCtx, SystemData, SystemDataOutput,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Function which computes a cached resource from its dependencies,
+/// which are passed in the order they are declared.
+type ComputeFn<T> = Arc<dyn Fn(&[&dyn Resource]) -> T + Send + Sync>;
+
+/// Resource holding the function registered for the cached
+/// resource `T` through `SchedulerBuilder::with_cached_resource()`.
+pub(crate) struct CachedCompute<T>(ComputeFn<T>);
+
+/// A tuple of resources from which a `Cached` resource is computed.
+pub trait CacheDeps: 'static {
+    /// Returns the IDs of the resources, in declaration order.
+    fn resource_ids() -> Vec<ResourceId>;
+}
+
+/// A function which computes a resource of type `T` from
+/// references to the resources in `Deps`.
+///
+/// This is implemented for closures taking a reference
+/// to each resource in `Deps`.
+pub trait Compute<Deps: CacheDeps, T>: Send + Sync + 'static {
+    #[doc(hidden)]
+    fn into_compute_fn(self) -> Box<dyn Resource>;
+}
+
+macro_rules! impl_cache_deps {
+    ($($ty:ident),*; $($idx:tt),*) => {
+        impl<$($ty),*> CacheDeps for ($($ty,)*)
+        where
+            $($ty: Resource),*
+        {
+            fn resource_ids() -> Vec<ResourceId> {
+                vec![$(resource_id_for::<$ty>()),*]
+            }
+        }
+
+        impl<$($ty,)* T, F> Compute<($($ty,)*), T> for F
+        where
+            $($ty: Resource,)*
+            T: Resource,
+            F: Fn($(&$ty),*) -> T + Send + Sync + 'static,
+        {
+            fn into_compute_fn(self) -> Box<dyn Resource> {
+                let compute: ComputeFn<T> = Arc::new(move |deps: &[&dyn Resource]| {
+                    self($(deps[$idx].downcast_ref::<$ty>().unwrap()),*)
+                });
+                Box::new(CachedCompute(compute))
+            }
+        }
+    };
+}
+
+impl_cache_deps!(A; 0);
+impl_cache_deps!(A, B; 0, 1);
+impl_cache_deps!(A, B, C; 0, 1, 2);
+impl_cache_deps!(A, B, C, D; 0, 1, 2, 3);
+
+/// Returns the ID of the resource holding the function
+/// which computes the cached resource `T`.
+pub(crate) fn compute_id_for<T: Resource>() -> ResourceId {
+    resource_id_for::<CachedCompute<T>>()
+}
+
+/// Specifies a read requirement for a resource `T` which is derived
+/// from the resources `Deps`, such as a spatial index built from positions.
+///
+/// The function which computes `T` is registered through
+/// `SchedulerBuilder::with_cached_resource()`. Before each run of the
+/// system, `T` is recomputed if any resource in `Deps` has changed
+/// since it was last computed, as determined by the resources' versions.
+/// It is always computed on the first run.
+///
+/// This declares reads of `Deps` and a write of `T`.
+pub struct Cached<T, Deps>
+where
+    T: Resource,
+{
+    ptr: *mut T,
+    version: *const AtomicU64,
+    /// Pointers to the dependencies and their version counters.
+    deps: Vec<(*const dyn Resource, *const AtomicU64)>,
+    /// Versions of the dependencies from which the value was
+    /// last computed, or `None` if it has not been computed.
+    computed_from: Option<Vec<u64>>,
+    compute: ComputeFn<T>,
+    _deps: PhantomData<fn() -> Deps>,
+}
+
+impl<T, Deps> Cached<T, Deps>
+where
+    T: Resource,
+{
+    fn dep_versions(&self) -> Vec<u64> {
+        self.deps
+            .iter()
+            .map(|(_, version)| unsafe { &**version }.load(Ordering::Acquire))
+            .collect()
+    }
+
+    fn dep_refs(&self) -> Vec<&dyn Resource> {
+        self.deps.iter().map(|(dep, _)| unsafe { &**dep }).collect()
+    }
+
+    fn recompute_if_changed(&mut self) {
+        let versions = self.dep_versions();
+        if self.computed_from.as_ref() == Some(&versions) {
+            return;
+        }
+
+        let value = (self.compute)(&self.dep_refs());
+        unsafe {
+            *self.ptr = value;
+            (&*self.version).fetch_add(1, Ordering::AcqRel);
+        }
+
+        self.computed_from = Some(versions);
+    }
+}
+
+impl<T, Deps> Deref for Cached<T, Deps>
+where
+    T: Resource,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + Resource, Deps> Send for Cached<T, Deps> {}
+unsafe impl<T: Send + Sync + Resource, Deps> Sync for Cached<T, Deps> {}
+
+impl<'a, T, Deps> SystemData<'a> for Cached<T, Deps>
+where
+    T: Resource,
+    Deps: CacheDeps,
+{
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        let compute = if resources.contains_id(compute_id_for::<T>()) {
+            Arc::clone(&resources.get::<CachedCompute<T>>().0)
+        } else {
+            panic!(
+                "no function registered to compute cached resource {}",
+                std::any::type_name::<T>()
+            )
+        };
+
+        let deps: Vec<_> = Deps::resource_ids()
+            .into_iter()
+            .map(|id| {
+                (
+                    resources.get_dyn_unchecked(id) as *const dyn Resource,
+                    resources.version_counter(id) as *const AtomicU64,
+                )
+            })
+            .collect();
+
+        let mut cached = Self {
+            ptr: std::ptr::null_mut(),
+            version: std::ptr::null(),
+            deps,
+            computed_from: None,
+            compute,
+            _deps: PhantomData,
+        };
+
+        if !resources.contains::<T>() {
+            // Compute the first value now, since there is
+            // no existing value to point to.
+            let value = (cached.compute)(&cached.dep_refs());
+            cached.computed_from = Some(cached.dep_versions());
+            resources.insert(value);
+        }
+
+        let id = resource_id_for::<T>();
+        cached.ptr = resources.get_mut_unchecked(id) as *mut T;
+        cached.version = resources.version_counter(id) as *const _;
+
+        cached
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        Deps::resource_ids()
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![resource_id_for::<T>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.recompute_if_changed();
+        self
+    }
+}
+
+impl<'a, T, Deps> SystemDataOutput<'a> for &'a Cached<T, Deps>
+where
+    T: Resource,
+    Deps: CacheDeps,
+{
+    type SystemData = Cached<T, Deps>;
+}
+
+impl<T, Deps> MacroData for &'static Cached<T, Deps>
+where
+    T: Resource,
+    Deps: CacheDeps,
+{
+    type SystemData = Cached<T, Deps>;
+}
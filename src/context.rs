@@ -0,0 +1,375 @@
+//! Per-dispatch metadata maintained by the scheduler.
+
+use crate::{
+    resource_id_for, MacroData, ResourceId, Resources, SystemCtx, SystemData, SystemDataOutput,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use parking_lot::Mutex;
+use std::any::{type_name, TypeId};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Frame number and delta time of the current dispatch,
+/// shared between a scheduler and its `SystemCtx`s.
+#[derive(Default)]
+pub(crate) struct FrameClock {
+    /// Number of the next dispatch.
+    next_frame: AtomicU64,
+    /// Delta time in nanoseconds.
+    delta: AtomicU64,
+    /// Start time of the previous dispatch.
+    last_start: Mutex<Option<Instant>>,
+}
+
+impl FrameClock {
+    /// Advances the clock to a dispatch starting at `start`.
+    pub(crate) fn begin(&self, start: Instant) {
+        let mut last_start = self.last_start.lock();
+        let delta = match *last_start {
+            Some(last) => start - last,
+            None => Duration::from_secs(0),
+        };
+        self.next_frame.fetch_add(1, Ordering::AcqRel);
+        self.delta.store(delta.as_nanos() as u64, Ordering::Release);
+        *last_start = Some(start);
+    }
+
+    /// Returns the number of the current or last dispatch.
+    pub(crate) fn frame(&self) -> u64 {
+        self.next_frame.load(Ordering::Acquire).saturating_sub(1)
+    }
+
+    /// Returns the number of the next dispatch.
+    pub(crate) fn next_frame(&self) -> u64 {
+        self.next_frame.load(Ordering::Acquire)
+    }
+
+    /// Sets the number of the next dispatch.
+    pub(crate) fn restore(&self, next_frame: u64) {
+        self.next_frame.store(next_frame, Ordering::Release);
+    }
+}
+
+/// Stats of the last completed dispatch, shared
+/// between a scheduler and its `SystemCtx`s.
+#[derive(Default)]
+pub(crate) struct LastDispatch {
+    contentions: AtomicU64,
+    /// Duration in nanoseconds.
+    duration: AtomicU64,
+    /// Bits of the `f64` parallelism.
+    parallelism: AtomicU64,
+}
+
+impl LastDispatch {
+    /// Records the stats of a dispatch which has completed.
+    pub(crate) fn record(&self, contentions: u64, duration: Duration, parallelism: f64) {
+        self.contentions.store(contentions, Ordering::Release);
+        self.duration
+            .store(duration.as_nanos() as u64, Ordering::Release);
+        self.parallelism
+            .store(parallelism.to_bits(), Ordering::Release);
+    }
+}
+
+/// Context passed to `Scheduler::execute_with_context()`, shared
+/// between a scheduler and its `SystemCtx`s.
+#[derive(Default)]
+pub(crate) struct FrameContextSlot {
+    current: Mutex<Option<ContextPtr>>,
+}
+
+/// Pointer to the context of the current dispatch, along with its type.
+struct ContextPtr(TypeId, *const ());
+
+// Safety: the context is `Send + Sync` and only
+// accessed while the dispatch which set it runs.
+unsafe impl Send for ContextPtr {}
+
+impl FrameContextSlot {
+    /// Makes `context` available to systems until the returned guard is dropped.
+    pub(crate) fn set<C: Send + Sync + 'static>(
+        self: &Arc<Self>,
+        context: &C,
+    ) -> FrameContextGuard {
+        *self.current.lock() = Some(ContextPtr(
+            TypeId::of::<C>(),
+            context as *const C as *const (),
+        ));
+        FrameContextGuard(Arc::clone(self))
+    }
+
+    /// Returns a pointer to the current context if it has type `C`, or null.
+    fn get<C: 'static>(&self) -> *const C {
+        match &*self.current.lock() {
+            Some(ContextPtr(type_id, ptr)) if *type_id == TypeId::of::<C>() => *ptr as *const C,
+            _ => ptr::null(),
+        }
+    }
+}
+
+/// Clears the context of a `FrameContextSlot` when dropped,
+/// including when the dispatch panics.
+pub(crate) struct FrameContextGuard(Arc<FrameContextSlot>);
+
+impl Drop for FrameContextGuard {
+    fn drop(&mut self) {
+        *self.0.current.lock() = None;
+    }
+}
+
+/// Bundles the metadata most systems need about the current dispatch
+/// into a single argument.
+///
+/// The frame number is 0 during the first dispatch and is incremented
+/// by each following dispatch, including warmup dispatches. The delta
+/// time is the time between the starts of the previous dispatch and
+/// the current one, and is zero during the first dispatch.
+///
+/// This declares no resource or component accesses, so it never
+/// causes conflicts.
+pub struct Context {
+    clock: Arc<FrameClock>,
+    frame: u64,
+    delta: Duration,
+}
+
+impl Context {
+    /// Returns the number of the current dispatch.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Returns the time elapsed since the start of the previous dispatch.
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+}
+
+impl<'a> SystemData<'a> for Context {
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        _resources: &mut Resources,
+        ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        Self {
+            clock: Arc::clone(&ctx.frame_clock),
+            frame: 0,
+            delta: Duration::from_secs(0),
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.frame = self.clock.frame();
+        self.delta = Duration::from_nanos(self.clock.delta.load(Ordering::Acquire));
+        self
+    }
+}
+
+impl<'a> SystemDataOutput<'a> for &'a Context {
+    type SystemData = Context;
+}
+
+impl MacroData for &'static Context {
+    type SystemData = Context;
+}
+
+/// Provides stats of the previous dispatch of the scheduler running
+/// the system, such as for systems which reduce their work while
+/// the scheduler is contention-bound.
+///
+/// All stats are zero during the first dispatch. Warmup dispatches
+/// count as previous dispatches.
+///
+/// This declares no resource or component accesses, so it never
+/// causes conflicts.
+pub struct SchedulerStats {
+    last: Arc<LastDispatch>,
+    contentions: u64,
+    duration: Duration,
+    parallelism: f64,
+}
+
+impl SchedulerStats {
+    /// Returns the number of times a task could not be started during
+    /// the previous dispatch because another running task held a
+    /// conflicting access to a resource.
+    pub fn contentions(&self) -> u64 {
+        self.contentions
+    }
+
+    /// Returns the time the previous dispatch took.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the parallelism of the previous dispatch,
+    /// as returned by `Scheduler::measure_parallelism()`.
+    pub fn parallelism(&self) -> f64 {
+        self.parallelism
+    }
+}
+
+impl<'a> SystemData<'a> for SchedulerStats {
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        _resources: &mut Resources,
+        ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        Self {
+            last: Arc::clone(&ctx.last_dispatch),
+            contentions: 0,
+            duration: Duration::from_secs(0),
+            parallelism: 0.0,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.contentions = self.last.contentions.load(Ordering::Acquire);
+        self.duration = Duration::from_nanos(self.last.duration.load(Ordering::Acquire));
+        self.parallelism = f64::from_bits(self.last.parallelism.load(Ordering::Acquire));
+        self
+    }
+}
+
+impl<'a> SystemDataOutput<'a> for &'a SchedulerStats {
+    type SystemData = SchedulerStats;
+}
+
+impl MacroData for &'static SchedulerStats {
+    type SystemData = SchedulerStats;
+}
+
+/// Resource standing in for the context of type `C` in the accesses of systems.
+struct FrameContextMarker<C>(PhantomData<fn() -> C>);
+
+/// Provides the context of type `C` passed to `Scheduler::execute_with_context()`
+/// for the current dispatch, such as input events or a frame token.
+///
+/// Unlike a resource, the context only exists during the dispatch it was
+/// passed to. Dereferencing panics if the dispatch was started without a
+/// context of type `C`; use `get()` to handle that case.
+///
+/// The context is immutable, so this declares a read of a resource
+/// which no system writes, and never causes conflicts.
+pub struct FrameContext<C: Send + Sync + 'static> {
+    slot: Arc<FrameContextSlot>,
+    ptr: *const C,
+}
+
+impl<C: Send + Sync + 'static> FrameContext<C> {
+    /// Returns the context, or `None` if the current
+    /// dispatch was started without a context of type `C`.
+    pub fn get(&self) -> Option<&C> {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<C: Send + Sync + 'static> Deref for FrameContext<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        self.get().unwrap_or_else(|| {
+            panic!(
+                "no frame context of type {} was passed to this dispatch",
+                type_name::<C>()
+            )
+        })
+    }
+}
+
+// Safety: the pointer is only dereferenced during the
+// dispatch which set it, and `C` is `Send + Sync`.
+unsafe impl<C: Send + Sync + 'static> Send for FrameContext<C> {}
+unsafe impl<C: Send + Sync + 'static> Sync for FrameContext<C> {}
+
+impl<'a, C: Send + Sync + 'static> SystemData<'a> for FrameContext<C> {
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        resources.insert_if_absent(FrameContextMarker::<C>(PhantomData));
+
+        Self {
+            slot: Arc::clone(&ctx.frame_context),
+            ptr: ptr::null(),
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![resource_id_for::<FrameContextMarker<C>>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.ptr = self.slot.get::<C>();
+        self
+    }
+
+    fn after_execution(&mut self) {
+        self.ptr = ptr::null();
+    }
+}
+
+impl<'a, C: Send + Sync + 'static> SystemDataOutput<'a> for &'a FrameContext<C> {
+    type SystemData = FrameContext<C>;
+}
+
+impl<C: Send + Sync + 'static> MacroData for &'static FrameContext<C> {
+    type SystemData = FrameContext<C>;
+}
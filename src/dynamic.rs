@@ -0,0 +1,100 @@
+//! Reads of resources which are selected at runtime
+//! from a set of candidates declared up front.
+
+use crate::resources::Resource;
+use crate::{
+    resource_id_for, CacheDeps, MacroData, ResourceId, Resources, SystemCtx, SystemData,
+    SystemDataOutput,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::marker::PhantomData;
+
+/// Specifies a read requirement for one of the resources in `C`,
+/// a tuple of candidate resource types, which is selected at runtime,
+/// e.g. through a handle stored in another resource.
+///
+/// For scheduling purposes, this is treated as a `Read<T>` of every
+/// candidate, so it is safe to access any of them regardless of which
+/// is selected. Candidates which do not exist are not accessible.
+// Safety: this contains raw pointers which must remain valid.
+pub struct DynamicRead<C: CacheDeps> {
+    candidates: Vec<(ResourceId, *const dyn Resource)>,
+    _candidates: PhantomData<fn() -> C>,
+}
+
+impl<C: CacheDeps> DynamicRead<C> {
+    /// Returns the candidate resource with the given ID, or `None`
+    /// if it does not exist or is not one of the candidates.
+    pub fn get_dyn(&self, id: ResourceId) -> Option<&dyn Resource> {
+        self.candidates
+            .iter()
+            .find(|(candidate, _)| *candidate == id)
+            .map(|(_, ptr)| unsafe { &**ptr })
+    }
+
+    /// Returns the resource of type `T`, or `None` if it
+    /// does not exist or is not one of the candidates.
+    pub fn get<T: Resource>(&self) -> Option<&T> {
+        self.get_dyn(resource_id_for::<T>())
+            .and_then(|resource| resource.downcast_ref())
+    }
+
+    /// Returns whether `id` is the ID of one of the candidates.
+    pub fn is_candidate(id: ResourceId) -> bool {
+        C::resource_ids().contains(&id)
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<C: CacheDeps> Send for DynamicRead<C> {}
+unsafe impl<C: CacheDeps> Sync for DynamicRead<C> {}
+
+impl<'a, C: CacheDeps> SystemData<'a> for DynamicRead<C> {
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        let candidates = C::resource_ids()
+            .into_iter()
+            .filter(|id| resources.contains_id(*id))
+            .map(|id| (id, resources.get_dyn_unchecked(id) as *const dyn Resource))
+            .collect();
+
+        Self {
+            candidates,
+            _candidates: PhantomData,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        C::resource_ids()
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, C: CacheDeps> SystemDataOutput<'a> for &'a DynamicRead<C> {
+    type SystemData = DynamicRead<C>;
+}
+
+impl<C: CacheDeps> MacroData for &'static DynamicRead<C> {
+    type SystemData = DynamicRead<C>;
+}
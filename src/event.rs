@@ -1,7 +1,10 @@
 use crate::mappings::Mappings;
-use crate::scheduler::TaskMessage;
+use crate::scheduler::{visit_events, Priority, TaskMessage};
 use crate::system::{SystemCtx, SystemDataOutput, SYSTEM_ID_MAPPINGS};
-use crate::{resource_id_for_component, MacroData, ResourceId, Resources, SystemData, SystemId};
+use crate::{
+    resource_id_for, resource_id_for_component, MacroData, ResourceId, Resources, SystemData,
+    SystemId,
+};
 use hashbrown::HashSet;
 use lazy_static::lazy_static;
 use legion::storage::ComponentTypeId;
@@ -10,6 +13,7 @@ use parking_lot::Mutex;
 use std::alloc::Layout;
 use std::any::TypeId;
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 /// ID of an event type, allocated consecutively.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
@@ -182,7 +186,7 @@ where
         );
 
         Self {
-            id: SYSTEM_ID_MAPPINGS.lock().alloc(),
+            id: SYSTEM_ID_MAPPINGS.lock().alloc_named(name),
             event_id: event_id_for::<E>(),
             resource_reads,
             resource_writes,
@@ -252,7 +256,53 @@ where
     }
 }
 
+/// A bump-allocated slice of events.
+struct EventSlice<E> {
+    ptr: *const E,
+    len: usize,
+}
+
+// Safety: the events are never mutated or freed once allocated.
+unsafe impl<E: Event> Send for EventSlice<E> {}
+unsafe impl<E: Event> Sync for EventSlice<E> {}
+
+/// Resource containing the events of type `E` triggered through
+/// `Trigger<E>` which have not yet been received by an `EventBatch<E>`.
+///
+/// `Trigger<E>` declares a read of this resource, and `EventBatch<E>`
+/// a write, so that a batch never runs concurrently with systems which
+/// may add to it. Events are only recorded once an `EventBatch<E>` has
+/// been initialized, so that the log does not grow without a consumer.
+struct EventLog<E: Event> {
+    /// Whether any system receives the events through an `EventBatch<E>`.
+    consumed: AtomicBool,
+    slices: Mutex<Vec<EventSlice<E>>>,
+}
+
+impl<E: Event> Default for EventLog<E> {
+    fn default() -> Self {
+        Self {
+            consumed: AtomicBool::new(false),
+            slices: Mutex::new(vec![]),
+        }
+    }
+}
+
 /// System data which allows you to trigger events of a given type.
+///
+/// Events are queued while the system runs and sent to the scheduler
+/// once it completes, so handlers never observe an event before the
+/// system which triggered it has finished. Within a single dispatch:
+/// * Events triggered by one run of a system are delivered to handlers
+///   and `EventBatch<E>` in the order they were triggered.
+/// * Events from different systems, or different runs of a oneshot
+///   system, are delivered in the order the systems completed, which
+///   is only deterministic under `Scheduler::execute_canonical()`.
+/// * All events triggered by systems during a dispatch are handled
+///   by end-of-tick handlers before the dispatch returns.
+///
+/// This declares a read of the event log of `E`, so emitters run
+/// concurrently with each other but never with an `EventBatch<E>`.
 pub struct Trigger<E>
 where
     E: Event,
@@ -260,8 +310,13 @@ where
     ctx: SystemCtx,
     queued: Vec<E>,
     id: EventId,
+    log: *const EventLog<E>,
 }
 
+// Safety: the event log is only accessed through its mutex.
+unsafe impl<E: Event> Send for Trigger<E> {}
+unsafe impl<E: Event> Sync for Trigger<E> {}
+
 impl<'a, E> SystemData<'a> for Trigger<E>
 where
     E: Event,
@@ -269,19 +324,22 @@ where
     type Output = &'a mut Self;
 
     unsafe fn load_from_resources(
-        _resources: &mut Resources,
+        resources: &mut Resources,
         ctx: SystemCtx,
         _world: &World,
     ) -> Self {
+        resources.insert_if_absent(EventLog::<E>::default());
+
         Self {
             ctx,
             queued: vec![],
             id: event_id_for::<E>(),
+            log: resources.get_unchecked(resource_id_for::<EventLog<E>>()) as *const _,
         }
     }
 
     fn resource_reads() -> Vec<ResourceId> {
-        vec![]
+        vec![resource_id_for::<EventLog<E>>()]
     }
 
     fn resource_writes() -> Vec<ResourceId> {
@@ -325,13 +383,22 @@ where
                 ptr::write(ptr.offset(index as isize), event);
             });
 
+        let log = unsafe { &*self.log };
+        if log.consumed.load(Ordering::Acquire) {
+            log.slices.lock().push(EventSlice { ptr, len });
+        }
+
         self.ctx
             .sender
-            .send(TaskMessage::TriggerEvents {
-                id: self.id,
-                ptr: ptr as *const (),
-                len,
-            })
+            .send(
+                TaskMessage::TriggerEvents {
+                    id: self.id,
+                    ptr: ptr as *const (),
+                    len,
+                    visit: visit_events::<E>,
+                },
+                Priority::Normal,
+            )
             .unwrap();
     }
 }
@@ -340,13 +407,26 @@ impl<E> Trigger<E>
 where
     E: Send + Sync + 'static,
 {
+    /// Triggers an event, which is sent once the system completes.
     pub fn trigger(&mut self, event: E) {
         self.queued.push(event);
     }
 
+    /// Triggers each of the given events in order.
     pub fn trigger_batched(&mut self, events: impl IntoIterator<Item = E>) {
         self.queued.extend(events);
     }
+
+    /// Emits an event. This is the same as `trigger()`.
+    pub fn emit(&mut self, event: E) {
+        self.trigger(event);
+    }
+
+    /// Emits each of the given events in order.
+    /// This is the same as `trigger_batched()`.
+    pub fn emit_batch(&mut self, events: impl IntoIterator<Item = E>) {
+        self.trigger_batched(events);
+    }
 }
 
 impl<'a, E> SystemDataOutput<'a> for &'a mut Trigger<E>
@@ -363,6 +443,129 @@ where
     type SystemData = Trigger<E>;
 }
 
+/// System data which receives, as a single batch, all events of type `E`
+/// triggered through `Trigger<E>` since the previous run of any `EventBatch<E>`.
+///
+/// Since `EventBatch<E>` conflicts with `Trigger<E>`, a system receiving
+/// the batch runs in a later stage than the systems triggering the events,
+/// and observes all events they triggered at once. This is useful when
+/// processing events together is cheaper than handling them one by one.
+///
+/// Events are still passed to any event handlers. Note that the batch is
+/// drained by the system which receives it, so if multiple systems use
+/// `EventBatch<E>`, each event is only received by one of them. A system
+/// cannot use both `Trigger<E>` and `EventBatch<E>`.
+pub struct EventBatch<E>
+where
+    E: Event,
+{
+    log: *const EventLog<E>,
+    events: Vec<*const E>,
+}
+
+// Safety: the events are never mutated or freed once allocated,
+// and the event log is only accessed through its mutex.
+unsafe impl<E: Event> Send for EventBatch<E> {}
+unsafe impl<E: Event> Sync for EventBatch<E> {}
+
+impl<'a, E> SystemData<'a> for EventBatch<E>
+where
+    E: Event,
+{
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        resources.insert_if_absent(EventLog::<E>::default());
+
+        let log = resources.get_unchecked::<EventLog<E>>(resource_id_for::<EventLog<E>>());
+        log.consumed.store(true, Ordering::Release);
+
+        Self {
+            log: log as *const _,
+            events: vec![],
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![resource_id_for::<EventLog<E>>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        let mut slices = unsafe { &*self.log }.slices.lock();
+
+        self.events.clear();
+        for slice in slices.drain(..) {
+            self.events
+                .extend((0..slice.len).map(|index| unsafe { slice.ptr.add(index) }));
+        }
+
+        self
+    }
+
+    fn after_execution(&mut self) {
+        self.events.clear();
+    }
+}
+
+impl<E> EventBatch<E>
+where
+    E: Event,
+{
+    /// Returns an iterator over the events in this batch,
+    /// in the order they were received by the scheduler.
+    pub fn iter(&self) -> impl Iterator<Item = &E> {
+        self.events.iter().map(|event| unsafe { &**event })
+    }
+
+    /// Returns the number of events in this batch.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Returns whether this batch contains no events.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Collects the events in this batch into a vector.
+    pub fn to_vec(&self) -> Vec<E>
+    where
+        E: Clone,
+    {
+        self.iter().cloned().collect()
+    }
+}
+
+impl<'a, E> SystemDataOutput<'a> for &'a EventBatch<E>
+where
+    E: Event,
+{
+    type SystemData = EventBatch<E>;
+}
+
+impl<E> MacroData for &'static EventBatch<E>
+where
+    E: Event,
+{
+    type SystemData = EventBatch<E>;
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,252 @@
+//! Queues of events which systems read through a cursor,
+//! as an alternative to event handlers.
+
+use crate::context::FrameClock;
+use crate::resources::Resource;
+use crate::{
+    resource_id_for, MacroData, ResourceId, Resources, SystemCtx, SystemData, SystemDataOutput,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Determines when events sent through `EventWriter<E>`
+/// are dropped, whether or not they have been read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventRetention {
+    /// Events are dropped once they were sent more than the given
+    /// number of dispatches ago. With the default of 2, events sent
+    /// during a dispatch can be read during that dispatch and the next.
+    Dispatches(u64),
+    /// The oldest events are dropped once the
+    /// queue holds more than the given number.
+    Capacity(usize),
+}
+
+impl Default for EventRetention {
+    fn default() -> Self {
+        EventRetention::Dispatches(2)
+    }
+}
+
+/// Resource holding the events of type `E` sent through `EventWriter<E>`.
+pub(crate) struct EventQueue<E: Resource> {
+    /// Events along with the frame in which they were sent, oldest first.
+    events: VecDeque<(u64, E)>,
+    /// Index of the first event in `events` among all events ever sent.
+    start: u64,
+    retention: EventRetention,
+}
+
+impl<E: Resource> EventQueue<E> {
+    pub(crate) fn new(retention: EventRetention) -> Self {
+        Self {
+            events: VecDeque::new(),
+            start: 0,
+            retention,
+        }
+    }
+
+    /// Drops the events which are no longer retained in `frame`.
+    fn prune(&mut self, frame: u64) {
+        let excess = match self.retention {
+            EventRetention::Dispatches(dispatches) => self
+                .events
+                .iter()
+                .take_while(|(sent, _)| sent + dispatches <= frame)
+                .count(),
+            EventRetention::Capacity(capacity) => self.events.len().saturating_sub(capacity),
+        };
+
+        self.events.drain(..excess);
+        self.start += excess as u64;
+    }
+
+    /// Returns the index after the last event sent.
+    fn end(&self) -> u64 {
+        self.start + self.events.len() as u64
+    }
+}
+
+/// Specifies a write requirement for the queue of events of type `E`,
+/// allowing the system to send events to all `EventReader<E>`s.
+///
+/// The queue is a resource, so the scheduler orders writers and readers
+/// like any other accesses: a reader added after a writer runs in a later
+/// stage and sees the events sent in the same dispatch. Events are dropped
+/// according to the `EventRetention` set through
+/// `SchedulerBuilder::with_event_retention()`, whether or not they have
+/// been read, so the queue does not grow without bound.
+// Safety: this contains a raw pointer which must remain valid.
+pub struct EventWriter<E: Resource> {
+    queue: *mut EventQueue<E>,
+    clock: Arc<FrameClock>,
+    frame: u64,
+}
+
+impl<E: Resource> EventWriter<E> {
+    /// Sends an event.
+    pub fn send(&mut self, event: E) {
+        let frame = self.frame;
+        self.queue_mut().events.push_back((frame, event));
+    }
+
+    /// Sends each of the given events in order.
+    pub fn send_batch(&mut self, events: impl IntoIterator<Item = E>) {
+        let frame = self.frame;
+        self.queue_mut()
+            .events
+            .extend(events.into_iter().map(|event| (frame, event)));
+    }
+
+    fn queue_mut(&mut self) -> &mut EventQueue<E> {
+        unsafe { &mut *self.queue }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<E: Resource> Send for EventWriter<E> {}
+unsafe impl<E: Resource> Sync for EventWriter<E> {}
+
+impl<'a, E: Resource> SystemData<'a> for EventWriter<E> {
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        resources.insert_if_absent(EventQueue::<E>::new(EventRetention::default()));
+
+        Self {
+            queue: resources.get_mut_unchecked(resource_id_for::<EventQueue<E>>())
+                as *mut EventQueue<E>,
+            clock: Arc::clone(&ctx.frame_clock),
+            frame: 0,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![resource_id_for::<EventQueue<E>>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.frame = self.clock.frame();
+        let frame = self.frame;
+        self.queue_mut().prune(frame);
+        self
+    }
+
+    fn after_execution(&mut self) {
+        // Keeps the queue within its capacity between runs.
+        let frame = self.frame;
+        self.queue_mut().prune(frame);
+    }
+}
+
+impl<'a, E: Resource> SystemDataOutput<'a> for &'a mut EventWriter<E> {
+    type SystemData = EventWriter<E>;
+}
+
+impl<E: Resource> MacroData for &'static mut EventWriter<E> {
+    type SystemData = EventWriter<E>;
+}
+
+/// Specifies a read requirement for the queue of events of type `E`,
+/// allowing the system to receive events sent through `EventWriter<E>`.
+///
+/// Each reader keeps its own cursor, so every reader sees every event
+/// which is still retained, and each event only once. Events which were
+/// dropped before a reader ran are never seen by it.
+// Safety: this contains a raw pointer which must remain valid.
+pub struct EventReader<E: Resource> {
+    queue: *const EventQueue<E>,
+    /// Index of the first event which this reader has not yet seen.
+    cursor: u64,
+}
+
+impl<E: Resource> EventReader<E> {
+    /// Returns an iterator over the events which this reader has not
+    /// yet seen, oldest first, marking them as seen.
+    pub fn iter(&mut self) -> impl Iterator<Item = &E> {
+        let queue = unsafe { &*self.queue };
+        let skip = self.cursor.saturating_sub(queue.start) as usize;
+        self.cursor = queue.end();
+
+        queue.events.iter().skip(skip).map(|(_, event)| event)
+    }
+
+    /// Returns the number of events which this reader has not yet seen.
+    pub fn len(&self) -> usize {
+        let queue = unsafe { &*self.queue };
+        (queue.end() - self.cursor.max(queue.start)) as usize
+    }
+
+    /// Returns whether there are no events which this reader has not yet seen.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<E: Resource> Send for EventReader<E> {}
+unsafe impl<E: Resource> Sync for EventReader<E> {}
+
+impl<'a, E: Resource> SystemData<'a> for EventReader<E> {
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        resources.insert_if_absent(EventQueue::<E>::new(EventRetention::default()));
+
+        Self {
+            queue: resources.get_unchecked(resource_id_for::<EventQueue<E>>())
+                as *const EventQueue<E>,
+            cursor: 0,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![resource_id_for::<EventQueue<E>>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, E: Resource> SystemDataOutput<'a> for &'a mut EventReader<E> {
+    type SystemData = EventReader<E>;
+}
+
+impl<E: Resource> MacroData for &'static mut EventReader<E> {
+    type SystemData = EventReader<E>;
+}
@@ -0,0 +1,214 @@
+//! Coarse access to groups of resources, such as all
+//! resources registered by a plugin.
+
+use crate::resources::Resource;
+use crate::{
+    resource_id_for, MacroData, ResourceId, Resources, SystemCtx, SystemData, SystemDataOutput,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Resource standing in for the group `G` in the accesses of systems.
+///
+/// When a scheduler is built, accesses of this resource are
+/// extended with the resources tagged into the group.
+struct GroupMarker<G>(PhantomData<fn() -> G>);
+
+/// Returns the ID of the resource standing in for the group `G`.
+pub(crate) fn group_id_for<G: 'static>() -> ResourceId {
+    resource_id_for::<GroupMarker<G>>()
+}
+
+/// Loads pointers to the resources tagged into the group `G`
+/// which exist, along with their version counters.
+unsafe fn load_members<G: 'static>(
+    resources: &mut Resources,
+) -> Vec<(ResourceId, *mut dyn Resource, *const AtomicU64)> {
+    resources.insert_if_absent(GroupMarker::<G>(PhantomData));
+
+    let group = group_id_for::<G>();
+    resources
+        .group_members(group)
+        .iter()
+        .copied()
+        .filter(|id| resources.contains_id(*id))
+        .map(|id| {
+            (
+                id,
+                resources.get_dyn_mut_unchecked(id) as *mut dyn Resource,
+                resources.version_counter(id) as *const AtomicU64,
+            )
+        })
+        .collect()
+}
+
+/// Specifies a read requirement for all resources tagged into
+/// the group `G` through `Resources::tag()`.
+///
+/// The resources in the group need not be known when the system is
+/// written. For scheduling purposes, this is treated as a `Read<T>` of
+/// each resource tagged into the group when the scheduler is built.
+// Safety: this contains raw pointers which must remain valid.
+pub struct ReadGroup<G: 'static> {
+    members: Vec<(ResourceId, *mut dyn Resource, *const AtomicU64)>,
+    _group: PhantomData<fn() -> G>,
+}
+
+impl<G: 'static> ReadGroup<G> {
+    /// Returns the resource of type `T`, or `None` if it
+    /// does not exist or is not tagged into the group.
+    pub fn get<T: Resource>(&self) -> Option<&T> {
+        let id = resource_id_for::<T>();
+        self.members
+            .iter()
+            .find(|(member, _, _)| *member == id)
+            .and_then(|(_, ptr, _)| unsafe { &**ptr }.downcast_ref())
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<G: 'static> Send for ReadGroup<G> {}
+unsafe impl<G: 'static> Sync for ReadGroup<G> {}
+
+impl<'a, G: 'static> SystemData<'a> for ReadGroup<G> {
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        Self {
+            members: load_members::<G>(resources),
+            _group: PhantomData,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![group_id_for::<G>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, G: 'static> SystemDataOutput<'a> for &'a ReadGroup<G> {
+    type SystemData = ReadGroup<G>;
+}
+
+impl<G: 'static> MacroData for &'static ReadGroup<G> {
+    type SystemData = ReadGroup<G>;
+}
+
+/// Specifies a write requirement for all resources tagged into
+/// the group `G` through `Resources::tag()`.
+///
+/// For scheduling purposes, this is treated as a `Write<T>` of
+/// each resource tagged into the group when the scheduler is built.
+/// Like `Write<T>`, the version of each resource is incremented after
+/// each run in which it was mutably accessed.
+// Safety: this contains raw pointers which must remain valid.
+pub struct WriteGroup<G: 'static> {
+    members: Vec<(ResourceId, *mut dyn Resource, *const AtomicU64)>,
+    /// Whether each member was mutably accessed during this run.
+    modified: Vec<bool>,
+    _group: PhantomData<fn() -> G>,
+}
+
+impl<G: 'static> WriteGroup<G> {
+    /// Returns the resource of type `T`, or `None` if it
+    /// does not exist or is not tagged into the group.
+    pub fn get<T: Resource>(&self) -> Option<&T> {
+        let id = resource_id_for::<T>();
+        self.members
+            .iter()
+            .find(|(member, _, _)| *member == id)
+            .and_then(|(_, ptr, _)| unsafe { &**ptr }.downcast_ref())
+    }
+
+    /// Returns the resource of type `T` mutably, or `None` if it
+    /// does not exist or is not tagged into the group.
+    pub fn get_mut<T: Resource>(&mut self) -> Option<&mut T> {
+        let id = resource_id_for::<T>();
+        let index = self
+            .members
+            .iter()
+            .position(|(member, _, _)| *member == id)?;
+
+        self.modified[index] = true;
+        unsafe { &mut *self.members[index].1 }.downcast_mut()
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<G: 'static> Send for WriteGroup<G> {}
+unsafe impl<G: 'static> Sync for WriteGroup<G> {}
+
+impl<'a, G: 'static> SystemData<'a> for WriteGroup<G> {
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        let members = load_members::<G>(resources);
+        Self {
+            modified: vec![false; members.len()],
+            members,
+            _group: PhantomData,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![group_id_for::<G>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+
+    fn after_execution(&mut self) {
+        for ((_, _, version), modified) in self.members.iter().zip(&mut self.modified) {
+            if *modified {
+                unsafe { &**version }.fetch_add(1, Ordering::AcqRel);
+                *modified = false;
+            }
+        }
+    }
+}
+
+impl<'a, G: 'static> SystemDataOutput<'a> for &'a mut WriteGroup<G> {
+    type SystemData = WriteGroup<G>;
+}
+
+impl<G: 'static> MacroData for &'static mut WriteGroup<G> {
+    type SystemData = WriteGroup<G>;
+}
@@ -0,0 +1,251 @@
+//! Access to maps of resources keyed by a value,
+//! such as per-player configuration.
+
+use crate::resources::Resource;
+use crate::{
+    resource_id_for, MacroData, ResourceId, Resources, SystemCtx, SystemData, SystemDataOutput,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Specifies a read requirement for the resource `HashMap<K, T>`,
+/// providing access to its values by key.
+///
+/// The whole map is a single resource, so systems accessing
+/// it conflict regardless of which keys they access.
+/// An empty map is inserted if the resource does not exist.
+// Safety: this contains a raw pointer which must remain valid.
+pub struct ReadSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    ptr: *const HashMap<K, T>,
+}
+
+impl<K, T> ReadSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    /// Returns the value with the given key, if it exists.
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.map().get(key)
+    }
+
+    /// Returns whether a value with the given key exists.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map().contains_key(key)
+    }
+
+    /// Returns an iterator over all keys and values, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &T)> {
+        self.map().iter()
+    }
+
+    fn map(&self) -> &HashMap<K, T> {
+        unsafe { &*self.ptr }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<K: Hash + Eq + Send + Sync + 'static, T: Send + Resource> Send for ReadSet<K, T> {}
+unsafe impl<K: Hash + Eq + Send + Sync + 'static, T: Send + Sync + Resource> Sync
+    for ReadSet<K, T>
+{
+}
+
+impl<'a, K, T> SystemData<'a> for ReadSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        resources.insert_if_absent(HashMap::<K, T>::new());
+
+        Self {
+            ptr: resources.get_unchecked(resource_id_for::<HashMap<K, T>>())
+                as *const HashMap<K, T>,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![resource_id_for::<HashMap<K, T>>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, K, T> SystemDataOutput<'a> for &'a ReadSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    type SystemData = ReadSet<K, T>;
+}
+
+impl<K, T> MacroData for &'static ReadSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    type SystemData = ReadSet<K, T>;
+}
+
+/// Specifies a write requirement for the resource `HashMap<K, T>`,
+/// providing access to its values by key.
+///
+/// Like `Write<T>`, the version of the map is incremented
+/// after each run in which it was mutably accessed.
+// Safety: this contains raw pointers which must remain valid.
+pub struct WriteSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    ptr: *mut HashMap<K, T>,
+    version: *const AtomicU64,
+    modified: bool,
+}
+
+impl<K, T> WriteSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    /// Returns the value with the given key, if it exists.
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.map().get(key)
+    }
+
+    /// Returns the value with the given key mutably, if it exists.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut T> {
+        self.map_mut().get_mut(key)
+    }
+
+    /// Returns whether a value with the given key exists.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.map().contains_key(key)
+    }
+
+    /// Inserts a value with the given key, returning
+    /// the old value with that key if it existed.
+    pub fn insert(&mut self, key: K, value: T) -> Option<T> {
+        self.map_mut().insert(key, value)
+    }
+
+    /// Removes the value with the given key, returning it if it existed.
+    pub fn remove(&mut self, key: &K) -> Option<T> {
+        self.map_mut().remove(key)
+    }
+
+    /// Returns an iterator over all keys and values, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &T)> {
+        self.map().iter()
+    }
+
+    fn map(&self) -> &HashMap<K, T> {
+        unsafe { &*self.ptr }
+    }
+
+    fn map_mut(&mut self) -> &mut HashMap<K, T> {
+        self.modified = true;
+        unsafe { &mut *self.ptr }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<K: Hash + Eq + Send + Sync + 'static, T: Send + Resource> Send for WriteSet<K, T> {}
+unsafe impl<K: Hash + Eq + Send + Sync + 'static, T: Send + Sync + Resource> Sync
+    for WriteSet<K, T>
+{
+}
+
+impl<'a, K, T> SystemData<'a> for WriteSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        resources.insert_if_absent(HashMap::<K, T>::new());
+
+        let id = resource_id_for::<HashMap<K, T>>();
+        Self {
+            ptr: resources.get_mut_unchecked(id) as *mut HashMap<K, T>,
+            version: resources.version_counter(id) as *const _,
+            modified: false,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![resource_id_for::<HashMap<K, T>>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+
+    fn after_execution(&mut self) {
+        if self.modified {
+            unsafe { &*self.version }.fetch_add(1, Ordering::AcqRel);
+            self.modified = false;
+        }
+    }
+}
+
+impl<'a, K, T> SystemDataOutput<'a> for &'a mut WriteSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    type SystemData = WriteSet<K, T>;
+}
+
+impl<K, T> MacroData for &'static mut WriteSet<K, T>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    T: Resource,
+{
+    type SystemData = WriteSet<K, T>;
+}
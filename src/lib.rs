@@ -7,32 +7,71 @@ extern crate mopa;
 #[macro_use]
 extern crate static_assertions;
 
+#[doc(hidden)]
+pub extern crate legion;
+
 #[cfg(feature = "system-registry")]
 pub extern crate inventory;
 #[cfg(feature = "system-registry")]
 pub extern crate parking_lot;
 
 mod accessor;
+mod cached;
+mod context;
+mod dynamic;
 mod event;
+mod event_queue;
+mod group;
+mod keyed;
 mod mappings;
 mod query;
 #[cfg(feature = "system-registry")]
 mod registry;
 mod resources;
 mod scheduler;
+mod spawn;
 mod system;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+mod timeout;
+mod tracked;
 mod try_default;
 
 pub use accessor::{EntityAccessor, QueryAccessor};
-pub use event::{CachedEventHandler, Event, EventHandler, EventId, RawEventHandler, Trigger};
-pub use query::{PreparedWorld, Query};
+pub use cached::{CacheDeps, Cached, Compute};
+pub use context::{Context, FrameContext, SchedulerStats};
+pub use dynamic::DynamicRead;
+pub use event::{
+    event_id_for, CachedEventHandler, Event, EventBatch, EventHandler, EventId, RawEventHandler,
+    Trigger,
+};
+pub use event_queue::{EventReader, EventRetention, EventWriter};
+pub use group::{ReadGroup, WriteGroup};
+pub use keyed::{ReadSet, WriteSet};
+pub use query::{EntityRead, PreparedWorld, Query};
 #[cfg(feature = "system-registry")]
 pub use registry::*;
-pub use resources::{resource_id_for, resource_id_for_component, ResourceId, Resources};
-pub use scheduler::{EventsBuilder, Scheduler, SchedulerBuilder};
+pub use resources::{
+    resource_id_for, resource_id_for_component, resource_id_for_dyn, Poolable, ResourceId,
+    ResourceSnapshot, Resources,
+};
+pub use scheduler::{
+    Checkpoint, ClonableScheduler, CompletedStages, Conflicts, Constraint, ConstraintAlgorithm,
+    DependencyWarning, DispatchStats, DuplicatePolicy, DuplicateResource, DynamicTaskRecord,
+    DynamicTrigger, EmptyAccessPolicy, EventsBuilder, FrozenScheduler, GreedyAssembler,
+    MaxParallelismAssembler, MissingResource, PreserveOrderAssembler, ProfileSpan, ReadHandle,
+    ReadOnlyResourcePolicy, RemainingWork, ResourceAccessStats, ResourceTraceEntry, Scheduler,
+    SchedulerBuilder, StageAssembler, StageId, SysNode, TraceAction, WorldResourceExtractor,
+};
+pub use spawn::EntityReserve;
 pub use system::{
-    system_id_for, CachedSystem, MacroData, RawSystem, Read, System, SystemCtx, SystemData,
-    SystemDataOutput, SystemId, Write,
+    access_of, alloc_system_id, system_id_for, CachedSystem, Changed, FlushPhase, FnOnceSystem,
+    MacroData, Marker, MaybeDebug, MaybeWrite, MutexWrite, RawSystem, Read, ReadCached,
+    ResourceEvents, ResourceFilter, ResourceMissing, ResourceModified, ResourcePredicate,
+    ScopedWrite, ScopedWriteGuard, System, SystemCtx, SystemData, SystemDataOutput, SystemId,
+    SystemNotScheduled, TryRead, Write,
 };
-pub use tonks_macros::{event_handler, system, Resource};
+pub use timeout::{TimedOut, TimeoutBudget};
+pub use tonks_macros::{event_handler, system, Resource, SystemData};
+pub use tracked::{Diff, TrackedChanges, TrackedWrite};
 pub use try_default::TryDefault;
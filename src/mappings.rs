@@ -10,6 +10,8 @@ where
 {
     counter: usize,
     mappings: HashMap<K, V>,
+    /// Names of the allocated values, if known, indexed by value.
+    names: Vec<Option<&'static str>>,
 }
 
 impl<K, V> Default for Mappings<K, V>
@@ -21,6 +23,7 @@ where
         Self {
             counter: 0,
             mappings: HashMap::new(),
+            names: vec![],
         }
     }
 }
@@ -38,17 +41,43 @@ where
         if let Some(value) = self.mappings.get(&key) {
             *value
         } else {
-            self.mappings.insert(key, V::from(self.counter));
-            self.counter += 1;
-            (self.counter - 1).into()
+            let value = self.alloc();
+            self.mappings.insert(key, value);
+            value
+        }
+    }
+
+    /// Like `get_or_alloc()`, but records `name` as
+    /// the name of the value if it is allocated.
+    pub fn get_or_alloc_named(&mut self, key: K, name: &'static str) -> V {
+        if let Some(value) = self.mappings.get(&key) {
+            *value
+        } else {
+            let value = self.alloc_named(name);
+            self.mappings.insert(key, value);
+            value
         }
     }
 
     pub fn alloc(&mut self) -> V {
+        self.names.push(None);
+        self.counter += 1;
+        (self.counter - 1).into()
+    }
+
+    /// Allocates a new value with the given name.
+    pub fn alloc_named(&mut self, name: &'static str) -> V {
+        self.names.push(Some(name));
         self.counter += 1;
         (self.counter - 1).into()
     }
 
+    /// Returns the name of the value with the given index,
+    /// if it was allocated with a name.
+    pub fn name(&self, index: usize) -> Option<&'static str> {
+        self.names.get(index).copied().flatten()
+    }
+
     pub fn len(&self) -> usize {
         self.counter
     }
@@ -66,4 +95,21 @@ mod tests {
         assert_eq!(mappings.get_or_alloc(TypeId::of::<usize>()), 0);
         assert_eq!(mappings.get_or_alloc(TypeId::of::<isize>()), 1);
     }
+
+    #[test]
+    fn names() {
+        let mut mappings = Mappings::<TypeId, usize>::new();
+
+        assert_eq!(mappings.get_or_alloc(TypeId::of::<usize>()), 0);
+        assert_eq!(
+            mappings.get_or_alloc_named(TypeId::of::<isize>(), "isize"),
+            1
+        );
+        assert_eq!(mappings.alloc_named("custom"), 2);
+
+        assert_eq!(mappings.name(0), None);
+        assert_eq!(mappings.name(1), Some("isize"));
+        assert_eq!(mappings.name(2), Some("custom"));
+        assert_eq!(mappings.name(3), None);
+    }
 }
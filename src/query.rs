@@ -14,6 +14,7 @@ use legion::query::{
 };
 use legion::storage::{Component, ComponentTypeId};
 use legion::world::World;
+use std::marker::PhantomData;
 
 /// A `legion::World` wrapper which can be safely passed to systems.
 pub struct PreparedWorld {
@@ -127,6 +128,79 @@ impl MacroData for &'static mut PreparedWorld {
     type SystemData = PreparedWorld;
 }
 
+/// System data which provides read access to components of type `C`
+/// on any entity, without access to the rest of the `World`.
+///
+/// This is a narrower alternative to `PreparedWorld` for systems which
+/// only need to look up a single component type by `Entity`. The
+/// component read is declared to the scheduler, so `EntityRead<C>`
+/// conflicts with any system writing `C`.
+pub struct EntityRead<C: Component> {
+    world: *const World,
+    _phantom: PhantomData<C>,
+}
+
+unsafe impl<C: Component> Send for EntityRead<C> {}
+unsafe impl<C: Component> Sync for EntityRead<C> {}
+
+impl<'a, C: Component> SystemData<'a> for EntityRead<C> {
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        _resources: &mut Resources,
+        _ctx: SystemCtx,
+        world: &World,
+    ) -> Self {
+        Self {
+            world: world as *const _,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![ComponentTypeId::of::<C>()]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, C: Component> SystemDataOutput<'a> for &'a EntityRead<C> {
+    type SystemData = EntityRead<C>;
+}
+
+impl<C: Component> MacroData for &'static EntityRead<C> {
+    type SystemData = EntityRead<C>;
+}
+
+impl<C: Component> EntityRead<C> {
+    /// Retrieves the component of type `C` for the given entity,
+    /// or `None` if the entity is dead or does not have the component.
+    pub fn get(&self, entity: Entity) -> Option<Ref<'_, C>> {
+        // Safe because the component read is declared to the scheduler,
+        // so no system writing `C` runs concurrently.
+        unsafe { &*self.world }.get_component(entity)
+    }
+
+    /// Returns whether the given entity has a component of type `C`.
+    pub fn has(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+}
+
 /// System data which allows for querying entities.
 pub struct Query<V>
 where
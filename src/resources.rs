@@ -2,18 +2,31 @@
 //! we use consecutive `usize`s as resource IDs so that a vector can be
 //! used rather than a hash map.
 
+use crate::group::group_id_for;
 use crate::mappings::Mappings;
+use crate::SystemId;
+use hashbrown::HashMap;
 use lazy_static::lazy_static;
 use legion::storage::ComponentTypeId;
 use parking_lot::Mutex;
-use std::any::TypeId;
+use std::any::{type_name, TypeId};
 use std::cell::UnsafeCell;
+use std::fmt::{self, Display};
 use std::iter;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Type {
     Resource(TypeId),
     Component(ComponentTypeId),
+    /// Synthetic resource shared by two systems which conflict
+    /// through `RawSystem::conflicts_with()` rather than their accesses.
+    Conflict(SystemId, SystemId),
+    /// Synthetic resource written by a system and read by the
+    /// systems which are required to run after it.
+    Ordering(SystemId),
+    /// Synthetic resource written by late systems and read by all others.
+    LateStages,
 }
 
 /// ID of a resource.
@@ -26,6 +39,18 @@ impl From<usize> for ResourceId {
     }
 }
 
+/// Formats the resource as `Resource(name)`, where `name` is the name of
+/// its type. Components and synthetic resources, whose names are not
+/// known, are formatted as `Resource(#id)`.
+impl Display for ResourceId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match RESOURCE_ID_MAPPINGS.lock().name(self.0) {
+            Some(name) => write!(f, "Resource({})", name),
+            None => write!(f, "Resource(#{})", self.0),
+        }
+    }
+}
+
 lazy_static! {
     /// Mappings from `TypeId`s to `ResourceId`s.
     pub static ref RESOURCE_ID_MAPPINGS: Mutex<Mappings<Type, ResourceId>> = Mutex::new(Mappings::new());
@@ -33,9 +58,18 @@ lazy_static! {
 
 /// Returns the resource ID corresponding to a given type.
 pub fn resource_id_for<T: Resource>() -> ResourceId {
+    resource_id_for_dyn::<T>()
+}
+
+/// Returns the resource ID corresponding to a possibly unsized type,
+/// such as a trait object.
+///
+/// Note that `dyn Trait` has a different ID from any of the concrete
+/// types implementing `Trait`.
+pub fn resource_id_for_dyn<T: ?Sized + 'static>() -> ResourceId {
     RESOURCE_ID_MAPPINGS
         .lock()
-        .get_or_alloc(Type::Resource(TypeId::of::<T>()))
+        .get_or_alloc_named(Type::Resource(TypeId::of::<T>()), type_name::<T>())
 }
 
 /// Returns the resource ID corresponding to a component type.
@@ -45,6 +79,29 @@ pub fn resource_id_for_component(component: ComponentTypeId) -> ResourceId {
         .get_or_alloc(Type::Component(component))
 }
 
+/// Returns the synthetic resource ID used to prevent two systems
+/// from running concurrently.
+pub(crate) fn resource_id_for_conflict(a: SystemId, b: SystemId) -> ResourceId {
+    let key = if a.0 <= b.0 { (a, b) } else { (b, a) };
+    RESOURCE_ID_MAPPINGS
+        .lock()
+        .get_or_alloc(Type::Conflict(key.0, key.1))
+}
+
+/// Returns the synthetic resource ID used to prevent the systems which
+/// must run after `system` from running concurrently with it.
+pub(crate) fn resource_id_for_ordering(system: SystemId) -> ResourceId {
+    RESOURCE_ID_MAPPINGS
+        .lock()
+        .get_or_alloc(Type::Ordering(system))
+}
+
+/// Returns the synthetic resource ID used to prevent late systems
+/// from running concurrently with any other system.
+pub(crate) fn resource_id_for_late_stages() -> ResourceId {
+    RESOURCE_ID_MAPPINGS.lock().get_or_alloc(Type::LateStages)
+}
+
 pub trait Resource: Send + Sync + mopa::Any + 'static {}
 
 impl<T: Send + Sync + mopa::Any> Resource for T {}
@@ -55,11 +112,48 @@ mod mopafy {
     mopafy!(Resource);
 }
 
+/// A resource whose storage can be reused through
+/// `Resources::remove_pooled()` and `Resources::insert_pooled()`,
+/// such as a large buffer which is rebuilt every frame.
+pub trait Poolable: Resource + Default {
+    /// Resets the value before it is reused, keeping its allocations.
+    fn reset(&mut self);
+}
+
+impl<T: Send + Sync + 'static> Poolable for Vec<T> {
+    fn reset(&mut self) {
+        self.clear();
+    }
+}
+
+/// The versions of the resources in a `Resources` at some point,
+/// as returned by `Resources::snapshot()`.
+///
+/// Comparing a snapshot with the current versions reveals which
+/// resources have changed since it was taken, without hashing
+/// or cloning any resource.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceSnapshot {
+    /// Version of each resource, indexed by the `ResourceId`,
+    /// or `None` if the resource did not exist.
+    versions: Vec<Option<u64>>,
+}
+
 /// Stores resources. Resource borrow access is unchecked,
 /// so most functions are unsafe.
 pub struct Resources {
     /// Stored resources, accessed by the `ResourceId` index.
     resources: Vec<UnsafeCell<Option<Box<dyn Resource>>>>,
+    /// Version counter of each resource slot, accessed by the `ResourceId` index.
+    ///
+    /// Counters are boxed so that accessors can hold pointers
+    /// to them while the vector grows.
+    #[allow(clippy::vec_box)]
+    versions: Vec<Box<AtomicU64>>,
+    /// Values removed through `remove_pooled()`, accessed by the `ResourceId` index.
+    pool: Vec<Option<Box<dyn Resource>>>,
+    /// Resources tagged into each group through `tag()`, by the ID of the group.
+    groups: HashMap<ResourceId, Vec<ResourceId>>,
 }
 
 unsafe impl Send for Resources {}
@@ -67,7 +161,12 @@ unsafe impl Sync for Resources {}
 
 impl Default for Resources {
     fn default() -> Self {
-        Self { resources: vec![] }
+        Self {
+            resources: vec![],
+            versions: vec![],
+            pool: vec![],
+            groups: HashMap::new(),
+        }
     }
 }
 
@@ -77,6 +176,19 @@ impl Resources {
         Self::default()
     }
 
+    /// Returns whether a resource of the given type exists.
+    pub fn contains<T: Resource>(&self) -> bool {
+        self.contains_id(resource_id_for::<T>())
+    }
+
+    /// Returns whether a resource with the given ID exists.
+    pub fn contains_id(&self, id: ResourceId) -> bool {
+        self.resources
+            .get(id.0)
+            .map(|resource| unsafe { &*resource.get() }.is_some())
+            .unwrap_or(false)
+    }
+
     /// Returns a reference to the resource.
     ///
     /// # Panics
@@ -90,8 +202,67 @@ impl Resources {
     /// # Panics
     /// Panics if the resource does not exist.
     pub fn get_mut<T: Resource>(&mut self) -> &mut T {
+        let id = resource_id_for::<T>();
         // Safety: borrow rules are enforced through &mut self.
-        unsafe { self.get_mut_unchecked(resource_id_for::<T>()) }
+        let resource = unsafe { self.get_mut_unchecked(id) };
+        self.versions[id.0].fetch_add(1, Ordering::AcqRel);
+        resource
+    }
+
+    /// Returns the version of the resource of the given type.
+    ///
+    /// The version is incremented whenever the resource is
+    /// inserted, accessed through `get_mut()`, or modified
+    /// by a system through `Write` or `MaybeWrite`.
+    pub fn version<T: Resource>(&self) -> u64 {
+        self.versions
+            .get(resource_id_for::<T>().0)
+            .map(|version| version.load(Ordering::Acquire))
+            .unwrap_or(0)
+    }
+
+    /// Returns a snapshot of the current versions of all resources.
+    pub fn snapshot(&self) -> ResourceSnapshot {
+        ResourceSnapshot {
+            versions: (0..self.resources.len())
+                .map(|index| self.version_of(ResourceId(index)))
+                .collect(),
+        }
+    }
+
+    /// Returns the IDs of the resources which have been inserted, removed
+    /// or modified since `snapshot` was taken, in ascending order.
+    ///
+    /// Modifications are detected through resource versions, so
+    /// a resource accessed mutably without being changed is included.
+    pub fn changed_since(&self, snapshot: &ResourceSnapshot) -> Vec<ResourceId> {
+        let len = self.resources.len().max(snapshot.versions.len());
+
+        (0..len)
+            .map(ResourceId)
+            .filter(|id| {
+                let old = snapshot.versions.get(id.0).copied().flatten();
+                old != self.version_of(*id)
+            })
+            .collect()
+    }
+
+    /// Returns the version of the resource with the
+    /// given ID, or `None` if it does not exist.
+    fn version_of(&self, id: ResourceId) -> Option<u64> {
+        if self.contains_id(id) {
+            Some(self.versions[id.0].load(Ordering::Acquire))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the version counter of the resource with the given ID.
+    ///
+    /// # Panics
+    /// Panics if no resource with the given ID has ever been inserted.
+    pub(crate) fn version_counter(&self, id: ResourceId) -> &AtomicU64 {
+        &self.versions[id.0]
     }
 
     /// Returns a reference to the resource with the given ID.
@@ -122,6 +293,39 @@ impl Resources {
         .unwrap()
     }
 
+    /// Returns a reference to the resource with the given ID
+    /// without checking its type.
+    ///
+    /// # Safety
+    /// Borrowing is unchecked, as with `get_unchecked()`.
+    pub(crate) unsafe fn get_dyn_unchecked(&self, id: ResourceId) -> &dyn Resource {
+        (&*self
+            .resources
+            .get(id.0)
+            .expect("failed to fetch resource")
+            .get())
+            .as_ref()
+            .expect("failed to fetch resource")
+            .as_ref()
+    }
+
+    /// Returns a mutable reference to the resource with the
+    /// given ID without checking its type.
+    ///
+    /// # Safety
+    /// Borrowing is unchecked, as with `get_mut_unchecked()`.
+    #[allow(clippy::mut_from_ref)]
+    pub(crate) unsafe fn get_dyn_mut_unchecked(&self, id: ResourceId) -> &mut dyn Resource {
+        (&mut *self
+            .resources
+            .get(id.0)
+            .expect("failed to fetch resource")
+            .get())
+            .as_mut()
+            .expect("failed to fetch resource")
+            .as_mut()
+    }
+
     /// Returns a mutable reference to the resource with the given ID.
     ///
     /// # Safety
@@ -157,34 +361,118 @@ impl Resources {
     /// Inserts a resource of the given type, replacing
     /// the old resource if it exists.
     pub fn insert<T: Resource>(&mut self, value: T) {
-        let id = resource_id_for::<T>();
+        self.insert_boxed(resource_id_for::<T>(), Box::new(value));
+    }
 
+    /// Inserts a boxed resource with the given ID, replacing
+    /// the old resource if it exists.
+    ///
+    /// The type of the resource must match the ID.
+    pub(crate) fn insert_boxed(&mut self, id: ResourceId, value: Box<dyn Resource>) {
+        self.extend_to(id);
+
+        self.resources[id.0] = UnsafeCell::new(Some(value));
+        self.versions[id.0].fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Extends the resource and version vectors so that they can hold `id`.
+    fn extend_to(&mut self, id: ResourceId) {
         if self.resources.len() <= id.0 {
             // Extend resources vector
             self.resources.extend(
                 iter::repeat_with(|| UnsafeCell::new(None)).take(id.0 - self.resources.len() + 1),
             );
+            self.versions.extend(
+                iter::repeat_with(|| Box::new(AtomicU64::new(0)))
+                    .take(id.0 - self.versions.len() + 1),
+            );
         }
+    }
 
-        self.resources[id.0] = UnsafeCell::new(Some(Box::new(value)));
+    /// Removes the resource with the given ID, returning it
+    /// if it existed.
+    pub(crate) fn take_boxed(&mut self, id: ResourceId) -> Option<Box<dyn Resource>> {
+        self.resources
+            .get_mut(id.0)
+            .and_then(|resource| resource.get_mut().take())
+    }
+
+    /// Inserts a resource of type `T`, reusing the value last removed
+    /// through `remove_pooled()` if there is one, and returns it.
+    ///
+    /// A reused value is reset through `Poolable::reset()`,
+    /// so its allocations are kept. Otherwise, the default
+    /// value is inserted. An existing resource is left as is.
+    pub fn insert_pooled<T: Poolable>(&mut self) -> &mut T {
+        let id = resource_id_for::<T>();
+        if !self.contains_id(id) {
+            let value = match self.pool.get_mut(id.0).and_then(Option::take) {
+                Some(mut value) => {
+                    value.downcast_mut::<T>().unwrap().reset();
+                    value
+                }
+                None => Box::new(T::default()),
+            };
+            self.insert_boxed(id, value);
+        }
+
+        self.get_mut::<T>()
+    }
+
+    /// Removes the resource of type `T`, retaining its value so that
+    /// the next call to `insert_pooled()` reuses its storage.
+    ///
+    /// Systems must not hold pointers to the resource, so this
+    /// should not be used for resources accessed by a scheduler's systems.
+    pub fn remove_pooled<T: Poolable>(&mut self) {
+        let id = resource_id_for::<T>();
+        if let Some(value) = self.take_boxed(id) {
+            if self.pool.len() <= id.0 {
+                self.pool
+                    .extend(iter::repeat_with(|| None).take(id.0 - self.pool.len() + 1));
+            }
+            self.pool[id.0] = Some(value);
+        }
+    }
+
+    /// Tags the resource of type `T` as part of the group `G`, so that
+    /// systems accessing the group through `ReadGroup<G>` or `WriteGroup<G>`
+    /// are scheduled as if they accessed `T`.
+    ///
+    /// The resource need not exist yet. Tags are read when a scheduler
+    /// is built, so tagging resources of a built scheduler has no effect.
+    pub fn tag<T: Resource, G: 'static>(&mut self) {
+        let members = self.groups.entry(group_id_for::<G>()).or_default();
+        let id = resource_id_for::<T>();
+        if !members.contains(&id) {
+            members.push(id);
+        }
+    }
+
+    /// Returns the resources tagged into the group with the given ID.
+    pub(crate) fn group_members(&self, group: ResourceId) -> &[ResourceId] {
+        self.groups.get(&group).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Returns an iterator over the IDs of all resources which exist.
+    pub(crate) fn ids(&self) -> impl Iterator<Item = ResourceId> + '_ {
+        (0..self.resources.len())
+            .map(ResourceId)
+            .filter(move |id| self.contains_id(*id))
     }
 
     /// Inserts a resource if it is absent.
     pub fn insert_if_absent<T: Resource>(&mut self, value: T) {
         let id = resource_id_for::<T>();
 
-        if self.resources.len() <= id.0 {
-            // Extend resources vector
-            self.resources.extend(
-                iter::repeat_with(|| UnsafeCell::new(None)).take(id.0 - self.resources.len() + 1),
-            );
-        }
+        self.extend_to(id);
 
         let resource = unsafe { &mut *self.resources[id.0].get() };
         if resource.is_some() {
             return;
         }
         self.resources[id.0] = UnsafeCell::new(Some(Box::new(value)));
+        self.versions[id.0].fetch_add(1, Ordering::AcqRel);
     }
 }
 
@@ -203,4 +491,19 @@ mod tests {
             assert_eq!(resources.get_unchecked::<usize>(ResourceId(1)), &1);
         }
     }
+
+    #[test]
+    fn pooled_reuses_allocation() {
+        let mut resources = Resources::new();
+        resources.insert_pooled::<Vec<u64>>().extend(0..1024);
+        let ptr = resources.get::<Vec<u64>>().as_ptr();
+
+        resources.remove_pooled::<Vec<u64>>();
+        assert!(!resources.contains::<Vec<u64>>());
+
+        let reused = resources.insert_pooled::<Vec<u64>>();
+        assert!(reused.is_empty());
+        assert!(reused.capacity() >= 1024);
+        assert_eq!(reused.as_ptr(), ptr);
+    }
 }
@@ -0,0 +1,441 @@
+//! Strategies for assembling systems into stages.
+
+use crate::SystemId;
+use hashbrown::{HashMap, HashSet};
+
+/// Pairwise conflicts between the systems passed to a `StageAssembler`.
+///
+/// Two systems conflict if they cannot safely run in parallel,
+/// e.g. because one writes a resource which the other accesses.
+#[derive(Debug, Clone, Default)]
+pub struct Conflicts {
+    pairs: HashSet<(SystemId, SystemId)>,
+}
+
+impl Conflicts {
+    /// Creates an empty set of conflicts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that the two systems conflict with each other.
+    pub fn insert(&mut self, a: SystemId, b: SystemId) {
+        self.pairs.insert(ordered(a, b));
+    }
+
+    /// Returns whether the two systems conflict with each other.
+    pub fn conflicts(&self, a: SystemId, b: SystemId) -> bool {
+        self.pairs.contains(&ordered(a, b))
+    }
+
+    /// Returns whether the given system conflicts with any system in `stage`.
+    pub fn conflicts_with_any(&self, system: SystemId, stage: &[SystemId]) -> bool {
+        stage.iter().any(|other| self.conflicts(system, *other))
+    }
+}
+
+fn ordered(a: SystemId, b: SystemId) -> (SystemId, SystemId) {
+    if a.0 <= b.0 {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// An ordering constraint requiring that the system `before`
+/// runs in an earlier stage than the system `after`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Constraint {
+    pub before: SystemId,
+    pub after: SystemId,
+}
+
+/// A strategy for assembling systems into stages.
+///
+/// Implementations are used by `SchedulerBuilder::build()` and may
+/// optimize for different goals, such as minimizing the number of stages
+/// or preserving insertion order.
+pub trait StageAssembler: Send + Sync {
+    /// Assembles `systems`, which are given in insertion order, into stages.
+    ///
+    /// The returned stages must contain each system exactly once. No stage
+    /// may contain two conflicting systems, and the `before` system of each
+    /// constraint must be placed in an earlier stage than the `after` system.
+    /// `SchedulerBuilder::build()` panics if any of these rules are violated.
+    fn assemble(
+        &self,
+        systems: &[SystemId],
+        conflicts: &Conflicts,
+        constraints: &[Constraint],
+    ) -> Vec<Vec<SystemId>>;
+}
+
+/// The default `StageAssembler`, which places each system
+/// in the first stage it does not conflict with. This tends to
+/// minimize the number of stages.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GreedyAssembler;
+
+impl StageAssembler for GreedyAssembler {
+    fn assemble(
+        &self,
+        systems: &[SystemId],
+        conflicts: &Conflicts,
+        constraints: &[Constraint],
+    ) -> Vec<Vec<SystemId>> {
+        let mut stages: Vec<Vec<SystemId>> = vec![];
+        let mut placed = HashMap::new();
+
+        for system in topological_order(systems, constraints) {
+            let first = earliest_stage(system, constraints, &placed);
+
+            let index = match (first..stages.len())
+                .find(|index| !conflicts.conflicts_with_any(system, &stages[*index]))
+            {
+                Some(index) => index,
+                None => {
+                    stages.push(vec![]);
+                    stages.len() - 1
+                }
+            };
+
+            stages[index].push(system);
+            placed.insert(system, index);
+        }
+
+        stages
+    }
+}
+
+/// A `StageAssembler` which only ever adds a system to the last
+/// stage or a new stage, so that no system runs in an earlier stage
+/// than a system added before it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PreserveOrderAssembler;
+
+impl StageAssembler for PreserveOrderAssembler {
+    fn assemble(
+        &self,
+        systems: &[SystemId],
+        conflicts: &Conflicts,
+        constraints: &[Constraint],
+    ) -> Vec<Vec<SystemId>> {
+        let mut stages: Vec<Vec<SystemId>> = vec![];
+        let mut placed = HashMap::new();
+
+        for system in topological_order(systems, constraints) {
+            let first = earliest_stage(system, constraints, &placed);
+
+            let fits_in_last = match stages.last() {
+                Some(last) => stages.len() > first && !conflicts.conflicts_with_any(system, last),
+                None => false,
+            };
+
+            if !fits_in_last {
+                stages.push(vec![]);
+            }
+
+            stages.last_mut().unwrap().push(system);
+            placed.insert(system, stages.len() - 1);
+        }
+
+        stages
+    }
+}
+
+/// A `StageAssembler` which colors the conflict graph of the systems,
+/// placing systems with the most conflicts first.
+///
+/// Placing highly conflicting systems first (the Welsh-Powell heuristic)
+/// usually yields fewer stages, and therefore more parallelism, than
+/// `GreedyAssembler`, at the cost of ignoring insertion order.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaxParallelismAssembler;
+
+impl StageAssembler for MaxParallelismAssembler {
+    fn assemble(
+        &self,
+        systems: &[SystemId],
+        conflicts: &Conflicts,
+        constraints: &[Constraint],
+    ) -> Vec<Vec<SystemId>> {
+        let degree = |system: SystemId| {
+            systems
+                .iter()
+                .filter(|other| conflicts.conflicts(system, **other))
+                .count()
+        };
+
+        let mut stages: Vec<Vec<SystemId>> = vec![];
+        let mut placed = HashMap::new();
+
+        for system in prioritized_topological_order(systems, constraints, degree) {
+            let first = earliest_stage(system, constraints, &placed);
+
+            let index = match (first..stages.len())
+                .find(|index| !conflicts.conflicts_with_any(system, &stages[*index]))
+            {
+                Some(index) => index,
+                None => {
+                    stages.push(vec![]);
+                    stages.len() - 1
+                }
+            };
+
+            stages[index].push(system);
+            placed.insert(system, index);
+        }
+
+        stages
+    }
+}
+
+/// The algorithm used to partition systems into stages,
+/// selected through `SchedulerBuilder::with_constraint_solver()`.
+///
+/// The default algorithm is `Greedy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintAlgorithm {
+    /// First-fit placement in insertion order. See `GreedyAssembler`.
+    Greedy,
+    /// Placement which never reorders systems. See `PreserveOrderAssembler`.
+    PreserveOrder,
+    /// Graph coloring which minimizes the number of stages.
+    /// See `MaxParallelismAssembler`.
+    MaxParallelism,
+}
+
+impl ConstraintAlgorithm {
+    /// Returns the `StageAssembler` implementing this algorithm.
+    pub fn assembler(self) -> Box<dyn StageAssembler> {
+        match self {
+            ConstraintAlgorithm::Greedy => Box::new(GreedyAssembler),
+            ConstraintAlgorithm::PreserveOrder => Box::new(PreserveOrderAssembler),
+            ConstraintAlgorithm::MaxParallelism => Box::new(MaxParallelismAssembler),
+        }
+    }
+}
+
+/// Returns the index of the earliest stage `system` may be placed in
+/// without violating any constraints, given the stages
+/// of the systems which have been placed so far.
+fn earliest_stage(
+    system: SystemId,
+    constraints: &[Constraint],
+    placed: &HashMap<SystemId, usize>,
+) -> usize {
+    constraints
+        .iter()
+        .filter(|constraint| constraint.after == system)
+        .filter_map(|constraint| placed.get(&constraint.before))
+        .map(|index| index + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Sorts systems such that the `before` system of each constraint
+/// comes before the `after` system. Systems which are not constrained
+/// relative to each other keep their insertion order.
+///
+/// # Panics
+/// Panics if the constraints contain a cycle.
+pub(crate) fn topological_order(systems: &[SystemId], constraints: &[Constraint]) -> Vec<SystemId> {
+    prioritized_topological_order(systems, constraints, |_| 0)
+}
+
+/// Like `topological_order()`, but among the systems whose constraints
+/// are satisfied, the one with the highest `priority` comes first.
+/// Ties are broken by insertion order.
+///
+/// # Panics
+/// Panics if the constraints contain a cycle.
+fn prioritized_topological_order(
+    systems: &[SystemId],
+    constraints: &[Constraint],
+    priority: impl Fn(SystemId) -> usize,
+) -> Vec<SystemId> {
+    let mut remaining = systems.to_vec();
+    let mut order = Vec::with_capacity(systems.len());
+    let mut done = HashSet::with_capacity(systems.len());
+
+    while !remaining.is_empty() {
+        let mut next: Option<(usize, usize)> = None;
+
+        for (index, system) in remaining.iter().enumerate() {
+            let ready = constraints.iter().all(|constraint| {
+                constraint.after != *system
+                    || done.contains(&constraint.before)
+                    || !systems.contains(&constraint.before)
+            });
+            if !ready {
+                continue;
+            }
+
+            let priority = priority(*system);
+            match next {
+                Some((_, best)) if best >= priority => (),
+                _ => next = Some((index, priority)),
+            }
+        }
+
+        let (next, _) = next.expect("ordering constraints between systems contain a cycle");
+
+        let system = remaining.remove(next);
+        done.insert(system);
+        order.push(system);
+    }
+
+    order
+}
+
+/// Verifies that stages returned by a `StageAssembler` are valid.
+///
+/// # Panics
+/// Panics if any of the rules documented on `StageAssembler::assemble()` are violated.
+pub(crate) fn assert_valid_stages(
+    systems: &[SystemId],
+    conflicts: &Conflicts,
+    constraints: &[Constraint],
+    stages: &[Vec<SystemId>],
+) {
+    let mut stage_of = HashMap::with_capacity(systems.len());
+
+    for (index, stage) in stages.iter().enumerate() {
+        for (position, system) in stage.iter().enumerate() {
+            assert!(
+                systems.contains(system),
+                "stage assembler returned unknown system {:?}",
+                system
+            );
+            assert!(
+                stage_of.insert(*system, index).is_none(),
+                "stage assembler returned system {:?} more than once",
+                system
+            );
+            assert!(
+                !conflicts.conflicts_with_any(*system, &stage[..position]),
+                "stage assembler placed conflicting systems in stage {}",
+                index
+            );
+        }
+    }
+
+    assert_eq!(
+        stage_of.len(),
+        systems.len(),
+        "stage assembler did not return all systems"
+    );
+
+    for constraint in constraints {
+        if let (Some(before), Some(after)) = (
+            stage_of.get(&constraint.before),
+            stage_of.get(&constraint.after),
+        ) {
+            assert!(
+                before < after,
+                "stage assembler violated ordering constraint {:?}",
+                constraint
+            );
+        }
+    }
+}
+
+/// The inputs from which stages were assembled, kept by the scheduler
+/// so that `Scheduler::reschedule()` can assemble them again.
+pub(crate) struct StageLayout {
+    pub(crate) assembler: Box<dyn StageAssembler>,
+    pub(crate) conflicts: Conflicts,
+    pub(crate) constraints: Vec<Constraint>,
+    /// All systems, in insertion order.
+    pub(crate) systems: Vec<SystemId>,
+    /// Systems added through `SchedulerBuilder::with_late_system()`.
+    pub(crate) late: Vec<SystemId>,
+    /// Limit set through `SchedulerBuilder::with_stage_limit()`.
+    pub(crate) stage_limit: Option<usize>,
+}
+
+impl Default for StageLayout {
+    fn default() -> Self {
+        Self {
+            assembler: Box::new(GreedyAssembler),
+            conflicts: Conflicts::new(),
+            constraints: vec![],
+            systems: vec![],
+            late: vec![],
+            stage_limit: None,
+        }
+    }
+}
+
+impl StageLayout {
+    /// Assembles the systems for which `include` returns `true` into stages.
+    ///
+    /// Late systems are assembled separately and appended after all other stages.
+    /// If there are more stages than the stage limit, they are merged.
+    pub(crate) fn assemble(&self, include: impl Fn(SystemId) -> bool) -> Vec<Vec<SystemId>> {
+        let ids: Vec<SystemId> = self
+            .systems
+            .iter()
+            .copied()
+            .filter(|id| include(*id))
+            .collect();
+
+        let stages = assemble_layout(
+            &*self.assembler,
+            &self.conflicts,
+            &self.constraints,
+            &ids,
+            &self.late,
+        );
+        limit_stages(stages, self.stage_limit)
+    }
+}
+
+/// Assembles `systems` into stages, appending the stages
+/// of the systems in `late` after all other stages.
+pub(crate) fn assemble_layout(
+    assembler: &dyn StageAssembler,
+    conflicts: &Conflicts,
+    constraints: &[Constraint],
+    systems: &[SystemId],
+    late: &[SystemId],
+) -> Vec<Vec<SystemId>> {
+    let (late, early): (Vec<SystemId>, Vec<SystemId>) =
+        systems.iter().partition(|id| late.contains(id));
+
+    let constraints_within = |systems: &[SystemId]| -> Vec<Constraint> {
+        constraints
+            .iter()
+            .filter(|constraint| {
+                systems.contains(&constraint.before) && systems.contains(&constraint.after)
+            })
+            .copied()
+            .collect()
+    };
+
+    let mut stages = assembler.assemble(&early, conflicts, &constraints_within(&early));
+    if !late.is_empty() {
+        stages.extend(assembler.assemble(&late, conflicts, &constraints_within(&late)));
+    }
+    assert_valid_stages(systems, conflicts, constraints, &stages);
+
+    stages
+}
+
+/// Merges runs of consecutive stages so that there are at most `limit` stages.
+///
+/// Systems keep their relative order, so running each merged stage serially
+/// in order preserves the ordering of the original stages. Merged stages
+/// may contain conflicting systems.
+pub(crate) fn limit_stages(stages: Vec<Vec<SystemId>>, limit: Option<usize>) -> Vec<Vec<SystemId>> {
+    let limit = match limit {
+        Some(limit) if stages.len() > limit => limit,
+        _ => return stages,
+    };
+
+    let per_stage = stages.len().div_ceil(limit);
+    stages
+        .chunks(per_stage)
+        .map(|chunk| chunk.concat())
+        .collect()
+}
@@ -1,14 +1,33 @@
 //! Building of stage pipelines, which are used to organize system
 //! execution order while ensuring resource borrow safety.
 
+use crate::cached::compute_id_for;
 use crate::event::HandleStrategy;
-use crate::scheduler::OrExtend;
+use crate::event_queue::EventQueue;
+use crate::resources::{
+    resource_id_for_conflict, resource_id_for_late_stages, resource_id_for_ordering, Resource,
+};
+use crate::scheduler::assembler::{
+    assemble_layout, limit_stages, Conflicts, Constraint, ConstraintAlgorithm, GreedyAssembler,
+    StageAssembler, StageLayout,
+};
+use crate::scheduler::graph::GraphSystem;
+use crate::scheduler::{
+    CheckpointedResource, DebugLabel, ExtractFn, OrExtend, RawResourceScope, ResourceScope,
+    UnhandledEventHandler, WorldResourceExtractor,
+};
+use crate::system::{accesses_conflict, slices_conflict};
 use crate::{
-    resource_id_for_component, CachedEventHandler, CachedSystem, Event, EventHandler,
-    RawEventHandler, RawSystem, ResourceId, Resources, Scheduler, System,
+    resource_id_for, resource_id_for_component, CacheDeps, CachedEventHandler, CachedSystem,
+    Compute, Event, EventHandler, EventId, EventRetention, RawEventHandler, RawSystem, ResourceId,
+    Resources, Scheduler, SysNode, System, SystemId,
 };
-use hashbrown::HashSet;
-use legion::storage::ComponentTypeId;
+use hashbrown::{HashMap, HashSet};
+use parking_lot::Mutex;
+use std::any::{Any, TypeId};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Builder of event pipelines.
 #[derive(Default)]
@@ -31,7 +50,10 @@ impl EventsBuilder {
         H: EventHandler<E>,
         E: Event,
     {
-        self.add_boxed(Box::new(CachedEventHandler::new(handler, "null")))
+        self.add_boxed(Box::new(CachedEventHandler::new(
+            handler,
+            std::any::type_name::<H>(),
+        )))
     }
 
     /// Adds a boxed event handler.
@@ -67,26 +89,219 @@ impl EventsBuilder {
     /// which can be used to further add systems.
     pub fn finish(self) -> SchedulerBuilder {
         SchedulerBuilder {
-            stages: vec![],
             events: self,
+            ..SchedulerBuilder::default()
         }
     }
 }
 
-/// Access to either a component or a resource.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Access {
-    Resource(ResourceId),
-    Component(ComponentTypeId),
+/// A resource which is required by one or more systems
+/// but is missing from a `Resources`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingResource {
+    /// ID of the missing resource.
+    pub resource_id: ResourceId,
+    /// IDs of the systems which read or write the resource.
+    pub required_by_systems: Vec<SystemId>,
+}
+
+/// A possible mistake in the accesses declared by a system,
+/// as returned by `SchedulerBuilder::audit_system_dependencies()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencyWarning {
+    /// The system appears to modify a resource which it only reads,
+    /// e.g. through a `Cell` or `Mutex` inside the resource.
+    PossibleMissingWrite {
+        system_id: SystemId,
+        suspected_resource: ResourceId,
+    },
+}
+
+/// Determines how the scheduler handles resources which are
+/// read by systems but written by none of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadOnlyResourcePolicy {
+    /// Reads of such resources are reference-counted like any other.
+    /// This is the default.
+    RefCount,
+    /// Such resources are immutable during dispatch, so reads of them are
+    /// not tracked at all. This skips their checks when acquiring resources
+    /// for a task, at the cost of excluding them from diagnostics such
+    /// as `Scheduler::trace_resource()`.
+    Immutable,
+}
+
+/// Determines how `SchedulerBuilder::build()` handles systems which
+/// declare no resource or component accesses, which usually indicates
+/// a forgotten accessor or undeclared world access.
+///
+/// Systems added through `SchedulerBuilder::with_empty_access()`
+/// are exempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyAccessPolicy {
+    /// Such systems are accepted. This is the default.
+    Allow,
+    /// A warning is logged for each such system
+    /// if the `log` feature is enabled.
+    Warn,
+    /// `build()` panics if there are any such systems.
+    Deny,
+}
+
+/// Determines how `SchedulerBuilder::build()` handles a resource
+/// registered through `SchedulerBuilder::with_optional_resource()`
+/// when a resource of the same type already exists, such as when
+/// two plugins register the same resource.
+///
+/// Resources registered through `SchedulerBuilder::override_resource()`
+/// always replace existing ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// The new resource replaces the existing one. This is the default.
+    Overwrite,
+    /// The new resource is dropped, keeping the existing one.
+    KeepExisting,
+    /// `SchedulerBuilder::try_build()` returns a `DuplicateResource`
+    /// error, and `SchedulerBuilder::build()` panics.
+    Error,
+}
+
+/// Error returned by `SchedulerBuilder::try_build()` when a resource
+/// is inserted more than once under `DuplicatePolicy::Error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicateResource {
+    id: ResourceId,
+    type_name: &'static str,
+}
+
+impl DuplicateResource {
+    /// Returns the ID of the duplicated resource.
+    pub fn id(&self) -> ResourceId {
+        self.id
+    }
+
+    /// Returns the name of the type of the duplicated resource.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl Display for DuplicateResource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "resource {} was inserted more than once; use `DuplicatePolicy` to allow this",
+            self.type_name
+        )
+    }
 }
 
+impl std::error::Error for DuplicateResource {}
+
+/// A resource registered through `SchedulerBuilder::with_optional_resource()`,
+/// along with the name of its type.
+type OptionalResource = (ResourceId, &'static str, Option<Box<dyn Resource>>);
+
 /// Builder of a stage pipeline.
-#[derive(Default)]
 pub struct SchedulerBuilder {
-    /// Stages which have been created so far. New systems can
-    /// be inserted into existing stages or be added in a new stage.
-    stages: Vec<Stage>,
+    /// Systems which have been added so far, in insertion order.
+    /// These are assembled into stages in `build()`.
+    systems: Vec<Box<dyn RawSystem>>,
     events: EventsBuilder,
+    /// Strategy used to assemble systems into stages.
+    assembler: Box<dyn StageAssembler>,
+    /// Systems registered through `with_skippable()`.
+    skippable: Vec<SystemId>,
+    /// Systems registered through `with_late_system()`.
+    late_systems: Vec<SystemId>,
+    /// Systems registered through `with_label()`, along with their labels.
+    labels: Vec<(String, SystemId)>,
+    /// Pools registered through `with_label_pool()`, along with their labels.
+    label_pools: Vec<(String, Arc<rayon::ThreadPool>)>,
+    /// Budget set through `with_dispatch_budget()`.
+    dispatch_budget: Option<Duration>,
+    /// Rates registered through `with_stage_rate()`.
+    stage_rates: Vec<(usize, u32)>,
+    /// Stage execution time above which a warning is logged.
+    long_stage_threshold: Option<Duration>,
+    /// Timeout set by `with_stall_timeout()`.
+    stall_timeout: Option<Duration>,
+    /// Limit set by `with_stage_limit()`.
+    stage_limit: Option<usize>,
+    /// Resources registered through `with_optional_resource()`.
+    /// The resource is `None` if its condition was false.
+    optional_resources: Vec<OptionalResource>,
+    /// Policy set by `on_duplicate_resource()`.
+    duplicate_policy: DuplicatePolicy,
+    /// Resources registered through `override_resource()`.
+    overrides: Vec<(ResourceId, Box<dyn Resource>)>,
+    /// Functions registered through `with_cached_resource()`.
+    cached_resources: Vec<(ResourceId, Box<dyn Resource>)>,
+    /// Number of warmup dispatches to run in `build()`.
+    warmup: u32,
+    /// Ordering constraints registered through `with_after()`.
+    constraints: Vec<Constraint>,
+    /// Barriers registered through `with_system_barrier_after()`: the system
+    /// each barrier follows, and the number of systems added before it.
+    barriers: Vec<(SystemId, usize)>,
+    /// Hooks registered through `with_resource_scope()`.
+    resource_scopes: Vec<Box<dyn RawResourceScope>>,
+    /// Systems registered through `with_system_if_resource()`
+    /// along with the resource which gates each of them.
+    resource_gates: Vec<(SystemId, ResourceId)>,
+    /// Extractors registered through `register_extractor()`.
+    extractors: Vec<ExtractFn>,
+    /// Policy for resources which no system writes.
+    read_only_policy: ReadOnlyResourcePolicy,
+    /// Policy for systems which declare no accesses.
+    empty_access_policy: EmptyAccessPolicy,
+    /// Systems registered through `with_empty_access()`.
+    empty_access_allowed: Vec<SystemId>,
+    /// Whether resource conflicts are ignored when assembling stages.
+    /// Set by `infer_stages_from_ordering_constraints_only()`.
+    ordering_constraints_only: bool,
+    /// Label set by `with_debug_label()`.
+    debug_label: Option<&'static str>,
+    /// Handler set by `with_unhandled_event_handler()`.
+    unhandled_event_handler: Option<UnhandledEventHandler>,
+    /// Resources registered through `with_checkpointed_resource()`.
+    checkpointed: Vec<CheckpointedResource>,
+}
+
+impl Default for SchedulerBuilder {
+    fn default() -> Self {
+        Self {
+            systems: vec![],
+            events: EventsBuilder::default(),
+            assembler: Box::new(GreedyAssembler),
+            skippable: vec![],
+            late_systems: vec![],
+            labels: vec![],
+            label_pools: vec![],
+            dispatch_budget: None,
+            stage_rates: vec![],
+            long_stage_threshold: None,
+            stall_timeout: None,
+            stage_limit: None,
+            optional_resources: vec![],
+            duplicate_policy: DuplicatePolicy::Overwrite,
+            overrides: vec![],
+            cached_resources: vec![],
+            warmup: 0,
+            constraints: vec![],
+            barriers: vec![],
+            resource_scopes: vec![],
+            resource_gates: vec![],
+            extractors: vec![],
+            read_only_policy: ReadOnlyResourcePolicy::RefCount,
+            empty_access_policy: EmptyAccessPolicy::Allow,
+            empty_access_allowed: vec![],
+            ordering_constraints_only: false,
+            debug_label: None,
+            unhandled_event_handler: None,
+            checkpointed: vec![],
+        }
+    }
 }
 
 impl SchedulerBuilder {
@@ -107,23 +322,12 @@ impl SchedulerBuilder {
             system.name(),
         );
 
-        if let Some(stage) = self
-            .stages
-            .iter_mut()
-            .find(|stage| !stage.conflicts_with(&*system))
-        {
-            stage.add(system);
-        } else {
-            // Create new stage.
-            let mut new_stage = Stage::new();
-            new_stage.add(system);
-            self.stages.push(new_stage);
-        }
+        self.systems.push(system);
     }
 
     /// Adds a system to the stage pipeline.
     pub fn add<S: System + 'static>(&mut self, system: S) {
-        let system = CachedSystem::new(system, "null");
+        let system = CachedSystem::new(system, std::any::type_name::<S>());
 
         self.add_boxed(Box::new(system));
     }
@@ -135,20 +339,811 @@ impl SchedulerBuilder {
         self
     }
 
+    /// Adds a system to the stage pipeline which is only included
+    /// in the built scheduler if the resource of type `R` exists.
+    ///
+    /// Presence is checked at the start of `build()`, against the `Resources`
+    /// passed to it along with any optional or overridden resources.
+    /// Resources inserted later, such as those inserted automatically
+    /// on the first dispatch, do not enable the system.
+    pub fn with_system_if_resource<R, S>(mut self, system: S) -> Self
+    where
+        R: Resource,
+        S: System + 'static,
+    {
+        let system = CachedSystem::new(system, std::any::type_name::<S>());
+        self.resource_gates
+            .push((system.id(), resource_id_for::<R>()));
+        self.add_boxed(Box::new(system));
+        self
+    }
+
+    /// Adds a non-critical system to the stage pipeline which
+    /// is skipped if it has not yet started when the dispatch
+    /// exceeds the budget set through `with_dispatch_budget()`.
+    pub fn with_skippable<S: System + 'static>(mut self, system: S) -> Self {
+        let system = CachedSystem::new(system, std::any::type_name::<S>());
+        self.skippable.push(system.id());
+        self.add_boxed(Box::new(system));
+        self
+    }
+
+    /// Adds a system which runs after all other systems, such as
+    /// one which exports metrics or cleans up at the end of a frame.
+    ///
+    /// Late systems are assembled into stages appended after all other
+    /// stages, and do not start until all other systems have completed.
+    /// They share a stage unless they conflict with each other.
+    ///
+    /// # Panics
+    /// `build()` panics if an ordering constraint requires
+    /// a late system to run before another system.
+    pub fn with_late_system<S: System + 'static>(mut self, system: S) -> Self {
+        let system = CachedSystem::new(system, std::any::type_name::<S>());
+        self.late_systems.push(system.id());
+        self.add_boxed(Box::new(system));
+        self
+    }
+
+    /// Adds a system to the stage pipeline with the given label.
+    ///
+    /// The system runs in every dispatch like any other, and can
+    /// additionally be run along with the other systems with the
+    /// same label through `Scheduler::execute_labeled()`.
+    pub fn with_label<S: System + 'static>(mut self, label: &str, system: S) -> Self {
+        let system = CachedSystem::new(system, std::any::type_name::<S>());
+        self.labels.push((label.to_owned(), system.id()));
+        self.add_boxed(Box::new(system));
+        self
+    }
+
+    /// Runs the systems added with the given label through `with_label()`
+    /// on `pool` rather than on the thread pool running the dispatch.
+    ///
+    /// On machines with several NUMA nodes, giving each group of related
+    /// systems a pool whose threads are pinned to one node keeps the data
+    /// they share in that node's caches. Systems in the same stage still
+    /// run concurrently, with each group on its own pool. Serial stages,
+    /// and dispatches through `Scheduler::execute_canonical()`, run all
+    /// of a stage's systems on the thread running the stage.
+    ///
+    /// # Panics
+    /// `build()` panics if no system has the given label.
+    pub fn with_label_pool(mut self, label: &str, pool: Arc<rayon::ThreadPool>) -> Self {
+        self.label_pools.push((label.to_owned(), pool));
+        self
+    }
+
+    /// Sets the time budget of each dispatch. Once a dispatch has
+    /// taken longer than `budget`, systems added through `with_skippable()`
+    /// which have not yet started are skipped for the rest of the dispatch,
+    /// so that the dispatch finishes sooner. Other systems always run.
+    ///
+    /// Skipped systems are counted in `DispatchStats::skipped_systems`.
+    pub fn with_dispatch_budget(mut self, budget: Duration) -> Self {
+        self.dispatch_budget = Some(budget);
+        self
+    }
+
+    /// Sets the strategy used to assemble systems into stages.
+    ///
+    /// The default is `GreedyAssembler`.
+    pub fn with_assembler(mut self, assembler: Box<dyn StageAssembler>) -> Self {
+        self.assembler = assembler;
+        self
+    }
+
+    /// Sets the algorithm used to partition systems into stages.
+    ///
+    /// This is a shorthand for `with_assembler()` with one
+    /// of the built-in assemblers.
+    pub fn with_constraint_solver(self, algorithm: ConstraintAlgorithm) -> Self {
+        self.with_assembler(algorithm.assembler())
+    }
+
+    /// Requires the system `system` to run in a later stage than
+    /// the system `dependency`, and not to start until `dependency`
+    /// has completed, even if they do not conflict.
+    ///
+    /// # Panics
+    /// `build()` panics if the ordering constraints contain a cycle.
+    pub fn with_after(mut self, system: SystemId, dependency: SystemId) -> Self {
+        self.constraints.push(Constraint {
+            before: dependency,
+            after: system,
+        });
+        self
+    }
+
+    /// Adds the systems added by `f` as an ordered group, in which each
+    /// system runs in a later stage than the system added before it,
+    /// even if they do not conflict.
+    ///
+    /// This is a shorthand for calling `with_after()` for each pair
+    /// of consecutively added systems. Systems outside the group
+    /// are still placed in parallel with those in the group.
+    pub fn with_ordered_group(self, f: impl FnOnce(Self) -> Self) -> Self {
+        let start = self.systems.len();
+        let mut builder = f(self);
+
+        let ids: Vec<SystemId> = builder.systems[start..]
+            .iter()
+            .map(|system| system.id())
+            .collect();
+        for pair in ids.windows(2) {
+            builder = builder.with_after(pair[1], pair[0]);
+        }
+
+        builder
+    }
+
+    /// Inserts a barrier after the stage containing the previously
+    /// added system of type `S`. All systems added after this call
+    /// run in later stages than that system, and do not start until
+    /// it has completed.
+    ///
+    /// Unlike ordering systems by their position, this does not depend
+    /// on the order in which other systems were added before the call.
+    ///
+    /// # Panics
+    /// Panics if no system of type `S` has been added, or if more than one has.
+    pub fn with_system_barrier_after<S: System + 'static>(mut self) -> Self {
+        let mut matching = self
+            .systems
+            .iter()
+            .filter(|system| system.system_type_id() == Some(TypeId::of::<S>()));
+
+        let system = match (matching.next(), matching.next()) {
+            (Some(system), None) => system.id(),
+            (Some(_), Some(_)) => panic!(
+                "more than one system has type {}",
+                std::any::type_name::<S>()
+            ),
+            (None, _) => panic!(
+                "no system of type {} has been added",
+                std::any::type_name::<S>()
+            ),
+        };
+
+        self.barriers.push((system, self.systems.len()));
+        self
+    }
+
+    /// Assembles stages using only the ordering constraints registered
+    /// through `with_after()`, ignoring resource conflicts between systems.
+    /// Each system is placed in the earliest stage after all of its dependencies.
+    ///
+    /// # Safety
+    /// Systems in the same stage run in parallel without any
+    /// further synchronization, so the constraints must ensure that
+    /// no two systems which access the same resource or component,
+    /// where at least one of them writes it, end up in the same stage.
+    pub unsafe fn infer_stages_from_ordering_constraints_only(mut self) -> Self {
+        self.ordering_constraints_only = true;
+        self
+    }
+
+    /// Registers a hook which temporarily replaces the resource of type `T`
+    /// while any stage, oneshot system or end-of-tick event handler which
+    /// accesses it runs.
+    ///
+    /// `scope_fn` is called on the scheduler thread with the current value
+    /// of the resource and a `run_stage` function. `run_stage` executes
+    /// the stage or task with the resource set to the value passed to it,
+    /// then returns that value. The value returned by `scope_fn` is stored as
+    /// the resource afterwards. The task is skipped if `run_stage` is not called.
+    ///
+    /// Scoped tasks do not run concurrently with any other task.
+    pub fn with_resource_scope<T, F>(mut self, scope_fn: F) -> Self
+    where
+        T: Resource + Default,
+        F: FnMut(T, &mut dyn FnMut(T) -> T) -> T + Send + Sync + 'static,
+    {
+        self.resource_scopes
+            .push(Box::new(ResourceScope::<T, F>::new(scope_fn)));
+        self
+    }
+
+    /// Registers an extractor which derives resources from the `World`
+    /// at the start of each dispatch, before any system runs.
+    ///
+    /// Extractors run in the order in which they were registered.
+    pub fn register_extractor<E: WorldResourceExtractor>(mut self) -> Self {
+        self.extractors.push(E::extract);
+        self
+    }
+
+    /// Includes the resource `T` in the state captured by
+    /// `Scheduler::checkpoint()` and restored by `Scheduler::restore()`.
+    pub fn with_checkpointed_resource<T: Resource + Clone>(mut self) -> Self {
+        self.checkpointed.push(CheckpointedResource::new::<T>());
+        self
+    }
+
+    /// Sets how `build()` handles a resource registered through
+    /// `with_optional_resource()` when a resource of the same type
+    /// was passed to `build()` or registered earlier.
+    ///
+    /// The default is `DuplicatePolicy::Overwrite`.
+    pub fn on_duplicate_resource(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Sets the policy for resources and components which are read
+    /// by systems but written by no system or event handler.
+    ///
+    /// The default is `ReadOnlyResourcePolicy::RefCount`.
+    pub fn with_read_only_policy(mut self, policy: ReadOnlyResourcePolicy) -> Self {
+        self.read_only_policy = policy;
+        self
+    }
+
+    /// Sets the policy for systems which declare no resource
+    /// or component accesses.
+    ///
+    /// The default is `EmptyAccessPolicy::Allow`.
+    pub fn with_empty_access_policy(mut self, policy: EmptyAccessPolicy) -> Self {
+        self.empty_access_policy = policy;
+        self
+    }
+
+    /// Sets a label identifying the scheduler, for use when
+    /// several schedulers exist, such as one for physics and
+    /// one for rendering.
+    ///
+    /// The label is included in the scheduler's panic messages,
+    /// `tracing` spans and profiling output, and is used by
+    /// its `Display` implementation.
+    pub fn with_debug_label(mut self, label: &'static str) -> Self {
+        self.debug_label = Some(label);
+        self
+    }
+
+    /// Sets a handler which is called for each triggered event
+    /// of a type for which no event handler is registered, such
+    /// as for logging events which were forgotten about.
+    ///
+    /// The handler is called on the scheduler thread with the ID
+    /// and value of each event once they have been triggered.
+    pub fn with_unhandled_event_handler(
+        mut self,
+        handler: impl Fn(EventId, &dyn Any) + Send + Sync + 'static,
+    ) -> Self {
+        self.unhandled_event_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Adds a system which intentionally declares no accesses,
+    /// exempting it from the policy set through `with_empty_access_policy()`.
+    pub fn with_empty_access<S: System + 'static>(mut self, system: S) -> Self {
+        let system = CachedSystem::new(system, std::any::type_name::<S>());
+        self.empty_access_allowed.push(system.id());
+        self.add_boxed(Box::new(system));
+        self
+    }
+
+    /// Runs the stage with index `stage` only on every `rate`th dispatch,
+    /// starting with the first. Stages are indexed in the order in
+    /// which they run, as in `Scheduler::profile_dispatch()`.
+    ///
+    /// This is useful for work which can be updated less frequently,
+    /// such as AI of far-away entities.
+    ///
+    /// # Panics
+    /// Panics if `stage` is 0, since the first stage always runs, or if
+    /// `rate` is 0. `build()` panics if there is no stage with index `stage`.
+    pub fn with_stage_rate(mut self, stage: usize, rate: u32) -> Self {
+        assert_ne!(stage, 0, "the first stage always runs");
+        assert_ne!(rate, 0, "stage rate must be positive");
+        self.stage_rates.push((stage, rate));
+        self
+    }
+
+    /// Logs a warning naming the slowest system of any stage
+    /// whose execution takes longer than `threshold`.
+    ///
+    /// Warnings are emitted through the `log` crate, so this
+    /// has no effect unless the `log` feature is enabled.
+    pub fn warn_on_long_stage(mut self, threshold: Duration) -> Self {
+        self.long_stage_threshold = Some(threshold);
+        self
+    }
+
+    /// Guards against a stalled thread pool, such as a custom pool whose
+    /// only thread is the one running the scheduler. If no task completes
+    /// within `timeout` while tasks are running, a task which has not yet
+    /// been picked up by the pool is run inline on the scheduler thread.
+    ///
+    /// Resources held by the task are released as usual once it completes.
+    /// Each spawned task is boxed while this is enabled, so it is off
+    /// by default.
+    pub fn with_stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = Some(timeout);
+        self
+    }
+
+    /// Caps the number of stages at `limit`, for targets with few cores
+    /// on which many small stages only add synchronization overhead.
+    ///
+    /// If more stages are assembled, runs of consecutive stages are merged
+    /// until there are at most `limit`. A merged stage may contain systems
+    /// which conflict, so its systems run serially in their original stage
+    /// order rather than in parallel. Resources are still acquired for the
+    /// stage as a whole, so other tasks cannot access them in the meantime.
+    /// Stages which need no merging run in parallel as usual.
+    ///
+    /// The limit also applies when `Scheduler::reschedule()` is called.
+    ///
+    /// # Panics
+    /// Panics if `limit` is 0.
+    pub fn with_stage_limit(mut self, limit: usize) -> Self {
+        assert_ne!(limit, 0, "stage limit must be positive");
+        self.stage_limit = Some(limit);
+        self
+    }
+
+    /// Executes the built scheduler `count` times against an empty
+    /// `World` during `build()`, moving the cost of the first few
+    /// dispatches, such as system initialization, into the build step.
+    ///
+    /// Note that systems still run normally during warmup dispatches,
+    /// so any changes they make to resources are kept. Warmup dispatches
+    /// are counted separately in `Scheduler::stats()`.
+    pub fn with_warmup(mut self, count: u32) -> Self {
+        self.warmup = count;
+        self
+    }
+
+    /// Registers a resource which is only available if `condition` is true,
+    /// such as one which only exists on certain platforms.
+    ///
+    /// If `condition` is true, the resource returned by `resource` is inserted
+    /// into the `Resources` passed to `build()`. Otherwise, any system
+    /// which reads or writes the resource is disabled and does not
+    /// become part of the built scheduler.
+    pub fn with_optional_resource<T: Resource>(
+        mut self,
+        condition: bool,
+        resource: impl FnOnce() -> T,
+    ) -> Self {
+        let resource = if condition {
+            Some(Box::new(resource()) as Box<dyn Resource>)
+        } else {
+            None
+        };
+
+        self.optional_resources.push((
+            resource_id_for::<T>(),
+            std::any::type_name::<T>(),
+            resource,
+        ));
+        self
+    }
+
+    /// Sets when events of type `E` sent through `EventWriter<E>` are dropped.
+    ///
+    /// The default is `EventRetention::Dispatches(2)`.
+    ///
+    /// # Panics
+    /// Panics if `retention` is `EventRetention::Dispatches(0)`,
+    /// since events would be dropped before they could be read.
+    pub fn with_event_retention<E: Resource>(mut self, retention: EventRetention) -> Self {
+        assert_ne!(
+            retention,
+            EventRetention::Dispatches(0),
+            "events must be retained for at least one dispatch"
+        );
+        self.overrides.push((
+            resource_id_for::<EventQueue<E>>(),
+            Box::new(EventQueue::<E>::new(retention)),
+        ));
+        self
+    }
+
+    /// Forces the resource of type `T` to be `value` in the built scheduler,
+    /// replacing any resource of that type in the `Resources` passed to
+    /// `build()` or registered through `with_optional_resource()`.
+    ///
+    /// This is mainly useful in tests which build the real scheduler
+    /// but need to swap in a mock resource.
+    pub fn override_resource<T: Resource>(mut self, value: T) -> Self {
+        self.overrides
+            .push((resource_id_for::<T>(), Box::new(value)));
+        self
+    }
+
+    /// Registers `compute` as the function which computes the resource
+    /// of type `T` from the resources `Deps`, for use by systems which
+    /// access the resource through `Cached<T, Deps>`.
+    ///
+    /// `compute` takes a reference to each resource in `Deps`.
+    pub fn with_cached_resource<T, Deps, F>(mut self, compute: F) -> Self
+    where
+        T: Resource,
+        Deps: CacheDeps,
+        F: Compute<Deps, T>,
+    {
+        self.cached_resources
+            .push((compute_id_for::<T>(), compute.into_compute_fn()));
+        self
+    }
+
+    /// Checks that all resources read or written by the systems
+    /// added so far are present in `resources`, returning the
+    /// resources which are missing.
+    ///
+    /// Note that resources implementing `Default` are inserted
+    /// automatically on the first dispatch, so they are reported
+    /// here if they have not been inserted manually.
+    pub fn check_resource_completeness(&self, resources: &Resources) -> Vec<MissingResource> {
+        let mut missing: Vec<MissingResource> = vec![];
+
+        for system in &self.systems {
+            for resource in system
+                .resource_reads()
+                .iter()
+                .chain(system.resource_writes())
+                .copied()
+                .filter(|resource| !resources.contains_id(*resource))
+            {
+                match missing.iter_mut().find(|m| m.resource_id == resource) {
+                    Some(m) => m.required_by_systems.push(system.id()),
+                    None => missing.push(MissingResource {
+                        resource_id: resource,
+                        required_by_systems: vec![system.id()],
+                    }),
+                }
+            }
+        }
+
+        missing
+    }
+
+    /// Checks the systems added so far for accesses which may have
+    /// been declared incorrectly.
+    ///
+    /// This is best-effort: only systems declared with `#[system]` are
+    /// checked, by looking for calls in the function body which modify
+    /// a resource parameter through interior mutability, such as `set()`
+    /// or `lock()`. Warnings may therefore be both false positives
+    /// and incomplete.
+    pub fn audit_system_dependencies(&self) -> Vec<DependencyWarning> {
+        self.systems
+            .iter()
+            .flat_map(|system| {
+                system
+                    .suspected_writes()
+                    .into_iter()
+                    .filter(move |resource| !system.resource_writes().contains(resource))
+                    .map(move |resource| DependencyWarning::PossibleMissingWrite {
+                        system_id: system.id(),
+                        suspected_resource: resource,
+                    })
+            })
+            .collect()
+    }
+
+    /// Returns an explanation of the stage each system added so far is
+    /// placed in, for finding out why a system runs later than expected.
+    ///
+    /// For each system, this lists the systems in earlier stages which it
+    /// conflicts with, along with the resources and components accessed
+    /// by both, and the systems it is required to run after. Systems which
+    /// `build()` would exclude because of absent resources are included.
+    ///
+    /// `resources` should be the resources which will be passed to
+    /// `build()`, since they determine the members of resource groups.
+    pub fn profile_stage_assignments(&self, resources: &Resources) -> String {
+        let ids: Vec<SystemId> = self.systems.iter().map(|system| system.id()).collect();
+        let expanded: HashMap<SystemId, ExpandedAccesses> = self
+            .systems
+            .iter()
+            .map(|system| (system.id(), expanded_accesses(&**system, resources)))
+            .collect();
+        let conflicts = self.layout_conflicts(resources);
+        let mut constraints = self.constraints.clone();
+        constraints.extend(self.barrier_constraints());
+
+        let stages = limit_stages(
+            assemble_layout(
+                &*self.assembler,
+                &conflicts,
+                &constraints,
+                &ids,
+                &self.late_systems,
+            ),
+            self.stage_limit,
+        );
+
+        let by_id: HashMap<SystemId, &dyn RawSystem> = self
+            .systems
+            .iter()
+            .map(|system| (system.id(), &**system))
+            .collect();
+
+        let mut report = String::new();
+        for (index, stage) in stages.iter().enumerate() {
+            for id in stage {
+                let system = by_id[id];
+                let mut reasons = vec![];
+
+                let conflicting: Vec<&dyn RawSystem> = stages[..index]
+                    .iter()
+                    .flatten()
+                    .map(|other| by_id[other])
+                    .filter(|other| conflicts.conflicts(*id, other.id()))
+                    .collect();
+                if !conflicting.is_empty() {
+                    let names: Vec<&str> = conflicting.iter().map(|other| other.name()).collect();
+                    let mut accesses = vec![];
+                    for other in &conflicting {
+                        let shared = shared_accesses(
+                            (system, &expanded[id]),
+                            (*other, &expanded[&other.id()]),
+                        );
+                        for access in shared {
+                            if !accesses.contains(&access) {
+                                accesses.push(access);
+                            }
+                        }
+                    }
+                    if accesses.is_empty() {
+                        reasons.push(format!("it conflicts with systems {}", names.join(", ")));
+                    } else {
+                        reasons.push(format!(
+                            "it conflicts with systems {} on {}",
+                            names.join(", "),
+                            accesses.join(", ")
+                        ));
+                    }
+                }
+
+                let dependencies: Vec<&str> = constraints
+                    .iter()
+                    .filter(|constraint| constraint.after == *id)
+                    .filter_map(|constraint| by_id.get(&constraint.before))
+                    .map(|dependency| dependency.name())
+                    .collect();
+                if !dependencies.is_empty() {
+                    reasons.push(format!(
+                        "it must run after systems {}",
+                        dependencies.join(", ")
+                    ));
+                }
+
+                if self.late_systems.contains(id) {
+                    reasons.push("it is a late system".to_owned());
+                }
+
+                if reasons.is_empty() {
+                    report += &format!("System {} was placed in stage {}.\n", system.name(), index);
+                } else {
+                    report += &format!(
+                        "System {} was placed in stage {} because {}.\n",
+                        system.name(),
+                        index,
+                        reasons.join(" and ")
+                    );
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Returns the conflicts between the systems added so far which are used
+    /// to assemble stages, with resource groups expanded as tagged in
+    /// `resources`. Both `build()` and `profile_stage_assignments()`
+    /// use this, so that the profile matches the built layout.
+    fn layout_conflicts(&self, resources: &Resources) -> Conflicts {
+        let mut conflicts = Conflicts::new();
+        if self.ordering_constraints_only {
+            return conflicts;
+        }
+
+        let systems: Vec<(&dyn RawSystem, bool, ExpandedAccesses)> = self
+            .systems
+            .iter()
+            .map(|system| {
+                let (reads, writes) = group_members(&**system, resources);
+                let in_groups = !reads.is_empty() || !writes.is_empty();
+                (
+                    &**system,
+                    in_groups,
+                    expanded_accesses(&**system, resources),
+                )
+            })
+            .collect();
+
+        for (index, (a, a_in_groups, (a_reads, a_writes))) in systems.iter().enumerate() {
+            for (b, b_in_groups, (b_reads, b_writes)) in &systems[index + 1..] {
+                // Systems which access groups conflict on the group members,
+                // as they do once wrapped by `expand_groups()`.
+                let groups_conflict = (*a_in_groups || *b_in_groups)
+                    && slices_conflict(a_reads, a_writes, b_reads, b_writes);
+
+                if a.conflicts_with(*b) || b.conflicts_with(*a) || groups_conflict {
+                    conflicts.insert(a.id(), b.id());
+                }
+            }
+        }
+
+        conflicts
+    }
+
+    /// Returns the ordering constraints which implement the
+    /// barriers registered through `with_system_barrier_after()`.
+    fn barrier_constraints(&self) -> Vec<Constraint> {
+        self.barriers
+            .iter()
+            .flat_map(|(barrier, index)| {
+                self.systems[*index..].iter().map(move |system| Constraint {
+                    before: *barrier,
+                    after: system.id(),
+                })
+            })
+            .collect()
+    }
+
+    /// Applies the `EmptyAccessPolicy` to the systems added so far.
+    fn check_empty_access(&self) {
+        if self.empty_access_policy == EmptyAccessPolicy::Allow {
+            return;
+        }
+
+        let empty: Vec<&str> = self
+            .systems
+            .iter()
+            .filter(|system| {
+                system.resource_reads().is_empty()
+                    && system.resource_writes().is_empty()
+                    && system.component_reads().is_empty()
+                    && system.component_writes().is_empty()
+                    && !self.empty_access_allowed.contains(&system.id())
+            })
+            .map(|system| system.name())
+            .collect();
+
+        if empty.is_empty() {
+            return;
+        }
+
+        match self.empty_access_policy {
+            EmptyAccessPolicy::Deny => panic!(
+                "{}: systems declare no resource or component accesses: {}",
+                DebugLabel(self.debug_label),
+                empty.join(", ")
+            ),
+            EmptyAccessPolicy::Warn => {
+                #[cfg(feature = "log")]
+                {
+                    for name in &empty {
+                        log::warn!("system {} declares no resource or component accesses", name);
+                    }
+                }
+            }
+            EmptyAccessPolicy::Allow => (),
+        }
+    }
+
     /// Creates a new `Scheduler` based on the stage pipeline
     /// which was built.
+    ///
+    /// # Panics
+    /// Panics if a resource is inserted more than
+    /// once under `DuplicatePolicy::Error`.
     pub fn build(self, resources: Resources) -> Scheduler {
+        self.try_build(resources)
+            .unwrap_or_else(|duplicate| panic!("{}", duplicate))
+    }
+
+    /// Builds the scheduler like `build()`, but returns an error
+    /// rather than panicking if a resource is inserted more than
+    /// once under `DuplicatePolicy::Error`.
+    pub fn try_build(mut self, mut resources: Resources) -> Result<Scheduler, DuplicateResource> {
+        let barriers = self.barrier_constraints();
+        self.constraints.extend(barriers);
+
+        let mut absent = vec![];
+        for (id, type_name, resource) in self.optional_resources.drain(..) {
+            let resource = match resource {
+                Some(resource) => resource,
+                None => {
+                    absent.push(id);
+                    continue;
+                }
+            };
+
+            if resources.contains_id(id) {
+                match self.duplicate_policy {
+                    DuplicatePolicy::Overwrite => (),
+                    DuplicatePolicy::KeepExisting => continue,
+                    DuplicatePolicy::Error => return Err(DuplicateResource { id, type_name }),
+                }
+            }
+            resources.insert_boxed(id, resource);
+        }
+
+        for (id, compute) in self.cached_resources.drain(..) {
+            resources.insert_boxed(id, compute);
+        }
+
+        for (id, resource) in self.overrides.drain(..) {
+            absent.retain(|absent| *absent != id);
+            resources.insert_boxed(id, resource);
+        }
+
+        // Disable systems which depend on absent optional resources.
+        self.systems.retain(|system| {
+            !system
+                .resource_reads()
+                .iter()
+                .chain(system.resource_writes())
+                .any(|resource| absent.contains(resource))
+        });
+
+        // Disable systems whose gating resource is absent.
+        let gated_out: Vec<SystemId> = self
+            .resource_gates
+            .iter()
+            .filter(|(_, resource)| !resources.contains_id(*resource))
+            .map(|(system, _)| *system)
+            .collect();
+        self.systems
+            .retain(|system| !gated_out.contains(&system.id()));
+
+        self.check_empty_access();
+
+        let ids: Vec<SystemId> = self.systems.iter().map(|system| system.id()).collect();
+        let conflicts = self.layout_conflicts(&resources);
+
+        self.systems = self
+            .systems
+            .into_iter()
+            .map(|system| expand_groups(system, &resources))
+            .collect();
+        let mut conflict_writes = custom_conflict_writes(&self.systems);
+        let (mut ordering_reads, ordering_writes) =
+            ordering_accesses(&ids, &self.constraints, &self.late_systems);
+        for (id, writes) in ordering_writes {
+            conflict_writes.entry(id).or_default().extend(writes);
+        }
+
+        let layout = StageLayout {
+            assembler: self.assembler,
+            conflicts,
+            constraints: self.constraints,
+            systems: ids,
+            late: self.late_systems,
+            stage_limit: self.stage_limit,
+        };
+        let stage_ids = layout.assemble(|_| true);
+
+        let mut by_id: HashMap<SystemId, Box<dyn RawSystem>> = self
+            .systems
+            .into_iter()
+            .map(|system| (system.id(), system))
+            .collect();
+
         let mut systems = vec![];
         let mut reads = vec![];
         let mut writes = vec![];
 
-        for stage in self.stages {
-            for system in &stage.systems {
+        for stage in stage_ids {
+            let stage: Vec<_> = stage
+                .into_iter()
+                .map(|id| by_id.remove(&id).unwrap())
+                .collect();
+
+            for system in &stage {
                 let mut system_reads = vec![];
                 let mut system_writes = vec![];
 
                 system_reads.extend(system.resource_reads().iter().copied());
+                system_reads.extend(ordering_reads.remove(&system.id()).unwrap_or_default());
                 system_writes.extend(system.resource_writes().iter().copied());
+                system_writes.extend(conflict_writes.remove(&system.id()).unwrap_or_default());
 
                 // Map component to resource IDs
                 system_reads.extend(
@@ -168,12 +1163,33 @@ impl SchedulerBuilder {
                 writes.push(system_writes);
             }
 
-            systems.push(stage.systems);
+            systems.push(stage);
+        }
+
+        if self.read_only_policy == ReadOnlyResourcePolicy::Immutable {
+            let written: HashSet<ResourceId> = writes
+                .iter()
+                .flatten()
+                .copied()
+                .chain(
+                    self.events
+                        .end_of_dispatch
+                        .iter()
+                        .flatten()
+                        .flat_map(|handler| handler.resource_writes().iter().copied()),
+                )
+                // Resource scopes replace their resource between stages.
+                .chain(self.resource_scopes.iter().map(|scope| scope.resource_id()))
+                .collect();
+
+            for system_reads in &mut reads {
+                system_reads.retain(|resource| written.contains(resource));
+            }
         }
 
         // Safety: the builder must work correctly to ensure
         // that stages are correct.
-        unsafe {
+        let mut scheduler = unsafe {
             Scheduler::new(
                 systems,
                 self.events.end_of_dispatch,
@@ -181,90 +1197,226 @@ impl SchedulerBuilder {
                 writes,
                 resources,
             )
+        };
+        for (stage, rate) in self.stage_rates {
+            assert!(
+                stage < scheduler.stage_rates.len(),
+                "stage {} does not exist",
+                stage
+            );
+            scheduler.stage_rates[stage] = rate;
+        }
+        scheduler.skippable = self.skippable.iter().map(|id| id.0).collect();
+        scheduler.dispatch_budget = self.dispatch_budget;
+        for (label, id) in self.labels {
+            scheduler.labels.entry(label).or_default().push(id);
         }
+        let mut system_pools = HashMap::new();
+        for (label, pool) in self.label_pools {
+            let ids = scheduler
+                .labels
+                .get(&label)
+                .unwrap_or_else(|| panic!("no system has the label \"{}\"", label));
+            for id in ids {
+                system_pools.insert(id.0, Arc::clone(&pool));
+            }
+        }
+        scheduler.system_pools = Arc::new(system_pools);
+        scheduler.long_stage_threshold = self.long_stage_threshold;
+        scheduler.stall_timeout = self.stall_timeout;
+        scheduler.resource_scopes = self.resource_scopes;
+        scheduler.extractors = self.extractors;
+        scheduler.layout = layout;
+        scheduler.serial_stages = scheduler.find_serial_stages();
+        scheduler.debug_label = self.debug_label;
+        scheduler.unhandled_event_handler = self.unhandled_event_handler;
+        scheduler.checkpointed = self.checkpointed;
+        scheduler.warmup(self.warmup);
+        Ok(scheduler)
     }
-}
 
-/// A stage of a stage builder.
-struct Stage {
-    /// Vector of items in this stage.
-    systems: Vec<Box<dyn RawSystem>>,
-    /// Set of resources which are read by this stage.
-    reads: HashSet<Access>,
-    /// Set of resources which are written by this stage.
-    writes: HashSet<Access>,
+    /// Creates a new `Scheduler` as with `build()`, wrapped
+    /// in an `Arc<Mutex<_>>` so that it can be shared.
+    pub fn build_arc(self, resources: Resources) -> Arc<Mutex<Scheduler>> {
+        Arc::new(Mutex::new(self.build(resources)))
+    }
 }
 
-impl Default for Stage {
-    fn default() -> Self {
-        Self {
-            systems: vec![],
-            reads: HashSet::new(),
-            writes: HashSet::new(),
+/// Computes the pairwise conflicts between systems.
+/// Allocates a synthetic resource for each pair of systems which
+/// conflict only through `RawSystem::conflicts_with()`. Both systems
+/// write the resource, so the scheduler never runs them concurrently,
+/// even when their stages could otherwise overlap.
+fn custom_conflict_writes(systems: &[Box<dyn RawSystem>]) -> HashMap<SystemId, Vec<ResourceId>> {
+    let mut writes: HashMap<SystemId, Vec<ResourceId>> = HashMap::new();
+
+    for (index, a) in systems.iter().enumerate() {
+        for b in &systems[index + 1..] {
+            if (a.conflicts_with(&**b) || b.conflicts_with(&**a)) && !accesses_conflict(&**a, &**b)
+            {
+                let resource = resource_id_for_conflict(a.id(), b.id());
+                writes.entry(a.id()).or_default().push(resource);
+                writes.entry(b.id()).or_default().push(resource);
+            }
         }
     }
+
+    writes
 }
 
-impl Stage {
-    /// Creates a new, empty stage.
-    pub fn new() -> Self {
-        Self::default()
+type SyntheticAccesses = HashMap<SystemId, Vec<ResourceId>>;
+
+/// Allocates synthetic resources which keep systems from starting
+/// before the systems they are required to run after have completed.
+///
+/// Placing a system in a later stage is not enough on its own, since
+/// a stage starts as soon as its resources are available. Each system
+/// which others must run after writes a resource which those systems
+/// read, and late systems write a resource which all others read.
+/// Stages start in order, so an excluded stage waits for the earlier
+/// one to complete, while systems which must run after the same
+/// system can still run concurrently.
+///
+/// Returns the synthetic reads and writes of each system.
+fn ordering_accesses(
+    systems: &[SystemId],
+    constraints: &[Constraint],
+    late: &[SystemId],
+) -> (SyntheticAccesses, SyntheticAccesses) {
+    let mut reads: SyntheticAccesses = HashMap::new();
+    let mut writes: SyntheticAccesses = HashMap::new();
+
+    for constraint in constraints {
+        if !systems.contains(&constraint.before) || !systems.contains(&constraint.after) {
+            continue;
+        }
+
+        let resource = resource_id_for_ordering(constraint.before);
+        let before = writes.entry(constraint.before).or_default();
+        if !before.contains(&resource) {
+            before.push(resource);
+        }
+        let after = reads.entry(constraint.after).or_default();
+        if !after.contains(&resource) {
+            after.push(resource);
+        }
+    }
+
+    if systems.iter().any(|id| late.contains(id)) {
+        let resource = resource_id_for_late_stages();
+        for id in systems {
+            if late.contains(id) {
+                writes.entry(*id).or_default().push(resource);
+            } else {
+                reads.entry(*id).or_default().push(resource);
+            }
+        }
     }
 
-    /// Returns whether the given system conflicts with this stage.
-    pub fn conflicts_with(&self, system: &dyn RawSystem) -> bool {
-        system
-            .resource_reads()
+    (reads, writes)
+}
+
+/// Returns descriptions of the resources and components
+/// through which the accesses of two systems conflict.
+fn shared_accesses(
+    (a, a_accesses): (&dyn RawSystem, &ExpandedAccesses),
+    (b, b_accesses): (&dyn RawSystem, &ExpandedAccesses),
+) -> Vec<String> {
+    fn conflicting<T: PartialEq + Copy>(
+        a_reads: &[T],
+        a_writes: &[T],
+        b_reads: &[T],
+        b_writes: &[T],
+    ) -> Vec<T> {
+        let mut shared: Vec<T> = a_writes
             .iter()
+            .filter(|access| b_reads.contains(access) || b_writes.contains(access))
             .copied()
-            .any(|resource| self.writes.contains(&Access::Resource(resource)))
-            || system.resource_writes().iter().copied().any(|resource| {
-                self.reads.contains(&Access::Resource(resource))
-                    || self.writes.contains(&Access::Resource(resource))
-            })
-            || system
-                .component_reads()
+            .collect();
+        shared.extend(
+            b_writes
                 .iter()
-                .copied()
-                .any(|component| self.writes.contains(&Access::Component(component)))
-            || system.component_writes().iter().copied().any(|component| {
-                self.reads.contains(&Access::Component(component))
-                    || self.writes.contains(&Access::Component(component))
-            })
+                .filter(|access| a_reads.contains(access))
+                .copied(),
+        );
+        shared
     }
 
-    /// Adds a system to this stage.
-    pub fn add(&mut self, system: Box<dyn RawSystem>) {
-        system
-            .resource_reads()
-            .iter()
-            .copied()
-            .for_each(|resource| {
-                self.reads.insert(Access::Resource(resource));
-            });
-        system
-            .resource_writes()
-            .iter()
-            .copied()
-            .for_each(|resource| {
-                self.writes.insert(Access::Resource(resource));
-            });
-        system
-            .component_reads()
-            .iter()
-            .copied()
-            .for_each(|component| {
-                self.reads.insert(Access::Component(component));
-            });
-        system
-            .component_writes()
+    let resources = conflicting(&a_accesses.0, &a_accesses.1, &b_accesses.0, &b_accesses.1);
+    let components = conflicting(
+        a.component_reads(),
+        a.component_writes(),
+        b.component_reads(),
+        b.component_writes(),
+    );
+
+    resources
+        .into_iter()
+        .map(|resource| format!("resource {:?}", resource))
+        .chain(
+            components
+                .into_iter()
+                .map(|component| format!("component {:?}", component)),
+        )
+        .collect()
+}
+
+/// Resources read and written by a system, including
+/// the members of the resource groups it accesses.
+type ExpandedAccesses = (Vec<ResourceId>, Vec<ResourceId>);
+
+/// Returns the resources tagged into the groups which
+/// `system` reads and writes, respectively.
+fn group_members(system: &dyn RawSystem, resources: &Resources) -> ExpandedAccesses {
+    let members = |accesses: &[ResourceId]| -> Vec<ResourceId> {
+        accesses
             .iter()
+            .flat_map(|access| resources.group_members(*access))
             .copied()
-            .for_each(|component| {
-                self.writes.insert(Access::Component(component));
-            });
-        self.systems.push(system);
+            .collect()
+    };
+    (
+        members(system.resource_reads()),
+        members(system.resource_writes()),
+    )
+}
+
+/// Returns the resources which `system` reads and writes once wrapped by
+/// `expand_groups()`. A resource which is written is not also read.
+fn expanded_accesses(system: &dyn RawSystem, resources: &Resources) -> ExpandedAccesses {
+    let (group_reads, group_writes) = group_members(system, resources);
+
+    let mut writes = system.resource_writes().to_vec();
+    for write in group_writes {
+        if !writes.contains(&write) {
+            writes.push(write);
+        }
+    }
+    let mut reads = system.resource_reads().to_vec();
+    for read in group_reads {
+        if !reads.contains(&read) && !writes.contains(&read) {
+            reads.push(read);
+        }
+    }
+    reads.retain(|read| !writes.contains(read));
+
+    (reads, writes)
+}
+
+/// Extends the accesses of a system which accesses resource groups
+/// with the resources tagged into those groups.
+fn expand_groups(system: Box<dyn RawSystem>, resources: &Resources) -> Box<dyn RawSystem> {
+    let (reads, writes) = group_members(&*system, resources);
+
+    if reads.is_empty() && writes.is_empty() {
+        return system;
     }
+
+    Box::new(GraphSystem::new(SysNode {
+        system,
+        reads,
+        writes,
+    }))
 }
 
 fn assert_valid_deps(reads: &[ResourceId], writes: &[ResourceId], name: &str) {
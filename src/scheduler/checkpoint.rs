@@ -0,0 +1,90 @@
+//! Checkpoints of the deterministic state of a scheduler.
+
+use crate::resources::Resource;
+use crate::{resource_id_for, ResourceId, Resources, Scheduler};
+
+/// Functions which copy a resource registered through
+/// `SchedulerBuilder::with_checkpointed_resource()`.
+#[derive(Clone, Copy)]
+pub(crate) struct CheckpointedResource {
+    pub(crate) id: ResourceId,
+    save: fn(&Resources) -> Option<Box<dyn Resource>>,
+    restore: fn(&mut Resources, &dyn Resource),
+}
+
+impl CheckpointedResource {
+    pub(crate) fn new<T: Resource + Clone>() -> Self {
+        Self {
+            id: resource_id_for::<T>(),
+            save: |resources| {
+                if resources.contains::<T>() {
+                    Some(Box::new(resources.get::<T>().clone()))
+                } else {
+                    None
+                }
+            },
+            // Resources are replaced in place, since systems
+            // hold pointers to them.
+            restore: |resources, value| {
+                let value = value.downcast_ref::<T>().unwrap().clone();
+                if resources.contains::<T>() {
+                    *resources.get_mut::<T>() = value;
+                } else {
+                    resources.insert(value);
+                }
+            },
+        }
+    }
+}
+
+/// The state of a scheduler after a dispatch, as returned
+/// by `Scheduler::checkpoint()`.
+///
+/// This holds copies of the resources registered through
+/// `SchedulerBuilder::with_checkpointed_resource()`, the frame
+/// number seen through `Context` and the dispatch counter.
+/// State held by systems themselves is not included.
+pub struct Checkpoint {
+    resources: Vec<(ResourceId, Box<dyn Resource>)>,
+    next_frame: u64,
+    dispatches: u64,
+}
+
+impl Scheduler {
+    /// Captures the deterministic state of this scheduler, which
+    /// can later be restored with `restore()`.
+    ///
+    /// This is intended for determinism tests, such as verifying that
+    /// two machines in a lockstep game produce identical states.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            resources: self
+                .checkpointed
+                .iter()
+                .filter_map(|resource| Some((resource.id, (resource.save)(&self.resources)?)))
+                .collect(),
+            next_frame: self.frame_clock.next_frame(),
+            dispatches: self.stats.dispatches,
+        }
+    }
+
+    /// Restores the state captured by `checkpoint()`, on this scheduler
+    /// or on one with the same systems and checkpointed resources.
+    ///
+    /// # Panics
+    /// Panics if a resource in the checkpoint was not
+    /// registered as checkpointed with this scheduler.
+    pub fn restore(&mut self, checkpoint: &Checkpoint) {
+        for (id, value) in &checkpoint.resources {
+            let resource = self
+                .checkpointed
+                .iter()
+                .find(|resource| resource.id == *id)
+                .unwrap_or_else(|| panic!("{}: resource {:?} is not checkpointed", self, id));
+            (resource.restore)(&mut self.resources, &**value);
+        }
+
+        self.frame_clock.restore(checkpoint.next_frame);
+        self.stats.dispatches = checkpoint.dispatches;
+    }
+}
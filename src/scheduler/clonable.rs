@@ -0,0 +1,56 @@
+//! Templates of systems from which independent schedulers are built.
+
+use crate::{CachedSystem, RawSystem, Resources, Scheduler, SchedulerBuilder, System};
+
+/// A set of systems which can be cloned to build any number
+/// of independent schedulers, such as one per simulation instance.
+///
+/// Only systems which implement `Clone` can be added.
+#[derive(Default)]
+pub struct ClonableScheduler(Vec<Box<dyn RawSystem>>);
+
+impl ClonableScheduler {
+    /// Creates a new `ClonableScheduler` with no systems.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a system to the template.
+    pub fn add<S: System + Clone>(&mut self, system: S) {
+        self.0.push(Box::new(CachedSystem::new(
+            system,
+            std::any::type_name::<S>(),
+        )));
+    }
+
+    /// Adds a system to the template, returning the
+    /// `ClonableScheduler` for method chaining.
+    pub fn with<S: System + Clone>(mut self, system: S) -> Self {
+        self.add(system);
+        self
+    }
+
+    /// Returns a `SchedulerBuilder` containing clones of all systems
+    /// in the template, which can be configured further before building.
+    ///
+    /// Each clone keeps the `SystemId` of its template.
+    pub fn instantiate(&self) -> SchedulerBuilder {
+        let mut builder = SchedulerBuilder::new();
+        for system in &self.0 {
+            builder.add_boxed(system.clone_dyn());
+        }
+        builder
+    }
+
+    /// Builds a `Scheduler` containing clones of all systems
+    /// in the template.
+    pub fn build(&self, resources: Resources) -> Scheduler {
+        self.instantiate().build(resources)
+    }
+}
+
+impl Clone for ClonableScheduler {
+    fn clone(&self) -> Self {
+        Self(self.0.iter().map(|system| system.clone_dyn()).collect())
+    }
+}
@@ -0,0 +1,22 @@
+//! Resources derived from the `World` before each dispatch.
+
+use crate::Resources;
+use legion::world::World;
+
+/// Derives resources from the `World`, such as the number of
+/// entities with some component.
+///
+/// Extractors are registered through `SchedulerBuilder::register_extractor()`
+/// and run on the calling thread at the start of each dispatch, before any
+/// system runs, in the order in which they were registered.
+///
+/// Systems hold pointers to the resources they access, so a resource which
+/// already exists must be updated in place through `Resources::get_mut()`
+/// rather than inserted again.
+pub trait WorldResourceExtractor: 'static {
+    /// Updates `resources` from the contents of `world`.
+    fn extract(world: &World, resources: &mut Resources);
+}
+
+/// An extractor function registered with the scheduler.
+pub(crate) type ExtractFn = fn(&World, &mut Resources);
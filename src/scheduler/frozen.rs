@@ -0,0 +1,39 @@
+//! A scheduler which can be executed but no longer modified.
+
+use crate::{Event, Resources, Scheduler};
+use legion::world::World;
+
+/// A `Scheduler` which can no longer be modified.
+///
+/// Only execution and read-only introspection are exposed,
+/// which prevents accidental changes to the schedule or its
+/// resources on the hot path. Created by `Scheduler::freeze()`.
+#[derive(Debug)]
+pub struct FrozenScheduler {
+    inner: Scheduler,
+}
+
+impl FrozenScheduler {
+    pub(crate) fn new(inner: Scheduler) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the `Resources` for this scheduler.
+    pub fn resources(&self) -> &Resources {
+        self.inner.resources()
+    }
+
+    /// Executes all systems and handles events.
+    pub fn execute(&mut self, world: &mut World) {
+        self.inner.execute(world);
+    }
+
+    /// Triggers an event manually. It will be handled
+    /// on the next call to `execute()`.
+    pub fn trigger<E>(&mut self, event: E)
+    where
+        E: Event,
+    {
+        self.inner.trigger(event);
+    }
+}
@@ -0,0 +1,177 @@
+//! Construction of schedulers from system graphs built by external tools.
+
+use crate::system::accesses_conflict;
+use crate::{RawSystem, ResourceId, Resources, SchedulerBuilder, SystemCtx, SystemId};
+use hashbrown::HashMap;
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::any::{Any, TypeId};
+use std::time::Duration;
+
+/// A node of a system graph passed to `SchedulerBuilder::from_system_graph()`.
+pub struct SysNode {
+    /// The system, which edges refer to by its `RawSystem::system_type_id()`.
+    pub system: Box<dyn RawSystem>,
+    /// Resources read by the system, in addition to
+    /// those declared by the system itself.
+    pub reads: Vec<ResourceId>,
+    /// Resources written by the system, in addition to
+    /// those declared by the system itself.
+    pub writes: Vec<ResourceId>,
+}
+
+/// A system whose accesses are extended with those declared in its `SysNode`.
+pub(crate) struct GraphSystem {
+    inner: Box<dyn RawSystem>,
+    reads: Vec<ResourceId>,
+    writes: Vec<ResourceId>,
+}
+
+impl GraphSystem {
+    pub(crate) fn new(node: SysNode) -> Self {
+        let mut reads = node.system.resource_reads().to_vec();
+        let mut writes = node.system.resource_writes().to_vec();
+
+        for write in node.writes {
+            if !writes.contains(&write) {
+                writes.push(write);
+            }
+        }
+        // A resource which is written need not also be read.
+        for read in node.reads {
+            if !reads.contains(&read) && !writes.contains(&read) {
+                reads.push(read);
+            }
+        }
+        reads.retain(|read| !writes.contains(read));
+
+        Self {
+            inner: node.system,
+            reads,
+            writes,
+        }
+    }
+}
+
+impl RawSystem for GraphSystem {
+    fn id(&self) -> SystemId {
+        self.inner.id()
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn resource_reads(&self) -> &[ResourceId] {
+        &self.reads
+    }
+
+    fn resource_writes(&self) -> &[ResourceId] {
+        &self.writes
+    }
+
+    fn component_reads(&self) -> &[ComponentTypeId] {
+        self.inner.component_reads()
+    }
+
+    fn component_writes(&self) -> &[ComponentTypeId] {
+        self.inner.component_writes()
+    }
+
+    fn init(&mut self, resources: &mut Resources, ctx: SystemCtx, world: &World) {
+        self.inner.init(resources, ctx, world);
+    }
+
+    unsafe fn execute_raw(&mut self, resources: &Resources, ctx: SystemCtx, world: &World) {
+        self.inner.execute_raw(resources, ctx, world);
+    }
+
+    unsafe fn execute_profiled(
+        &mut self,
+        resources: &Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) -> Vec<(&'static str, Duration)> {
+        self.inner.execute_profiled(resources, ctx, world)
+    }
+
+    fn is_defunct(&self) -> bool {
+        self.inner.is_defunct()
+    }
+
+    fn conflicts_with(&self, other: &dyn RawSystem) -> bool {
+        accesses_conflict(self, other) || self.inner.conflicts_with(other)
+    }
+
+    fn suspected_writes(&self) -> Vec<ResourceId> {
+        self.inner.suspected_writes()
+    }
+
+    fn system_type_id(&self) -> Option<TypeId> {
+        self.inner.system_type_id()
+    }
+
+    fn take_any(self: Box<Self>) -> Option<Box<dyn Any + Send>> {
+        self.inner.take_any()
+    }
+
+    fn take_data(&mut self) -> Option<Box<dyn Any + Send>> {
+        self.inner.take_data()
+    }
+
+    fn restore_data(&mut self, data: Box<dyn Any + Send>) -> Result<(), Box<dyn Any + Send>> {
+        self.inner.restore_data(data)
+    }
+
+    fn clone_dyn(&self) -> Box<dyn RawSystem> {
+        Box::new(GraphSystem {
+            inner: self.inner.clone_dyn(),
+            reads: self.reads.clone(),
+            writes: self.writes.clone(),
+        })
+    }
+}
+
+impl SchedulerBuilder {
+    /// Creates a `SchedulerBuilder` from a system graph, such as one
+    /// generated by a visual editor.
+    ///
+    /// Each edge `(before, after)` requires the system whose type has
+    /// the `TypeId` `before` to complete before the system whose type
+    /// has the `TypeId` `after` starts, as with
+    /// `SchedulerBuilder::with_after()`. Systems are identified by
+    /// `RawSystem::system_type_id()`, which `CachedSystem` implements.
+    ///
+    /// The returned builder can be configured further before building.
+    ///
+    /// # Panics
+    /// Panics if an edge refers to a type which no node has,
+    /// or to a type which more than one node has.
+    pub fn from_system_graph(nodes: Vec<SysNode>, edges: Vec<(TypeId, TypeId)>) -> Self {
+        let mut by_type: HashMap<TypeId, Option<SystemId>> = HashMap::new();
+        let mut builder = SchedulerBuilder::new();
+
+        for node in nodes {
+            if let Some(type_id) = node.system.system_type_id() {
+                let id = node.system.id();
+                by_type
+                    .entry(type_id)
+                    .and_modify(|existing| *existing = None)
+                    .or_insert(Some(id));
+            }
+            builder.add_boxed(Box::new(GraphSystem::new(node)));
+        }
+
+        let lookup = |type_id: TypeId| match by_type.get(&type_id) {
+            Some(Some(id)) => *id,
+            Some(None) => panic!("more than one system has type {:?}", type_id),
+            None => panic!("no system has type {:?}", type_id),
+        };
+
+        for (before, after) in edges {
+            builder = builder.with_after(lookup(after), lookup(before));
+        }
+
+        builder
+    }
+}
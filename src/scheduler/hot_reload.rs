@@ -0,0 +1,106 @@
+//! Replacement of system implementations in a built scheduler.
+
+use crate::{RawSystem, ResourceId, Resources, SystemCtx, SystemId};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::any::{Any, TypeId};
+use std::time::Duration;
+
+/// A system which replaced another through `Scheduler::hot_reload_systems()`,
+/// taking over the ID of the system it replaced.
+pub(crate) struct ReloadedSystem {
+    pub(crate) id: SystemId,
+    pub(crate) inner: Box<dyn RawSystem>,
+}
+
+impl RawSystem for ReloadedSystem {
+    fn id(&self) -> SystemId {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn resource_reads(&self) -> &[ResourceId] {
+        self.inner.resource_reads()
+    }
+
+    fn resource_writes(&self) -> &[ResourceId] {
+        self.inner.resource_writes()
+    }
+
+    fn component_reads(&self) -> &[ComponentTypeId] {
+        self.inner.component_reads()
+    }
+
+    fn component_writes(&self) -> &[ComponentTypeId] {
+        self.inner.component_writes()
+    }
+
+    fn init(&mut self, resources: &mut Resources, ctx: SystemCtx, world: &World) {
+        self.inner.init(resources, ctx, world);
+    }
+
+    unsafe fn execute_raw(&mut self, resources: &Resources, ctx: SystemCtx, world: &World) {
+        self.inner.execute_raw(resources, ctx, world);
+    }
+
+    unsafe fn execute_profiled(
+        &mut self,
+        resources: &Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) -> Vec<(&'static str, Duration)> {
+        self.inner.execute_profiled(resources, ctx, world)
+    }
+
+    fn is_defunct(&self) -> bool {
+        self.inner.is_defunct()
+    }
+
+    fn conflicts_with(&self, other: &dyn RawSystem) -> bool {
+        self.inner.conflicts_with(other)
+    }
+
+    fn suspected_writes(&self) -> Vec<ResourceId> {
+        self.inner.suspected_writes()
+    }
+
+    fn system_type_id(&self) -> Option<TypeId> {
+        self.inner.system_type_id()
+    }
+
+    fn take_any(self: Box<Self>) -> Option<Box<dyn Any + Send>> {
+        self.inner.take_any()
+    }
+
+    fn take_data(&mut self) -> Option<Box<dyn Any + Send>> {
+        self.inner.take_data()
+    }
+
+    fn restore_data(&mut self, data: Box<dyn Any + Send>) -> Result<(), Box<dyn Any + Send>> {
+        self.inner.restore_data(data)
+    }
+
+    fn clone_dyn(&self) -> Box<dyn RawSystem> {
+        Box::new(ReloadedSystem {
+            id: self.id,
+            inner: self.inner.clone_dyn(),
+        })
+    }
+}
+
+/// Returns whether `new` accesses exactly the same resources
+/// and components as `old`, so that it can replace `old`
+/// without changing the stage layout.
+pub(crate) fn same_accesses(old: &dyn RawSystem, new: &dyn RawSystem) -> bool {
+    same_elements(old.resource_reads(), new.resource_reads())
+        && same_elements(old.resource_writes(), new.resource_writes())
+        && same_elements(old.component_reads(), new.component_reads())
+        && same_elements(old.component_writes(), new.component_writes())
+}
+
+fn same_elements<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+    a.len() == b.len() && a.iter().all(|x| b.contains(x)) && b.iter().all(|x| a.contains(x))
+}
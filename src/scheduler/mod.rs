@@ -1,23 +1,74 @@
 use bit_set::BitSet;
 use bumpalo::Bump;
-use crossbeam::{Receiver, Sender};
+use hashbrown::HashMap;
 use rayon::prelude::*;
 use smallvec::{smallvec, SmallVec};
 use std::collections::VecDeque;
 use thread_local::ThreadLocal;
 
+mod assembler;
 mod builder;
-
+mod checkpoint;
+mod clonable;
+mod extractor;
+mod frozen;
+mod graph;
+mod hot_reload;
+mod observer;
+mod priority_channel;
+mod profile;
+mod resource_scope;
+mod stats;
+mod subscription;
+mod trace;
+
+use crate::context::{FrameClock, FrameContextSlot, LastDispatch};
 use crate::event::event_id_for;
-use crate::system::SystemCtx;
+use crate::spawn::SpawnQueue;
+use crate::system::{DetachedTasks, SystemCtx};
 use crate::{
-    resources::RESOURCE_ID_MAPPINGS, system::SYSTEM_ID_MAPPINGS, Event, EventId, RawEventHandler,
-    RawSystem, ResourceId, Resources, SystemId,
+    resources::{Resource, RESOURCE_ID_MAPPINGS},
+    system::SYSTEM_ID_MAPPINGS,
+    timeout::Deadlines,
+    Event, EventId, RawEventHandler, RawSystem, ResourceId, ResourceSnapshot, Resources, System,
+    SystemId, TimeoutBudget,
+};
+use assembler::StageLayout;
+pub use assembler::{
+    Conflicts, Constraint, ConstraintAlgorithm, GreedyAssembler, MaxParallelismAssembler,
+    PreserveOrderAssembler, StageAssembler,
 };
-pub use builder::{EventsBuilder, SchedulerBuilder};
+pub use builder::{
+    DependencyWarning, DuplicatePolicy, DuplicateResource, EmptyAccessPolicy, EventsBuilder,
+    MissingResource, ReadOnlyResourcePolicy, SchedulerBuilder,
+};
+pub use checkpoint::Checkpoint;
+use checkpoint::CheckpointedResource;
+pub use clonable::ClonableScheduler;
+use crossbeam::channel::RecvTimeoutError;
+use extractor::ExtractFn;
+pub use extractor::WorldResourceExtractor;
+pub use frozen::FrozenScheduler;
+pub use graph::SysNode;
+use hot_reload::ReloadedSystem;
 use legion::world::World;
+pub(crate) use observer::{visit_events, VisitEvents};
+use observer::{Observer, PendingEvents, UnhandledEventHandler};
+use parking_lot::{Mutex, RwLock};
+pub(crate) use priority_channel::{Priority, PriorityReceiver, PrioritySender};
+pub use profile::ProfileSpan;
+pub(crate) use resource_scope::{RawResourceScope, ResourceScope};
+use stats::BusyTime;
+pub use stats::{DispatchStats, ResourceAccessStats};
+use std::any::TypeId;
+use std::fmt::{self, Display};
 use std::iter;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+pub use subscription::ReadHandle;
+use subscription::{RawSubscription, Subscription};
+pub use trace::{ResourceTraceEntry, TraceAction};
 
 /// Context of a running system, used for internal purposes.
 #[derive(Clone)]
@@ -25,19 +76,94 @@ pub struct Context {
     /// The ID of this system.
     pub(crate) id: SystemId,
     /// Sender for communicating with the scheduler.
-    pub(crate) sender: Sender<TaskMessage>,
+    pub(crate) sender: PrioritySender<TaskMessage>,
 }
 
 /// ID of a stage, allocated consecutively for use as indices into vectors.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
 pub struct StageId(usize);
 
+impl StageId {
+    /// Returns the index of the stage in the schedule.
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Work left in the schedule after `Scheduler::execute_partial()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RemainingWork {
+    stages: usize,
+}
+
+impl RemainingWork {
+    /// Returns the number of stages which have not yet run.
+    pub fn stages(&self) -> usize {
+        self.stages
+    }
+
+    /// Returns whether all stages of the schedule have run.
+    pub fn is_complete(&self) -> bool {
+        self.stages == 0
+    }
+}
+
+/// Stages run by `Scheduler::execute_within_budget()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedStages {
+    stages: Vec<StageId>,
+    remaining: usize,
+}
+
+impl CompletedStages {
+    /// Returns the stages which ran, in the order they were started.
+    pub fn stages(&self) -> &[StageId] {
+        &self.stages
+    }
+
+    /// Returns the number of stages which were deferred to the next call.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Returns whether all stages of the schedule have run.
+    pub fn is_complete(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+/// What caused a oneshot system or event handler to run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicTrigger {
+    /// The system was scheduled as a oneshot.
+    Oneshot,
+    /// The system handled events of the given type.
+    Event(EventId),
+}
+
+/// A oneshot system or event handler which ran during a dispatch,
+/// as returned by `Scheduler::last_dynamic_tasks()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DynamicTaskRecord {
+    /// The system or event handler which ran.
+    pub system: SystemId,
+    /// What caused it to run.
+    pub trigger: DynamicTrigger,
+    /// Position in the order in which oneshots and event
+    /// handlers were started during the dispatch.
+    pub index: usize,
+}
+
 /// A stage in the completion of a dispatch. Each stage
 /// contains systems which can be executed in parallel.
 type Stage = SmallVec<[SystemId; 6]>;
 
 type ResourceVec = SmallVec<[ResourceId; 8]>;
 
+/// Capacity of the channel used to communicate with running systems,
+/// unless changed by `Scheduler::calibrate()`.
+const DEFAULT_CHANNEL_CAPACITY: usize = 8;
+
 /// A raw pointer to some `T`.
 ///
 /// # Safety
@@ -52,6 +178,46 @@ unsafe impl<T: ?Sized + Sync> Sync for SharedRawPtr<T> {}
 
 type DynSystem = (dyn RawSystem + 'static);
 
+/// Determines where the tasks of a dispatch are spawned.
+#[derive(Clone, Copy)]
+enum Spawner<'a, 's> {
+    /// Tasks are spawned on the global thread pool.
+    Global,
+    /// Tasks are spawned into a scope provided by the caller
+    /// of `Scheduler::execute_within_scope()`.
+    Scope(&'a rayon::Scope<'s>),
+    /// Tasks are spawned on a pool assigned through
+    /// `SchedulerBuilder::with_label_pool()`.
+    Pool(&'a rayon::ThreadPool),
+}
+
+impl<'a, 's> Spawner<'a, 's> {
+    fn spawn(self, task: impl FnOnce() + Send + 'static) {
+        match self {
+            Spawner::Global => rayon::spawn(task),
+            Spawner::Scope(scope) => scope.spawn(move |_| task()),
+            Spawner::Pool(pool) => pool.spawn(task),
+        }
+    }
+}
+
+/// The tasks run by a dispatch.
+enum DispatchTasks<'a> {
+    /// All stages, subject to their rates.
+    All,
+    /// The systems added with the given label.
+    Labeled(&'a str),
+    /// Some of the stages of a partial execution.
+    Partial(Vec<Task>),
+}
+
+/// A spawned task which can be claimed by whichever of the thread
+/// pool and the scheduler thread gets to it first.
+type InlineSlot = Arc<Mutex<Option<Box<dyn FnOnce() + Send>>>>;
+
+/// Spans recorded for each stage during a profiled dispatch.
+type StageSpans = Mutex<Vec<(StageId, ProfileSpan)>>;
+
 /// A mutable raw pointer to some `T`.
 ///
 /// # Safety
@@ -94,7 +260,16 @@ pub(crate) enum TaskMessage {
         id: EventId,
         ptr: *const (),
         len: usize,
+        /// Passes the events to a function as `&dyn Any`,
+        /// for use by the unhandled event handler.
+        visit: VisitEvents,
     },
+    /// Requests that the system with the given ID be run
+    /// as a oneshot, sent by `SystemCtx::schedule_oneshot()`.
+    ScheduleOneshot(SystemId),
+    /// Indicates that the system with the given ID dropped its
+    /// `ScopedWriteGuard`, releasing its write of the resource.
+    ReleaseWrite(SystemId, ResourceId),
 }
 
 unsafe impl Send for TaskMessage {}
@@ -169,6 +344,57 @@ pub struct Scheduler {
     /// TODO: implement a lock-free bump arena instead.
     #[derivative(Debug = "ignore")]
     bump: Arc<ThreadLocal<Bump>>,
+    /// Detached tasks spawned through `SystemCtx::spawn_detached()`.
+    #[derivative(Debug = "ignore")]
+    detached: Arc<DetachedTasks>,
+    /// Counter returned by `SystemCtx::world_generation()`.
+    world_generation: Arc<AtomicU64>,
+    /// Resources captured by `checkpoint()`.
+    #[derivative(Debug = "ignore")]
+    checkpointed: Vec<CheckpointedResource>,
+    /// Whether the current dispatch runs in the canonical
+    /// order, set by `execute_canonical()`.
+    canonical: bool,
+    /// Whether tasks which cannot obtain their resources are requeued
+    /// rather than waited for, set during `execute_async()`.
+    #[cfg(feature = "async")]
+    yield_when_blocked: bool,
+    /// Whether a task was requeued because of `yield_when_blocked`.
+    #[cfg(feature = "async")]
+    blocked: bool,
+    /// Stages which remain to be run by `execute_partial()`.
+    partial: VecDeque<Task>,
+    /// Number of stages when the current partial execution started.
+    partial_total: usize,
+    /// Time after which `execute_within_budget()` starts no further stages.
+    stage_deadline: Option<Instant>,
+    /// Stages started by the current call to `execute_within_budget()`.
+    completed_stages: Vec<StageId>,
+    /// Label set by `SchedulerBuilder::with_debug_label()`.
+    debug_label: Option<&'static str>,
+    /// Clock read by the `Context` system data.
+    #[derivative(Debug = "ignore")]
+    frame_clock: Arc<FrameClock>,
+    /// Context read by the `FrameContext` system data,
+    /// set by `execute_with_context()`.
+    #[derivative(Debug = "ignore")]
+    frame_context: Arc<FrameContextSlot>,
+    /// Scheduled systems by their `system_type_id()`,
+    /// used by `SystemCtx::schedule_oneshot()`.
+    #[derivative(Debug = "ignore")]
+    system_types: Arc<RwLock<HashMap<TypeId, SystemId>>>,
+    /// Oneshots and event handlers started during
+    /// the current or last dispatch.
+    dynamic_tasks: Vec<DynamicTaskRecord>,
+    /// Stats read by the `SchedulerStats` system data.
+    #[derivative(Debug = "ignore")]
+    last_dispatch: Arc<LastDispatch>,
+    /// Number of times a task was blocked during the current dispatch.
+    dispatch_contentions: u64,
+    /// Time budgets of systems, copied from the
+    /// `TimeoutBudget` resource on each dispatch.
+    #[derivative(Debug = "ignore")]
+    deadlines: Arc<Deadlines>,
 
     /// Number of currently running systems.
     runnning_systems_count: usize,
@@ -176,6 +402,9 @@ pub struct Scheduler {
     ///
     /// This is indexed by the `SystemId`.
     running_systems: BitSet,
+    /// Writes released by running systems through `ScopedWrite`,
+    /// which must not be released again once their task completes.
+    released_writes: Vec<(SystemId, ResourceId)>,
 
     /// Vector of systems which can be executed. This includes oneshottable
     /// systems as well.
@@ -185,6 +414,15 @@ pub struct Scheduler {
     systems: Vec<Option<Box<DynSystem>>>,
     /// Vector containing the systems for each stage.
     stages: Vec<Stage>,
+    /// Number of dispatches between runs of each stage,
+    /// set through `SchedulerBuilder::with_stage_rate()`.
+    ///
+    /// This vector is indexed by the `StageId`.
+    stage_rates: Vec<u32>,
+    /// Number of dispatches remaining until each stage next runs.
+    ///
+    /// This vector is indexed by the `StageId`.
+    stage_counters: Vec<u32>,
 
     /// Vector containing the reads required for each system.
     ///
@@ -230,12 +468,128 @@ pub struct Scheduler {
 
     /// Receiving end of the channel used to communicate with running systems.
     #[derivative(Debug = "ignore")]
-    receiver: Receiver<TaskMessage>,
+    receiver: PriorityReceiver<TaskMessage>,
     /// Sending end of the above channel. This can be cloned and sent to systems.
     #[derivative(Debug = "ignore")]
-    sender: Sender<TaskMessage>,
+    sender: PrioritySender<TaskMessage>,
+
+    /// Capacity of the channel used to communicate with running systems.
+    channel_capacity: usize,
+    /// Number of tasks which have been dispatched but not yet completed.
+    running_tasks: usize,
+    /// Highest value of `running_tasks` since the last call to `calibrate()`.
+    peak_running_tasks: usize,
 
     is_first_run: bool,
+
+    /// Stage execution time above which a warning is logged, or `None`
+    /// if stages should not be timed.
+    long_stage_threshold: Option<Duration>,
+
+    /// Time after which tasks which have not started are run inline,
+    /// set by `SchedulerBuilder::with_stall_timeout()`.
+    stall_timeout: Option<Duration>,
+    /// Tasks spawned during the current dispatch while `stall_timeout`
+    /// is set. Slots are emptied once their task starts.
+    #[derivative(Debug = "ignore")]
+    unstarted: Vec<InlineSlot>,
+
+    /// Spans recorded for each stage while running `profile_dispatch()`,
+    /// or `None` if not profiling.
+    #[derivative(Debug = "ignore")]
+    profile: Option<Arc<StageSpans>>,
+
+    /// Counters returned by `stats()`.
+    stats: DispatchStats,
+    /// Time spent running systems during the current dispatch.
+    #[derivative(Debug = "ignore")]
+    busy: Arc<BusyTime>,
+    /// Parallel efficiency of the last dispatch,
+    /// returned by `measure_parallelism()`.
+    last_parallelism: f64,
+    /// Number of times a task was blocked on each resource.
+    ///
+    /// This vector is indexed by the `ResourceId`.
+    resource_contentions: Vec<u64>,
+    /// Whether the scheduler is running warmup dispatches,
+    /// which are counted separately.
+    warming_up: bool,
+
+    /// Hooks registered through `SchedulerBuilder::with_resource_scope()`.
+    #[derivative(Debug = "ignore")]
+    pub(crate) resource_scopes: Vec<Box<dyn RawResourceScope>>,
+
+    /// Systems replaced through `hot_reload_systems()` which
+    /// have not yet been initialized.
+    pending_init: Vec<SystemId>,
+    /// Whether systems were initialized against the world discarded
+    /// after warmup, so those accessing the world must be initialized again.
+    warmed_up: bool,
+    /// Resources which were added or removed by `execute_with_resources()`,
+    /// whose systems must be reinitialized before the next dispatch.
+    stale_resources: Vec<ResourceId>,
+
+    /// Resources passed to `trace_resource()`.
+    traced_resources: Vec<ResourceId>,
+    /// Accesses to traced resources recorded since
+    /// the last call to `take_resource_trace()`.
+    resource_trace: Vec<ResourceTraceEntry>,
+
+    /// Bit set containing bits set for systems which are skipped once
+    /// the dispatch exceeds `dispatch_budget`.
+    ///
+    /// This is indexed by the `SystemId`.
+    skippable: BitSet,
+    /// Time after which skippable systems are no longer started,
+    /// set through `SchedulerBuilder::with_dispatch_budget()`.
+    dispatch_budget: Option<Duration>,
+    /// Time after which skippable systems are skipped
+    /// during the current dispatch.
+    skip_deadline: Option<Instant>,
+    /// Number of systems skipped during the current dispatch.
+    skipped: Arc<AtomicU64>,
+
+    /// Systems disabled through `set_system_enabled()`.
+    ///
+    /// This set is indexed by the `SystemId`.
+    disabled: BitSet,
+    /// Inputs from which the stages were assembled,
+    /// used by `reschedule()`.
+    #[derivative(Debug = "ignore")]
+    pub(crate) layout: StageLayout,
+    /// Stages merged by `SchedulerBuilder::with_stage_limit()` which
+    /// contain conflicting or ordered systems, and so run serially.
+    ///
+    /// This set is indexed by the `StageId`.
+    pub(crate) serial_stages: BitSet,
+
+    /// Systems added through `SchedulerBuilder::with_label()`,
+    /// in the order they were added, keyed by label.
+    pub(crate) labels: HashMap<String, Vec<SystemId>>,
+    /// Thread pools assigned through `SchedulerBuilder::with_label_pool()`.
+    ///
+    /// This map is keyed by the `SystemId`, and shared with system contexts.
+    #[derivative(Debug = "ignore")]
+    pub(crate) system_pools: Arc<HashMap<usize, Arc<rayon::ThreadPool>>>,
+
+    /// Observers registered through `add_observer()`.
+    ///
+    /// This vector is indexed by the `EventId`.
+    #[derivative(Debug = "ignore")]
+    observers: Vec<Vec<Observer>>,
+    /// Events triggered by running tasks which have
+    /// not yet been passed to observers.
+    pending_observed: Vec<PendingEvents>,
+    /// Handler called for events which no event handler handles.
+    #[derivative(Debug = "ignore")]
+    unhandled_event_handler: Option<UnhandledEventHandler>,
+
+    /// Extractors registered through `SchedulerBuilder::register_extractor()`.
+    extractors: Vec<ExtractFn>,
+
+    /// Subscriptions created through `subscribe_read()`.
+    #[derivative(Debug = "ignore")]
+    subscriptions: Vec<Box<dyn RawSubscription>>,
 }
 
 impl Scheduler {
@@ -319,11 +673,12 @@ impl Scheduler {
         // We use a bounded channel because the only overhead
         // is typically on the sender's side—the receiver, the scheduler, should
         // plow through messages. This may be changed in the future.
-        let (sender, receiver) = crossbeam::bounded(8);
+        let (sender, receiver) = priority_channel::bounded(DEFAULT_CHANNEL_CAPACITY);
 
         let bump = ThreadLocal::new();
 
         let starting_queue = Self::create_task_queue(&stage_systems);
+        let system_types = Arc::new(RwLock::new(system_types_of(&systems)));
 
         Self {
             resources,
@@ -336,8 +691,11 @@ impl Scheduler {
 
             runnning_systems_count: 0,
             running_systems: BitSet::with_capacity(systems.len()),
+            released_writes: vec![],
 
             systems,
+            stage_rates: vec![1; stage_systems.len()],
+            stage_counters: vec![0; stage_systems.len()],
             stages: stage_systems,
 
             system_reads,
@@ -352,14 +710,90 @@ impl Scheduler {
             event_writes,
 
             bump: Arc::new(bump),
+            detached: Arc::new(DetachedTasks::default()),
+            world_generation: Arc::new(AtomicU64::new(0)),
+            checkpointed: vec![],
+            canonical: false,
+            #[cfg(feature = "async")]
+            yield_when_blocked: false,
+            #[cfg(feature = "async")]
+            blocked: false,
+            partial: VecDeque::new(),
+            partial_total: 0,
+            stage_deadline: None,
+            completed_stages: vec![],
+            debug_label: None,
+            frame_clock: Arc::new(FrameClock::default()),
+            frame_context: Arc::new(FrameContextSlot::default()),
+            system_types,
+            dynamic_tasks: vec![],
+            last_dispatch: Arc::new(LastDispatch::default()),
+            dispatch_contentions: 0,
+            deadlines: Arc::new(Deadlines::new(num_systems)),
 
             sender,
             receiver,
 
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            running_tasks: 0,
+            peak_running_tasks: 0,
+
             is_first_run: true,
+
+            long_stage_threshold: None,
+            stall_timeout: None,
+            unstarted: vec![],
+
+            profile: None,
+
+            stats: DispatchStats::default(),
+            busy: Arc::new(BusyTime::default()),
+            last_parallelism: 0.0,
+            resource_contentions: vec![],
+            warming_up: false,
+            resource_scopes: vec![],
+            pending_init: vec![],
+            warmed_up: false,
+            stale_resources: vec![],
+            traced_resources: vec![],
+            resource_trace: vec![],
+            skippable: BitSet::new(),
+            dispatch_budget: None,
+            skip_deadline: None,
+            skipped: Arc::new(AtomicU64::new(0)),
+            disabled: BitSet::new(),
+            layout: StageLayout::default(),
+            serial_stages: BitSet::new(),
+            labels: HashMap::new(),
+            system_pools: Arc::new(HashMap::new()),
+            observers: vec![],
+            pending_observed: vec![],
+            unhandled_event_handler: None,
+            extractors: vec![],
+            subscriptions: vec![],
+        }
+    }
+
+    fn publish_subscriptions(&mut self) {
+        self.subscriptions
+            .retain(|subscription| !subscription.is_abandoned());
+
+        for subscription in &self.subscriptions {
+            subscription.publish(&self.resources);
         }
     }
 
+    /// Asserts that all vectors indexed by the `SystemId` cover
+    /// every system. Anything which adds systems to a built
+    /// scheduler must grow these vectors together.
+    fn debug_assert_system_capacity(&self) {
+        let len = self.systems.len();
+        debug_assert!(self.running_systems.capacity() >= len);
+        debug_assert_eq!(self.system_reads.len(), len);
+        debug_assert_eq!(self.system_writes.len(), len);
+        debug_assert!(self.event_handlers.len() <= len);
+    }
+
     fn create_task_queue(stages: &[Stage]) -> VecDeque<Task> {
         stages
             .iter()
@@ -373,185 +807,1333 @@ impl Scheduler {
         &self.resources
     }
 
-    /// Executes all systems and handles events.
-    pub fn execute(&mut self, world: &mut World) {
-        if self.is_first_run {
-            self.is_first_run = false;
+    /// Returns a snapshot of the current versions of this
+    /// scheduler's resources, for use with `resources_changed_since()`.
+    pub fn resource_snapshot(&self) -> ResourceSnapshot {
+        self.resources.snapshot()
+    }
 
-            self.on_first_run(world);
+    /// Returns the IDs of the resources which have been inserted, removed
+    /// or modified since `snapshot` was taken, in ascending order.
+    ///
+    /// This allows serializing only the resources which changed
+    /// since the last snapshot.
+    pub fn resources_changed_since(&self, snapshot: &ResourceSnapshot) -> Vec<ResourceId> {
+        self.resources.changed_since(snapshot)
+    }
+
+    /// Replaces the resource of type `T` with `new`, returning
+    /// the old value, or inserts `new` if no such resource exists.
+    ///
+    /// The value is replaced in place, so systems which have already
+    /// loaded the resource observe the new value on their next run.
+    /// The resource's version is incremented exactly once.
+    pub fn replace_resource<T: Resource>(&mut self, new: T) -> Option<T> {
+        if self.resources.contains::<T>() {
+            Some(std::mem::replace(self.resources.get_mut::<T>(), new))
+        } else {
+            self.resources.insert(new);
+            None
         }
+    }
 
-        // Reset the task queue to the starting queue.
-        self.task_queue.extend(self.starting_queue.iter().copied());
+    /// Returns the number of per-system slots in this scheduler.
+    ///
+    /// Every system and event handler in the scheduler has
+    /// a `SystemId` less than this value.
+    pub fn systems_len(&self) -> usize {
+        self.systems.len()
+    }
 
-        // While there are remaining tasks, dispatch them.
-        // When we encounter a task which can't be run because
-        // of conflicting dependencies, we wait for tasks to
-        // complete by listening on the channel.
-        while let Some(task) = self.task_queue.pop_front() {
-            // Attempt to run task.
-            self.run_task(task, world);
+    /// Returns the capacity of the channel used by running systems
+    /// to communicate with the scheduler.
+    pub fn channel_capacity(&self) -> usize {
+        self.channel_capacity
+    }
+
+    /// Runs a warmup dispatch against an empty `World`, measuring
+    /// the number of tasks which run concurrently, and then resizes
+    /// the channel used by running systems so that they do not block
+    /// when many tasks complete at once.
+    ///
+    /// Only the channel capacity is tuned; the capacities of stages
+    /// and resource lists are fixed when the scheduler is built.
+    ///
+    /// As with `SchedulerBuilder::with_warmup()`, systems run normally
+    /// during the warmup dispatch. If the channel is resized, all systems
+    /// and event handlers are initialized again on the next dispatch.
+    pub fn calibrate(&mut self) {
+        self.peak_running_tasks = 0;
+        self.warmup(1);
+
+        let capacity = self
+            .peak_running_tasks
+            .next_power_of_two()
+            .max(DEFAULT_CHANNEL_CAPACITY);
+
+        if capacity != self.channel_capacity {
+            // Systems and event handlers hold clones of the old sender
+            // in their contexts, so all of them have to be initialized
+            // again with the new one.
+            let (sender, receiver) = priority_channel::bounded(capacity);
+            self.sender = sender;
+            self.receiver = receiver;
+            self.channel_capacity = capacity;
+            self.is_first_run = true;
         }
+    }
 
-        // Wait for remaining systems to complete.
-        while self.runnning_systems_count > 0 {
-            let num = self.wait_for_completion();
-            self.runnning_systems_count -= num;
+    /// Returns counters describing the work done by this scheduler.
+    pub fn stats(&self) -> DispatchStats {
+        self.stats
+    }
 
-            // Run any handlers/oneshots scheduled by these systems
-            while let Some(task) = self.task_queue.pop_front() {
-                self.run_task(task, world);
+    /// Returns the parallel efficiency of the last dispatch: the total time
+    /// spent running systems and event handlers, divided by the product of
+    /// the dispatch's wall-clock time and the number of threads in the pool.
+    ///
+    /// A value close to 1.0 means all threads were busy, while a value close
+    /// to 0.0 means systems mostly ran one after another. Returns 0.0 if no
+    /// dispatch has run.
+    pub fn measure_parallelism(&self) -> f64 {
+        self.last_parallelism
+    }
+
+    /// Returns, for each resource accessed by any system or event handler,
+    /// the number of readers and writers and the number of times tasks
+    /// were blocked on it.
+    ///
+    /// Resources with many writers and contentions limit parallelism
+    /// and may be worth splitting.
+    pub fn resource_access_stats(&self) -> HashMap<ResourceId, ResourceAccessStats> {
+        let mut stats: HashMap<ResourceId, ResourceAccessStats> = HashMap::new();
+
+        for id in 0..self.system_reads.len() {
+            let (reads, writes) = match self.event_handlers.get(id) {
+                Some(Some(handler)) => (handler.resource_reads(), handler.resource_writes()),
+                _ => (&self.system_reads[id][..], &self.system_writes[id][..]),
+            };
+
+            for read in reads {
+                stats.entry(*read).or_default().readers += 1;
+            }
+            for write in writes {
+                stats.entry(*write).or_default().writers += 1;
             }
         }
 
-        assert!(self.task_queue.is_empty());
-        assert!(self.running_systems.is_empty());
+        for (id, stats) in &mut stats {
+            stats.contentions = self.resource_contentions.get(id.0).copied().unwrap_or(0);
+        }
+
+        stats
     }
 
-    fn on_first_run(&mut self, world: &mut World) {
-        let sender = self.sender.clone();
-        let bump = Arc::clone(&self.bump);
-        let resources = &mut self.resources;
+    /// Records every acquisition and release of the resource
+    /// with the given ID, along with the systems which accessed it.
+    ///
+    /// This is intended for diagnosing systems which access resources
+    /// they did not declare. Recorded accesses are returned by
+    /// `take_resource_trace()` and, if the `log` feature is enabled,
+    /// logged at the debug level.
+    pub fn trace_resource(&mut self, id: ResourceId) {
+        if !self.traced_resources.contains(&id) {
+            self.traced_resources.push(id);
+        }
+    }
 
-        // Initialize all systems and event handlers.
-        self.systems
-            .iter_mut()
-            .filter(|sys| sys.is_some())
-            .for_each(|sys| {
-                let sys = sys.as_mut().unwrap();
+    /// Returns the IDs of the running systems and event handlers which
+    /// currently hold the resource with the given ID, and thus block
+    /// any task which conflicts with them on that resource.
+    ///
+    /// This is intended for diagnosing unexpectedly long waits
+    /// for resources.
+    pub fn systems_blocking_on_resource(&self, id: ResourceId) -> Vec<SystemId> {
+        let held =
+            self.writes_held.contains(id.0) || self.reads_held.get(id.0).copied().unwrap_or(0) > 0;
+        if !held {
+            return vec![];
+        }
 
-                let ctx = SystemCtx {
-                    sender: sender.clone(),
-                    id: sys.id(),
-                    bump: Arc::clone(&bump),
+        self.running_systems
+            .iter()
+            .map(SystemId)
+            .filter(|system| {
+                let (reads, writes) = match self.event_handlers.get(system.0) {
+                    Some(Some(handler)) => (handler.resource_reads(), handler.resource_writes()),
+                    _ => (
+                        &self.system_reads[system.0][..],
+                        &self.system_writes[system.0][..],
+                    ),
                 };
+                reads.contains(&id) || writes.contains(&id)
+            })
+            .collect()
+    }
 
-                sys.init(resources, ctx, world);
-            });
-
-        self.event_handlers
-            .iter_mut()
-            .filter(|handler| handler.is_some())
-            .for_each(|handler| {
-                let handler = handler.as_mut().unwrap();
+    /// Returns the accesses to traced resources which have been
+    /// recorded since the last call to this function.
+    pub fn take_resource_trace(&mut self) -> Vec<ResourceTraceEntry> {
+        std::mem::take(&mut self.resource_trace)
+    }
 
-                let ctx = SystemCtx {
-                    sender: sender.clone(),
-                    id: handler.id(),
-                    bump: Arc::clone(&bump),
-                };
+    /// Returns a handle which reads the value of the resource
+    /// of type `T` as of the end of the last dispatch.
+    ///
+    /// The value is cloned and published at the end of each dispatch,
+    /// so other threads can read it without racing running systems.
+    ///
+    /// # Panics
+    /// Panics if the resource does not exist.
+    pub fn subscribe_read<T: Resource + Clone>(&mut self) -> ReadHandle<T> {
+        let (subscription, handle) = Subscription::<T>::new(&self.resources);
+        self.subscriptions.push(Box::new(subscription));
+        handle
+    }
 
-                handler.init(resources, ctx, world);
-            })
+    /// Removes the system of type `S` from the schedule and returns it,
+    /// e.g. so that its state can be inspected or persisted.
+    ///
+    /// Returns `None` if no system of type `S` is scheduled. If more than
+    /// one is, the one with the lowest `SystemId` is removed.
+    pub fn take_system<S: System>(&mut self) -> Option<S> {
+        let id = self
+            .systems
+            .iter()
+            .flatten()
+            .find(|system| system.system_type_id() == Some(TypeId::of::<S>()))?
+            .id();
+
+        let system = self.remove_systems(&[id]).pop()?;
+        let system = system
+            .take_any()
+            .and_then(|system| system.downcast::<S>().ok())
+            .expect("system type ID does not match its type");
+        Some(*system)
     }
 
-    /// Triggers an event manually. It will be handled
-    /// on the next call to `execute()`.
-    pub fn trigger<E>(&mut self, event: E)
-    where
-        E: Event,
-    {
-        let id = event_id_for::<E>();
-        // Don't trigger events which have no handlers.
-        if self.end_of_tick_handlers.len() <= id.0 {
-            return;
+    /// Enables or disables the system with the given ID.
+    ///
+    /// Disabled systems are not run, but remain in their stage until
+    /// `reschedule()` is called. All systems are initially enabled.
+    pub fn set_system_enabled(&mut self, id: SystemId, enabled: bool) {
+        if enabled {
+            self.disabled.remove(id.0);
+        } else {
+            self.disabled.insert(id.0);
         }
+    }
 
-        let ptr = self.bump.get_or_default().alloc(event) as *mut E as *const ();
-        let len = 1;
-        self.task_queue.push_front(Task::HandleEvent(id, ptr, len));
+    /// Returns whether the system with the given ID is enabled.
+    pub fn is_system_enabled(&self, id: SystemId) -> bool {
+        !self.disabled.contains(id.0)
     }
 
-    fn run_task(&mut self, task: Task, world: &mut World) {
-        let reads = reads_for_task(
-            &self.stage_reads,
-            &self.system_reads,
-            &self.event_reads,
-            &task,
-        );
-        let writes = writes_for_task(
-            &self.stage_writes,
-            &self.system_writes,
-            &self.event_writes,
-            &task,
-        );
+    /// Assembles the enabled systems into stages again, as `build()` did
+    /// for all systems. Disabling systems which conflict with others may
+    /// allow the remaining systems to run in fewer stages.
+    ///
+    /// Disabled systems are left out of the new stages, so they do not run
+    /// even once re-enabled until this is called again. Rates set through
+    /// `SchedulerBuilder::with_stage_rate()` are reset, since the stages change.
+    pub fn reschedule(&mut self) {
+        let systems = &self.systems;
+        let disabled = &self.disabled;
+        let stages = self
+            .layout
+            .assemble(|id| matches!(systems.get(id.0), Some(Some(_))) && !disabled.contains(id.0));
+
+        self.stages = stages
+            .into_iter()
+            .map(|stage| stage.into_iter().collect())
+            .collect();
+        self.stage_rates = vec![1; self.stages.len()];
+        self.stage_counters = vec![0; self.stages.len()];
+        self.recompute_stage_accesses();
+    }
 
-        // For event handlers, we have to check that the handler is not already running, since it takes &mut self.
-        let not_running = if let Task::HandleEvent(id, _, _) = &task {
-            if self.end_of_tick_handlers[id.0]
-                .iter()
-                .any(|id| self.running_systems.contains(id.0))
-            {
-                Err(())
-            } else {
-                Ok(())
-            }
-        } else {
-            Ok(())
-        };
+    /// Replaces the implementations of already scheduled systems,
+    /// e.g. after reloading them from a recompiled dynamic library.
+    ///
+    /// Each replacement takes over the ID and stage of the system with the
+    /// given ID. If its system data has the same type as that of the old
+    /// system, as for `CachedSystem`s with the same `SystemData`, the
+    /// loaded data is carried over, so cached state such as the values
+    /// of `ReadCached` is kept. Otherwise, the replacement is initialized
+    /// before the next dispatch. State held by the old system value
+    /// itself is dropped.
+    ///
+    /// A replacement must access exactly the same resources and components
+    /// as the system it replaces, since the stage layout is not changed.
+    /// If any replacement does not, or no system with its ID is scheduled,
+    /// no systems are replaced and the IDs of the offending replacements
+    /// are returned. The scheduler must then be rebuilt instead.
+    pub fn hot_reload_systems(
+        &mut self,
+        replacements: impl IntoIterator<Item = (SystemId, Box<dyn RawSystem>)>,
+    ) -> Result<(), Vec<SystemId>> {
+        let replacements: Vec<_> = replacements.into_iter().collect();
 
-        match try_obtain_resources(reads, writes, &mut self.reads_held, &mut self.writes_held)
-            .and(not_running)
-        {
-            Ok(()) => {
-                // Run task and proceed.
-                #[cfg(feature = "log")]
-                {
-                    log::trace!(
-                        "Dispatching task of type {:?} (reads: {:?}, writes: {:?})",
-                        task,
-                        reads,
-                        writes
-                    );
-                }
-                let systems = self.dispatch_task(task, world);
-                self.runnning_systems_count += systems;
-            }
-            Err(()) => {
-                // Execution is blocked: wait for tasks to finish.
-                // Re-push the task we attempted to run to the queue.
-                // TODO: optimize this
-                self.task_queue.push_front(task);
-                let num = self.wait_for_completion();
-                self.runnning_systems_count -= num;
+        let incompatible: Vec<SystemId> = replacements
+            .iter()
+            .filter(
+                |(id, new)| match self.systems.get(id.0).and_then(|sys| sys.as_ref()) {
+                    Some(old) => !hot_reload::same_accesses(&**old, &**new),
+                    None => true,
+                },
+            )
+            .map(|(id, _)| *id)
+            .collect();
+
+        if !incompatible.is_empty() {
+            return Err(incompatible);
+        }
+
+        for (id, mut new) in replacements {
+            // Unwrap is allowed because replacements were validated above.
+            let old = self.systems[id.0].as_mut().unwrap();
+            let restored = match old.take_data() {
+                Some(data) => new.restore_data(data).is_ok(),
+                None => false,
+            };
+
+            self.systems[id.0] = Some(Box::new(ReloadedSystem { id, inner: new }));
+            if !restored {
+                self.pending_init.push(id);
             }
         }
+        *self.system_types.write() = system_types_of(&self.systems);
+
+        Ok(())
     }
 
-    /// Waits for messages from running systems and handles them.
-    ///
-    /// At any point, returns with the number of systems which have completed.
-    fn wait_for_completion(&mut self) -> usize {
-        // Unwrap is allowed because the channel never becomes disconnected
-        // (`Scheduler` holds a `Sender` handle for it).
-        // This will never block indefinitely because there are always
-        // systems running when this is invoked.
-        let msg = self.receiver.recv().unwrap();
+    /// Freezes this scheduler, returning a `FrozenScheduler`
+    /// which can be executed but not modified.
+    pub fn freeze(self) -> FrozenScheduler {
+        FrozenScheduler::new(self)
+    }
 
-        match msg {
-            // TODO: events
-            TaskMessage::SystemComplete(id) => {
-                self.release_resources_for_system(id);
-                self.running_systems.remove(id.0);
-                1
-            }
-            TaskMessage::StageComplete(id) => {
-                self.release_resources_for_stage(id);
-                let running_systems = &mut self.running_systems;
-                self.stages[id.0].iter().for_each(|id| {
-                    running_systems.remove(id.0);
-                });
-                self.stages[id.0].len()
-            }
-            TaskMessage::TriggerEvents { id, ptr, len } => {
-                if self.end_of_tick_handlers.len() <= id.0 {
-                    return 0;
-                }
+    /// Shuts down this scheduler, blocking until all tasks spawned
+    /// through `SystemCtx::spawn_detached()` have completed.
+    pub fn shutdown(self) {
+        self.detached.wait();
+    }
 
-                self.task_queue.push_back(Task::HandleEvent(id, ptr, len));
-                0
-            }
-            TaskMessage::EventHandlingComplete(id) => {
+    /// Shuts down this scheduler like `shutdown()`, returning
+    /// its `Resources` so that they can outlive the scheduler,
+    /// e.g. to be passed to the scheduler of the next scene.
+    ///
+    /// Since `ResourceId`s are allocated globally, the returned
+    /// `Resources` can be passed directly to `SchedulerBuilder::build()`
+    /// of a scheduler with entirely different systems.
+    pub fn take_resources(self) -> Resources {
+        self.detached.wait();
+        self.resources
+    }
+
+    /// Executes all systems once, then shuts down this scheduler
+    /// like `take_resources()`, returning its `Resources` along
+    /// with the final `stats()`.
+    ///
+    /// This is useful when the scheduler is used to run
+    /// a single parallel job rather than a game loop.
+    ///
+    /// The stats are not wrapped in a `Result`, since a dispatch has no
+    /// error to report: a panic in a system on the thread pool aborts
+    /// the process, and one on the calling thread unwinds out of this
+    /// method, just as with `execute()`.
+    pub fn execute_once_and_teardown(mut self, world: &mut World) -> (Resources, DispatchStats) {
+        self.execute(world);
+        let stats = self.stats();
+        (self.take_resources(), stats)
+    }
+
+    /// Increments the world generation returned by `SystemCtx::world_generation()`.
+    ///
+    /// This should be called whenever the world is modified outside
+    /// of systems, e.g. after flushing a `legion` `CommandBuffer`
+    /// or inserting entities between dispatches.
+    pub fn bump_world_generation(&self) {
+        self.world_generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Executes all systems and handles events.
+    pub fn execute(&mut self, world: &mut World) {
+        self.dispatch(world, Spawner::Global, DispatchTasks::All);
+    }
+
+    /// Executes all systems and handles events like `execute()`, making
+    /// `context` available to systems through `FrameContext<C>` for the
+    /// duration of this dispatch only.
+    ///
+    /// This suits per-frame data, such as input events, which would
+    /// otherwise need to be inserted and removed as a resource each frame.
+    pub fn execute_with_context<C: Send + Sync + 'static>(
+        &mut self,
+        context: C,
+        world: &mut World,
+    ) {
+        let _guard = self.frame_context.set(&context);
+        self.dispatch(world, Spawner::Global, DispatchTasks::All);
+    }
+
+    /// Executes all systems and handles events in the canonical order:
+    /// one task at a time, with stages in ascending order of their IDs
+    /// and the systems of each stage in ascending order of their `SystemId`s.
+    ///
+    /// The canonical order is reproducible across runs and machines,
+    /// unlike the order of `execute()`. This is intended for correctness
+    /// runs in CI: a test which passes under the canonical order but fails
+    /// under `execute()` points to a missing ordering constraint.
+    pub fn execute_canonical(&mut self, world: &mut World) {
+        self.canonical = true;
+        self.dispatch(world, Spawner::Global, DispatchTasks::All);
+        self.canonical = false;
+    }
+
+    /// Executes roughly `fraction` of the stages of the schedule, continuing
+    /// from where the previous call left off, and handles the events
+    /// triggered by them. This allows time-slicing a simulation whose
+    /// schedule takes longer than a frame.
+    ///
+    /// Each call runs at least one stage. Once all stages have run, the
+    /// next call starts over from the first stage, so two calls with a
+    /// fraction of 0.5 are equivalent to one call to `execute()`. Each
+    /// call counts as a dispatch, e.g. for `Context::frame()`.
+    ///
+    /// # Panics
+    /// Panics if `fraction` is not positive.
+    pub fn execute_partial(&mut self, fraction: f64, world: &mut World) -> RemainingWork {
+        assert!(fraction > 0.0, "{}: fraction must be positive", self);
+
+        if self.partial.is_empty() {
+            self.reset_task_queue();
+            self.partial = self.task_queue.drain(..).collect();
+            self.partial_total = self.partial.len();
+        }
+
+        let count = ((self.partial_total as f64 * fraction).ceil() as usize)
+            .max(1)
+            .min(self.partial.len());
+        let tasks = self.partial.drain(..count).collect();
+        self.dispatch(world, Spawner::Global, DispatchTasks::Partial(tasks));
+
+        RemainingWork {
+            stages: self.partial.len(),
+        }
+    }
+
+    /// Returns the oneshot systems and event handlers which ran during
+    /// the last dispatch, in the order they were started.
+    ///
+    /// This is intended for debugging whether, and when,
+    /// dynamically scheduled systems ran.
+    pub fn last_dynamic_tasks(&self) -> &[DynamicTaskRecord] {
+        &self.dynamic_tasks
+    }
+
+    /// Records a oneshot or event handling task which is about to run.
+    fn record_dynamic_task(&mut self, task: &Task) {
+        let (systems, trigger) = match task {
+            Task::Oneshot(id) => (vec![*id], DynamicTrigger::Oneshot),
+            Task::HandleEvent(id, _, _) => (
+                self.end_of_tick_handlers[id.0].to_vec(),
+                DynamicTrigger::Event(*id),
+            ),
+            _ => return,
+        };
+
+        for system in systems {
+            self.dynamic_tasks.push(DynamicTaskRecord {
+                system,
+                trigger,
+                index: self.dynamic_tasks.len(),
+            });
+        }
+    }
+
+    /// Executes stages until `budget` has elapsed, continuing from where
+    /// the previous call left off, and handles the events triggered by them.
+    /// This is intended for loops with a strict frame time, such as VR rendering.
+    ///
+    /// No further stages are started once the budget is exceeded, but
+    /// running stages complete normally, so the call may take longer than
+    /// `budget`. Deferred stages run at the start of the next call. Each call
+    /// runs at least one stage and counts as a dispatch, as with `execute_partial()`.
+    pub fn execute_within_budget(
+        &mut self,
+        budget: Duration,
+        world: &mut World,
+    ) -> CompletedStages {
+        if self.partial.is_empty() {
+            self.reset_task_queue();
+            self.partial = self.task_queue.drain(..).collect();
+            self.partial_total = self.partial.len();
+        }
+
+        let tasks = self.partial.drain(..).collect();
+        self.stage_deadline = Some(Instant::now() + budget);
+        self.dispatch(world, Spawner::Global, DispatchTasks::Partial(tasks));
+        self.stage_deadline = None;
+
+        CompletedStages {
+            stages: std::mem::take(&mut self.completed_stages),
+            remaining: self.partial.len(),
+        }
+    }
+
+    /// Executes only the systems added with the given label through
+    /// `SchedulerBuilder::with_label()`, and handles the events they trigger.
+    ///
+    /// The labeled systems run in the order they were added, in parallel
+    /// where their resource accesses do not conflict. This is useful for
+    /// subsystems which run on demand, such as saving the game.
+    ///
+    /// # Panics
+    /// Panics if no system has the given label.
+    pub fn execute_labeled(&mut self, label: &str, world: &mut World) {
+        assert!(
+            self.labels.contains_key(label),
+            "{}: no system has the label \"{}\"",
+            self,
+            label
+        );
+        self.dispatch(world, Spawner::Global, DispatchTasks::Labeled(label));
+    }
+
+    /// Executes all systems and handles events, spawning tasks into
+    /// `scope` rather than onto the global thread pool. This allows
+    /// the dispatch to share a thread pool with other work spawned into
+    /// the same scope, such as asset streaming or audio mixing.
+    ///
+    /// Like `execute()`, this function does not return until all systems
+    /// have completed, so the dispatch always finishes before the scope's
+    /// join point. While waiting for systems to complete, the calling thread
+    /// runs other pending work from the pool if it is a worker thread.
+    pub fn execute_within_scope<'s>(&mut self, scope: &rayon::Scope<'s>, world: &mut World) {
+        self.dispatch(world, Spawner::Scope(scope), DispatchTasks::All);
+    }
+
+    /// Executes all systems and handles events like `execute()`, but awaits
+    /// the completion of tasks rather than blocking the calling thread,
+    /// so that the scheduler can be driven from an async runtime.
+    ///
+    /// Systems still run on the rayon thread pool; only waiting for them
+    /// yields to the runtime. Tasks which run inside a resource scope,
+    /// as registered through `SchedulerBuilder::with_resource_scope()`,
+    /// still block while they run, and the stall timeout is not applied.
+    ///
+    /// If the returned future is dropped before it completes, the rest of
+    /// the dispatch is abandoned: dropping it blocks until the systems
+    /// which are already running have completed, and systems which have
+    /// not yet started do not run.
+    #[cfg(feature = "async")]
+    pub async fn execute_async(&mut self, world: &mut World) {
+        let start = self.begin_dispatch(world, DispatchTasks::All);
+        self.yield_when_blocked = true;
+
+        let mut guard = AbandonDispatch {
+            scheduler: self,
+            completed: false,
+        };
+        let scheduler = &mut *guard.scheduler;
+
+        loop {
+            // Run tasks until one is blocked by a running task.
+            while let Some(task) = scheduler.task_queue.pop_front() {
+                scheduler.run_task(task, world, Spawner::Global);
+                if scheduler.blocked {
+                    scheduler.blocked = false;
+                    break;
+                }
+            }
+
+            if scheduler.runnning_systems_count == 0 && scheduler.task_queue.is_empty() {
+                break;
+            }
+
+            // Unwrap is allowed because the scheduler holds a sender.
+            let msg = scheduler.receiver.recv_async().await.unwrap();
+            let num = scheduler.handle_message(msg);
+            scheduler.runnning_systems_count -= num;
+        }
+
+        scheduler.yield_when_blocked = false;
+        scheduler.finish_dispatch(world, start);
+        guard.completed = true;
+    }
+
+    /// Runs a dispatch of the given tasks.
+    fn dispatch(&mut self, world: &mut World, spawner: Spawner, tasks: DispatchTasks) {
+        // Stage and system spans are children of this span.
+        #[cfg(feature = "trace")]
+        let _span = tracing::info_span!("dispatch", scheduler = %self).entered();
+
+        let start = self.begin_dispatch(world, tasks);
+
+        // While there are remaining tasks, dispatch them.
+        // When we encounter a task which can't be run because
+        // of conflicting dependencies, we wait for tasks to
+        // complete by listening on the channel.
+        while let Some(task) = self.task_queue.pop_front() {
+            // Stages which could not start yet are requeued, and
+            // must not be deferred once they have been attempted.
+            if let (Task::Stage(id), Some(deadline)) = (task, self.stage_deadline) {
+                if !self.completed_stages.contains(&id) {
+                    if !self.completed_stages.is_empty() && Instant::now() >= deadline {
+                        self.partial.push_back(task);
+                        continue;
+                    }
+                    self.completed_stages.push(id);
+                }
+            }
+
+            // Attempt to run task.
+            self.run_task(task, world, spawner);
+        }
+
+        // Wait for remaining systems to complete.
+        while self.runnning_systems_count > 0 {
+            let num = self.wait_for_completion(spawner);
+            self.runnning_systems_count -= num;
+
+            // Run any handlers/oneshots scheduled by these systems
+            while let Some(task) = self.task_queue.pop_front() {
+                self.run_task(task, world, spawner);
+            }
+        }
+
+        self.finish_dispatch(world, start);
+    }
+
+    /// Prepares a dispatch of the given tasks, returning its start time.
+    fn begin_dispatch(&mut self, world: &mut World, tasks: DispatchTasks) -> Instant {
+        self.debug_assert_system_capacity();
+
+        let start = Instant::now();
+        self.skip_deadline = self.dispatch_budget.map(|budget| start + budget);
+        self.busy.take();
+        self.frame_clock.begin(start);
+        self.dispatch_contentions = 0;
+        self.dynamic_tasks.clear();
+
+        for extract in &self.extractors {
+            extract(world, &mut self.resources);
+        }
+
+        if self.resources.contains::<TimeoutBudget>() {
+            self.deadlines
+                .update_budgets(self.resources.get::<TimeoutBudget>());
+        }
+
+        if self.is_first_run {
+            self.is_first_run = false;
+            self.pending_init.clear();
+            self.warmed_up = false;
+            self.stale_resources.clear();
+
+            self.on_first_run(world);
+        } else {
+            if !self.pending_init.is_empty() {
+                self.init_pending_systems(world);
+            }
+            if self.warmed_up {
+                self.warmed_up = false;
+                self.reinit_world_accessors(world);
+            }
+            if !self.stale_resources.is_empty() {
+                // System data holds pointers to the resources it accesses,
+                // so it has to be reloaded to observe the changed resources.
+                let stale = std::mem::take(&mut self.stale_resources);
+                self.reinit_systems_accessing(&stale, world);
+            }
+        }
+
+        match tasks {
+            DispatchTasks::Labeled(label) => {
+                // Systems excluded from the scheduler or disabled are skipped.
+                let systems = &self.systems;
+                let disabled = &self.disabled;
+                self.task_queue.extend(
+                    self.labels[label]
+                        .iter()
+                        .filter(|id| {
+                            matches!(systems.get(id.0), Some(Some(_))) && !disabled.contains(id.0)
+                        })
+                        .map(|id| Task::Oneshot(*id)),
+                );
+            }
+            DispatchTasks::All => self.reset_task_queue(),
+            DispatchTasks::Partial(tasks) => self.task_queue.extend(tasks),
+        }
+
+        start
+    }
+
+    /// Completes a dispatch which started at `start`
+    /// once all of its tasks have completed.
+    fn finish_dispatch(&mut self, world: &mut World, start: Instant) {
+        assert!(self.task_queue.is_empty());
+        assert!(self.running_systems.is_empty());
+        self.unstarted.clear();
+
+        // All systems have completed, so entities queued
+        // through `EntityReserve` can be inserted.
+        if self.resources.contains::<SpawnQueue>() {
+            self.resources.get_mut::<SpawnQueue>().commit(world);
+        }
+
+        self.cleanup_defunct_systems();
+        self.publish_subscriptions();
+
+        let wall = start.elapsed().as_secs_f64() * rayon::current_num_threads() as f64;
+        self.last_parallelism = if wall > 0.0 {
+            self.busy.take().as_secs_f64() / wall
+        } else {
+            0.0
+        };
+        self.last_dispatch.record(
+            self.dispatch_contentions,
+            start.elapsed(),
+            self.last_parallelism,
+        );
+
+        let skipped = self.skipped.swap(0, Ordering::AcqRel);
+        if self.warming_up {
+            self.stats.warmup_dispatches += 1;
+        } else {
+            self.stats.dispatches += 1;
+            self.stats.system_executions -= skipped;
+            self.stats.skipped_systems += skipped;
+        }
+    }
+
+    /// Resets the task queue to the starting queue,
+    /// skipping stages which do not run this dispatch.
+    fn reset_task_queue(&mut self) {
+        for task in self.starting_queue.iter().copied() {
+            if let Task::Stage(id) = task {
+                let counter = &mut self.stage_counters[id.0];
+                if *counter > 0 {
+                    *counter -= 1;
+                    continue;
+                }
+                *counter = self.stage_rates[id.0] - 1;
+            }
+            self.task_queue.push_back(task);
+        }
+    }
+
+    /// Executes all systems `count` times against an empty `World`,
+    /// which is then discarded.
+    fn warmup(&mut self, count: u32) {
+        let mut world = World::new();
+
+        self.warming_up = true;
+        for _ in 0..count {
+            self.execute(&mut world);
+        }
+        self.warming_up = false;
+
+        // Systems may hold pointers into the discarded world, so those
+        // accessing it are initialized again on the first real dispatch.
+        // Other systems keep the data initialized during warmup.
+        self.warmed_up = count > 0;
+    }
+
+    /// Executes all systems and handles events, with the resources
+    /// in `additional` available to systems for the duration of the dispatch.
+    ///
+    /// This is useful for resources which are created fresh each frame.
+    /// The resources are moved back into `additional` once the dispatch
+    /// completes, even if it panics, so the scheduler's own resources are
+    /// left unchanged. Systems accessing them are reinitialized before the
+    /// next dispatch, so they never observe resources which have been
+    /// moved back.
+    ///
+    /// # Panics
+    /// Panics if this scheduler already holds a resource of the same
+    /// type as one in `additional`.
+    pub fn execute_with_resources(&mut self, additional: &mut Resources, world: &mut World) {
+        let ids: Vec<ResourceId> = additional.ids().collect();
+
+        for id in &ids {
+            assert!(
+                !self.resources.contains_id(*id),
+                "{}: resource {:?} is already held by the scheduler",
+                self,
+                id
+            );
+        }
+
+        for id in &ids {
+            let resource = additional.take_boxed(*id).unwrap();
+            self.resources.insert_boxed(*id, resource);
+        }
+        self.stale_resources.extend(ids.iter().copied());
+
+        let guard = ReturnResources {
+            scheduler: self,
+            additional,
+            ids,
+        };
+        guard.scheduler.execute(world);
+    }
+
+    /// Reinitializes all systems and event handlers which access
+    /// any of the given resources.
+    fn reinit_systems_accessing(&mut self, ids: &[ResourceId], world: &World) {
+        let base_ctx = self.create_system_ctx(SystemId::default());
+        let resources = &mut self.resources;
+
+        let accesses_any = |reads: &[ResourceId], writes: &[ResourceId]| {
+            reads.iter().chain(writes).any(|id| ids.contains(id))
+        };
+
+        for sys in self.systems.iter_mut().flatten() {
+            if accesses_any(sys.resource_reads(), sys.resource_writes()) {
+                let ctx = base_ctx.with_id(sys.id());
+
+                sys.init(resources, ctx, world);
+            }
+        }
+
+        for handler in self.event_handlers.iter_mut().flatten() {
+            if accesses_any(handler.resource_reads(), handler.resource_writes()) {
+                let ctx = base_ctx.with_id(handler.id());
+
+                handler.init(resources, ctx, world);
+            }
+        }
+    }
+
+    /// Reinitializes systems which access components, along with all event
+    /// handlers, whose data may hold pointers into a previous world.
+    fn reinit_world_accessors(&mut self, world: &World) {
+        let base_ctx = self.create_system_ctx(SystemId::default());
+        let resources = &mut self.resources;
+
+        for sys in self.systems.iter_mut().flatten() {
+            if !sys.component_reads().is_empty() || !sys.component_writes().is_empty() {
+                let ctx = base_ctx.with_id(sys.id());
+
+                sys.init(resources, ctx, world);
+            }
+        }
+
+        // Event handlers do not declare their component accesses.
+        for handler in self.event_handlers.iter_mut().flatten() {
+            let ctx = base_ctx.with_id(handler.id());
+
+            handler.init(resources, ctx, world);
+        }
+    }
+
+    /// Executes all systems and handles events, recording the time
+    /// spent in each stage and system.
+    ///
+    /// The returned span tree can be converted to the folded stack
+    /// format used by flamegraph tools with `ProfileSpan::to_folded()`.
+    pub fn profile_dispatch(&mut self, world: &mut World) -> ProfileSpan {
+        let stages = Arc::new(Mutex::new(vec![]));
+        self.profile = Some(Arc::clone(&stages));
+
+        let start = Instant::now();
+        self.execute(world);
+        let duration = start.elapsed();
+
+        self.profile = None;
+
+        let mut stages = std::mem::take(&mut *stages.lock());
+        stages.sort_by_key(|(id, _)| id.0);
+
+        let name = match self.debug_label {
+            Some(label) => format!("dispatch {}", label),
+            None => "dispatch".to_owned(),
+        };
+
+        ProfileSpan::new(
+            name,
+            duration,
+            stages.into_iter().map(|(_, span)| span).collect(),
+        )
+    }
+
+    /// Removes systems which have marked themselves as defunct
+    /// from the schedule, recomputing the stages' resource accesses.
+    fn cleanup_defunct_systems(&mut self) {
+        let systems = &self.systems;
+        let defunct: Vec<SystemId> = self
+            .stages
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|id| matches!(&systems[id.0], Some(sys) if sys.is_defunct()))
+            .collect();
+
+        if defunct.is_empty() {
+            return;
+        }
+
+        self.remove_systems(&defunct);
+    }
+
+    /// Removes systems from the schedule, returning them, and
+    /// recomputes the stages' resource accesses.
+    fn remove_systems(&mut self, ids: &[SystemId]) -> Vec<Box<DynSystem>> {
+        let removed = ids
+            .iter()
+            .filter_map(|id| {
+                self.system_reads[id.0].clear();
+                self.system_writes[id.0].clear();
+                self.systems[id.0].take()
+            })
+            .collect();
+
+        for stage in &mut self.stages {
+            stage.retain(|id| !ids.contains(id));
+        }
+
+        // Keep the rates of the remaining stages.
+        let stages = &self.stages;
+        let mut index = 0;
+        self.stage_rates.retain(|_| {
+            index += 1;
+            !stages[index - 1].is_empty()
+        });
+        let mut index = 0;
+        self.stage_counters.retain(|_| {
+            index += 1;
+            !stages[index - 1].is_empty()
+        });
+        self.stages.retain(|stage| !stage.is_empty());
+
+        self.recompute_stage_accesses();
+        *self.system_types.write() = system_types_of(&self.systems);
+
+        removed
+    }
+
+    /// Recomputes the resource accesses of each stage and
+    /// the starting task queue after the stages change.
+    fn recompute_stage_accesses(&mut self) {
+        let system_reads = &self.system_reads;
+        let system_writes = &self.system_writes;
+        self.stage_reads = self
+            .stages
+            .iter()
+            .map(|stage| {
+                stage
+                    .iter()
+                    .flat_map(|id| system_reads[id.0].iter().copied())
+                    .collect()
+            })
+            .collect();
+        self.stage_writes = self
+            .stages
+            .iter()
+            .map(|stage| {
+                stage
+                    .iter()
+                    .flat_map(|id| system_writes[id.0].iter().copied())
+                    .collect()
+            })
+            .collect();
+
+        self.serial_stages = self.find_serial_stages();
+
+        self.starting_queue = Self::create_task_queue(&self.stages);
+        // Stage IDs may have changed, so a partial execution starts over.
+        self.partial.clear();
+    }
+
+    /// Finds the stages whose systems cannot run in parallel, because
+    /// two of them conflict or are ordered by a constraint.
+    fn find_serial_stages(&self) -> BitSet {
+        let conflicting = |a: SystemId, b: SystemId| {
+            let (a_reads, a_writes) = (&self.system_reads[a.0], &self.system_writes[a.0]);
+            let (b_reads, b_writes) = (&self.system_reads[b.0], &self.system_writes[b.0]);
+            a_writes
+                .iter()
+                .any(|write| b_reads.contains(write) || b_writes.contains(write))
+                || b_writes.iter().any(|write| a_reads.contains(write))
+        };
+        let ordered = |a: SystemId, b: SystemId| {
+            self.layout.constraints.iter().any(|constraint| {
+                (constraint.before == a && constraint.after == b)
+                    || (constraint.before == b && constraint.after == a)
+            })
+        };
+
+        self.stages
+            .iter()
+            .enumerate()
+            .filter(|(_, stage)| {
+                stage.iter().enumerate().any(|(i, a)| {
+                    stage[i + 1..]
+                        .iter()
+                        .any(|b| conflicting(*a, *b) || ordered(*a, *b))
+                })
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn on_first_run(&mut self, world: &mut World) {
+        let base_ctx = self.create_system_ctx(SystemId::default());
+        let resources = &mut self.resources;
+
+        // Initialize all systems and event handlers.
+        self.systems
+            .iter_mut()
+            .filter(|sys| sys.is_some())
+            .for_each(|sys| {
+                let sys = sys.as_mut().unwrap();
+
+                let ctx = base_ctx.with_id(sys.id());
+
+                sys.init(resources, ctx, world);
+            });
+
+        self.event_handlers
+            .iter_mut()
+            .filter(|handler| handler.is_some())
+            .for_each(|handler| {
+                let handler = handler.as_mut().unwrap();
+
+                let ctx = base_ctx.with_id(handler.id());
+
+                handler.init(resources, ctx, world);
+            })
+    }
+
+    /// Initializes the systems replaced through `hot_reload_systems()`.
+    fn init_pending_systems(&mut self, world: &World) {
+        let base_ctx = self.create_system_ctx(SystemId::default());
+
+        for id in std::mem::take(&mut self.pending_init) {
+            if let Some(sys) = self.systems[id.0].as_mut() {
+                sys.init(&mut self.resources, base_ctx.with_id(id), world);
+            }
+        }
+    }
+
+    /// Registers a callback which is called for each event of type `E`
+    /// triggered by a system, without being registered as a system.
+    ///
+    /// Observers are called on the scheduler thread once the task which
+    /// triggered the events has completed. They should return quickly,
+    /// since no further tasks are started while they run.
+    pub fn add_observer<E: Event>(&mut self, observer: impl Fn(&E) + Send + Sync + 'static) {
+        self.observers
+            .get_mut_or_extend(event_id_for::<E>().0)
+            .push(observer::observer(observer));
+    }
+
+    /// Passes events triggered by completed tasks to observers.
+    fn run_observers(&mut self) {
+        for events in self.pending_observed.drain(..) {
+            for observer in &self.observers[events.id.0] {
+                observer(events.ptr, events.len);
+            }
+        }
+    }
+
+    /// Triggers an event manually. It will be handled
+    /// on the next call to `execute()`.
+    pub fn trigger<E>(&mut self, event: E)
+    where
+        E: Event,
+    {
+        let id = event_id_for::<E>();
+        // Don't trigger events which have no handlers.
+        if self.end_of_tick_handlers.len() <= id.0 {
+            return;
+        }
+
+        let ptr = self.bump.get_or_default().alloc(event) as *mut E as *const ();
+        let len = 1;
+        self.task_queue.push_front(Task::HandleEvent(id, ptr, len));
+    }
+
+    fn run_task(&mut self, task: Task, world: &mut World, spawner: Spawner) {
+        if self.is_task_scoped(&task) {
+            self.run_scoped_task(task, world, spawner);
+            return;
+        }
+
+        self.run_task_unscoped(task, world, spawner);
+    }
+
+    fn run_task_unscoped(&mut self, task: Task, world: &mut World, spawner: Spawner) {
+        let reads = reads_for_task(
+            &self.stage_reads,
+            &self.system_reads,
+            &self.event_reads,
+            &task,
+        );
+        let writes = writes_for_task(
+            &self.stage_writes,
+            &self.system_writes,
+            &self.event_writes,
+            &task,
+        );
+
+        // For event handlers and oneshots, we have to check that the handler
+        // or system is not already running, since it takes &mut self.
+        let not_running = match &task {
+            Task::HandleEvent(id, _, _)
+                if self.end_of_tick_handlers[id.0]
+                    .iter()
+                    .any(|id| self.running_systems.contains(id.0)) =>
+            {
+                Err(())
+            }
+            Task::Oneshot(id) if self.running_systems.contains(id.0) => Err(()),
+            _ => Ok(()),
+        };
+
+        let obtained = match try_obtain_resources(
+            reads,
+            writes,
+            &mut self.reads_held,
+            &mut self.writes_held,
+        ) {
+            Ok(()) => Ok(()),
+            Err(resource) => {
+                self.dispatch_contentions += 1;
+                if !self.warming_up {
+                    *self.resource_contentions.get_mut_or_extend(resource.0) += 1;
+                }
+                Err(())
+            }
+        };
+
+        match obtained.and(not_running) {
+            Ok(()) => {
+                // Run task and proceed.
+                #[cfg(feature = "log")]
+                {
+                    log::trace!(
+                        "Dispatching task of type {:?} (reads: {:?}, writes: {:?})",
+                        task,
+                        reads,
+                        writes
+                    );
+                }
+                self.trace_task(&task, TraceAction::Acquire);
+                self.record_dynamic_task(&task);
+                let systems = self.dispatch_task(task, world, spawner);
+                self.runnning_systems_count += systems;
+                self.running_tasks += 1;
+                self.peak_running_tasks = self.peak_running_tasks.max(self.running_tasks);
+                if !self.warming_up {
+                    let disabled = match task {
+                        Task::Stage(id) => self.stages[id.0]
+                            .iter()
+                            .filter(|id| self.disabled.contains(id.0))
+                            .count(),
+                        _ => 0,
+                    };
+                    self.stats.system_executions += (systems - disabled) as u64;
+                }
+
+                // In the canonical order, tasks never overlap.
+                if self.canonical {
+                    while self.running_tasks > 0 {
+                        let num = self.wait_for_completion(spawner);
+                        self.runnning_systems_count -= num;
+                    }
+                }
+            }
+            Err(()) => {
+                // Execution is blocked: wait for tasks to finish.
+                // Re-push the task we attempted to run to the queue.
+                // TODO: optimize this
+                self.task_queue.push_front(task);
+
+                // `execute_async()` awaits the completion itself.
+                #[cfg(feature = "async")]
+                {
+                    if self.yield_when_blocked {
+                        self.blocked = true;
+                        return;
+                    }
+                }
+
+                let num = self.wait_for_completion(spawner);
+                self.runnning_systems_count -= num;
+            }
+        }
+    }
+
+    /// Returns whether any hook registered through `with_resource_scope()`
+    /// replaces a resource accessed by the given task.
+    fn is_task_scoped(&self, task: &Task) -> bool {
+        if self.resource_scopes.is_empty() {
+            return false;
+        }
+
+        let reads = reads_for_task(
+            &self.stage_reads,
+            &self.system_reads,
+            &self.event_reads,
+            task,
+        );
+        let writes = writes_for_task(
+            &self.stage_writes,
+            &self.system_writes,
+            &self.event_writes,
+            task,
+        );
+
+        self.resource_scopes.iter().any(|scope| {
+            let resource = scope.resource_id();
+            reads.contains(&resource) || writes.contains(&resource)
+        })
+    }
+
+    /// Runs a task inside the resource scopes which apply to it,
+    /// blocking until the task completes.
+    ///
+    /// Since the scopes replace resources, all other
+    /// tasks must complete before the task runs.
+    fn run_scoped_task(&mut self, task: Task, world: &mut World, spawner: Spawner) {
+        self.wait_for_all(spawner);
+
+        let mut all_scopes = std::mem::take(&mut self.resource_scopes);
+        let reads = reads_for_task(
+            &self.stage_reads,
+            &self.system_reads,
+            &self.event_reads,
+            &task,
+        );
+        let writes = writes_for_task(
+            &self.stage_writes,
+            &self.system_writes,
+            &self.event_writes,
+            &task,
+        );
+        let mut scopes: Vec<&mut Box<dyn RawResourceScope>> = all_scopes
+            .iter_mut()
+            .filter(|scope| {
+                let resource = scope.resource_id();
+                reads.contains(&resource) || writes.contains(&resource)
+            })
+            .collect();
+
+        self.run_in_scopes(task, &mut scopes, world, spawner);
+
+        self.resource_scopes = all_scopes;
+    }
+
+    fn run_in_scopes(
+        &mut self,
+        task: Task,
+        scopes: &mut [&mut Box<dyn RawResourceScope>],
+        world: &mut World,
+        spawner: Spawner,
+    ) {
+        match scopes.split_first_mut() {
+            Some((scope, rest)) => scope.run(self, &mut |scheduler| {
+                scheduler.run_in_scopes(task, rest, world, spawner)
+            }),
+            None => {
+                self.run_task_unscoped(task, world, spawner);
+                self.wait_for_all(spawner);
+            }
+        }
+    }
+
+    /// Waits until all running tasks have completed.
+    fn wait_for_all(&mut self, spawner: Spawner) {
+        while self.runnning_systems_count > 0 {
+            let num = self.wait_for_completion(spawner);
+            self.runnning_systems_count -= num;
+        }
+    }
+
+    /// Waits for messages from running systems and handles them.
+    ///
+    /// At any point, returns with the number of systems which have completed.
+    fn wait_for_completion(&mut self, spawner: Spawner) -> usize {
+        // Unwrap is allowed because the channel never becomes disconnected
+        // (`Scheduler` holds a `Sender` handle for it).
+        // This will never block indefinitely because there are always
+        // systems running when this is invoked.
+        let msg = match spawner {
+            Spawner::Global | Spawner::Pool(_) => self.recv_message(),
+            Spawner::Scope(_) => self.help_until_message(),
+        };
+
+        self.handle_message(msg)
+    }
+
+    /// Handles a message sent by a task, returning
+    /// the number of systems which completed.
+    fn handle_message(&mut self, msg: TaskMessage) -> usize {
+        match msg {
+            // TODO: events
+            TaskMessage::SystemComplete(id) => {
+                self.run_observers();
+                self.trace_task(&Task::Oneshot(id), TraceAction::Release);
+                self.running_tasks -= 1;
+                self.release_resources_for_system(id);
+                self.running_systems.remove(id.0);
+                1
+            }
+            TaskMessage::StageComplete(id) => {
+                self.run_observers();
+                self.trace_task(&Task::Stage(id), TraceAction::Release);
+                self.running_tasks -= 1;
+                self.release_resources_for_stage(id);
+                let running_systems = &mut self.running_systems;
+                self.stages[id.0].iter().for_each(|id| {
+                    running_systems.remove(id.0);
+                });
+                self.stages[id.0].len()
+            }
+            TaskMessage::TriggerEvents {
+                id,
+                ptr,
+                len,
+                visit,
+            } => {
+                if self
+                    .observers
+                    .get(id.0)
+                    .map(|observers| !observers.is_empty())
+                    .unwrap_or(false)
+                {
+                    self.pending_observed.push(PendingEvents { id, ptr, len });
+                }
+
+                let handled = self
+                    .end_of_tick_handlers
+                    .get(id.0)
+                    .map(|handlers| !handlers.is_empty())
+                    .unwrap_or(false);
+                if !handled {
+                    if let Some(handler) = &self.unhandled_event_handler {
+                        // Safety: `visit` matches the type of the events.
+                        unsafe { visit(ptr, len, &mut |event| handler(id, event)) };
+                    }
+                }
+
+                if self.end_of_tick_handlers.len() <= id.0 {
+                    return 0;
+                }
+
+                self.task_queue.push_back(Task::HandleEvent(id, ptr, len));
+                0
+            }
+            TaskMessage::EventHandlingComplete(id) => {
+                self.run_observers();
+                self.trace_task(
+                    &Task::HandleEvent(id, std::ptr::null(), 0),
+                    TraceAction::Release,
+                );
+                self.running_tasks -= 1;
                 self.release_resources_for_event_handler(id);
                 let running_systems = &mut self.running_systems;
                 self.end_of_tick_handlers[id.0].iter().for_each(|id| {
@@ -559,59 +2141,210 @@ impl Scheduler {
                 });
                 self.end_of_tick_handlers[id.0].len()
             }
+            TaskMessage::ScheduleOneshot(id) => {
+                // The system may have been removed or disabled since the request was sent.
+                if matches!(self.systems.get(id.0), Some(Some(_))) && !self.disabled.contains(id.0)
+                {
+                    self.task_queue.push_front(Task::Oneshot(id));
+                }
+                0
+            }
+            TaskMessage::ReleaseWrite(system, resource) => {
+                // The write is only held while the system's task is running.
+                if self.running_systems.contains(system.0) && self.writes_held.remove(resource.0) {
+                    self.released_writes.push((system, resource));
+                }
+                0
+            }
+        }
+    }
+
+    /// Runs pending work from the current thread pool until a
+    /// message is received. Since the caller of `execute_within_scope()`
+    /// is usually a worker thread, blocking it could otherwise starve the
+    /// pool of threads to run the spawned tasks on.
+    fn help_until_message(&mut self) -> TaskMessage {
+        loop {
+            if let Ok(msg) = self.receiver.try_recv() {
+                return msg;
+            }
+
+            match rayon::yield_now() {
+                Some(rayon::Yield::Executed) => (),
+                // No pending work: remaining tasks are already running elsewhere.
+                _ => return self.recv_message(),
+            }
+        }
+    }
+
+    /// Blocks until a message is received. If a stall timeout is set
+    /// and no message arrives in time, a task which has not started
+    /// is run inline on this thread, so that dispatches make progress
+    /// even if the thread pool never runs the spawned tasks.
+    ///
+    /// The task still sends its completion message, so resources
+    /// are released as if it had run on the thread pool.
+    fn recv_message(&mut self) -> TaskMessage {
+        let timeout = match self.stall_timeout {
+            Some(timeout) => timeout,
+            None => return self.receiver.recv().unwrap(),
+        };
+
+        loop {
+            match self.receiver.recv_timeout(timeout) {
+                Ok(msg) => return msg,
+                Err(RecvTimeoutError::Timeout) => self.run_unstarted_task(),
+                Err(RecvTimeoutError::Disconnected) => unreachable!(),
+            }
+        }
+    }
+
+    /// Runs the oldest spawned task which has not started yet,
+    /// if there is one.
+    fn run_unstarted_task(&mut self) {
+        self.unstarted.retain(|slot| slot.lock().is_some());
+
+        let task = self.unstarted.iter().find_map(|slot| slot.lock().take());
+        if let Some(task) = task {
+            #[cfg(feature = "log")]
+            log::warn!("{}: thread pool stalled; running a task inline", self);
+            task();
+        }
+    }
+
+    /// Spawns a task, keeping it claimable by `run_unstarted_task()`
+    /// if a stall timeout is set.
+    fn spawn(&mut self, spawner: Spawner, task: impl FnOnce() + Send + 'static) {
+        if self.stall_timeout.is_none() {
+            spawner.spawn(task);
+            return;
+        }
+
+        let slot: InlineSlot = Arc::new(Mutex::new(Some(Box::new(task))));
+        self.unstarted.push(Arc::clone(&slot));
+        spawner.spawn(move || {
+            let task = slot.lock().take();
+            if let Some(task) = task {
+                task();
+            }
+        });
+    }
+
+    /// Records the acquisition or release of any traced
+    /// resources accessed by the given task.
+    fn trace_task(&mut self, task: &Task, action: TraceAction) {
+        if self.traced_resources.is_empty() {
+            return;
+        }
+
+        let reads = reads_for_task(
+            &self.stage_reads,
+            &self.system_reads,
+            &self.event_reads,
+            task,
+        );
+        let writes = writes_for_task(
+            &self.stage_writes,
+            &self.system_writes,
+            &self.event_writes,
+            task,
+        );
+
+        let systems: Vec<SystemId> = match task {
+            Task::Stage(id) => self.stages[id.0].to_vec(),
+            Task::Oneshot(id) => vec![*id],
+            Task::HandleEvent(id, _, _) => self.end_of_tick_handlers[id.0].to_vec(),
+        };
+
+        let traced = &self.traced_resources;
+        let accesses = reads
+            .iter()
+            .map(|resource| (*resource, false))
+            .chain(writes.iter().map(|resource| (*resource, true)))
+            .filter(|(resource, _)| traced.contains(resource));
+
+        for (resource, write) in accesses {
+            #[cfg(feature = "log")]
+            {
+                log::debug!(
+                    "{:?} of resource {:?} (write: {}) by systems {:?}",
+                    action,
+                    resource,
+                    write,
+                    systems
+                );
+            }
+
+            self.resource_trace.push(ResourceTraceEntry {
+                resource,
+                action,
+                write,
+                systems: systems.clone(),
+            });
         }
     }
 
     fn release_resources_for_system(&mut self, id: SystemId) {
         let reads = &self.system_reads[id.0];
         let writes = &self.system_writes[id.0];
+        let released = take_released_writes(&mut self.released_writes, &[id]);
 
         for read in reads {
             self.reads_held[read.0] -= 1;
         }
 
         for write in writes {
-            self.writes_held.remove(write.0);
+            if !released.contains(write) {
+                self.writes_held.remove(write.0);
+            }
         }
     }
 
     fn release_resources_for_stage(&mut self, id: StageId) {
+        let released = take_released_writes(&mut self.released_writes, &self.stages[id.0]);
+
         for read in &self.stage_reads[id.0] {
             self.reads_held[read.0] -= 1;
         }
 
         for write in &self.stage_writes[id.0] {
-            self.writes_held.remove(write.0);
+            if !released.contains(write) {
+                self.writes_held.remove(write.0);
+            }
         }
     }
 
     fn release_resources_for_event_handler(&mut self, id: EventId) {
         let reads = &self.event_reads[id.0];
         let writes = &self.event_writes[id.0];
+        let released =
+            take_released_writes(&mut self.released_writes, &self.end_of_tick_handlers[id.0]);
 
         for read in reads {
             self.reads_held[read.0] -= 1;
         }
 
         for write in writes {
-            self.writes_held.remove(write.0);
+            if !released.contains(write) {
+                self.writes_held.remove(write.0);
+            }
         }
     }
 
     /// Dispatches a task, returning the number of systems spawned.
-    fn dispatch_task(&mut self, task: Task, world: &mut World) -> usize {
+    fn dispatch_task(&mut self, task: Task, world: &mut World, spawner: Spawner) -> usize {
         match task {
             Task::Stage(id) => {
                 let running_systems = &mut self.running_systems;
                 self.stages[id.0].iter().for_each(|id| {
                     running_systems.insert(id.0);
                 });
-                self.dispatch_stage(id, world);
+                self.dispatch_stage(id, world, spawner);
                 self.stages[id.0].len()
             }
             Task::Oneshot(id) => {
                 self.running_systems.insert(id.0);
-                self.dispatch_system(id, world);
+                self.dispatch_system(id, world, spawner);
                 1
             }
             Task::HandleEvent(id, ptr, len) => {
@@ -622,7 +2355,7 @@ impl Scheduler {
                     running_systems.insert(id.0);
                 });
 
-                self.dispatch_event_handlers(id, ptr, len, world);
+                self.dispatch_event_handlers(id, ptr, len, world, spawner);
 
                 let handlers = &self.end_of_tick_handlers[id.0];
                 handlers.len()
@@ -630,7 +2363,29 @@ impl Scheduler {
         }
     }
 
-    fn dispatch_stage(&mut self, id: StageId, world: &mut World) {
+    /// Returns the systems of the given stage which were assigned a thread
+    /// pool through `SchedulerBuilder::with_label_pool()`, grouped by pool.
+    fn pooled_systems(&self, id: StageId) -> Vec<(Arc<rayon::ThreadPool>, Vec<SystemId>)> {
+        let mut groups: Vec<(Arc<rayon::ThreadPool>, Vec<SystemId>)> = vec![];
+        if self.system_pools.is_empty() {
+            return groups;
+        }
+
+        for sys_id in &self.stages[id.0] {
+            let pool = match self.system_pools.get(&sys_id.0) {
+                Some(pool) => pool,
+                None => continue,
+            };
+            match groups.iter_mut().find(|(p, _)| Arc::ptr_eq(p, pool)) {
+                Some((_, ids)) => ids.push(*sys_id),
+                None => groups.push((Arc::clone(pool), vec![*sys_id])),
+            }
+        }
+
+        groups
+    }
+
+    fn dispatch_stage(&mut self, id: StageId, world: &mut World, spawner: Spawner) {
         // Rather than spawning each system independently, we optimize
         // this by running them in batch. This reduces synchronization overhead
         // with the scheduler using channels.
@@ -646,30 +2401,144 @@ impl Scheduler {
         let world = SharedRawPtr(world as *const World);
 
         let sender = self.sender.clone();
-        let bump = Arc::clone(&self.bump);
+        let base_ctx = self.create_system_ctx(SystemId::default());
+        let long_stage_threshold = self.long_stage_threshold;
+        let profile = self.profile.clone();
+
+        let skippable = SharedRawPtr(&self.skippable as *const BitSet);
+        let disabled = SharedRawPtr(&self.disabled as *const BitSet);
+        let busy = Arc::clone(&self.busy);
+        let skip_deadline = self.skip_deadline;
+        let skipped = Arc::clone(&self.skipped);
+        let canonical = self.canonical;
+        let serial = self.serial_stages.contains(id.0);
+        let pooled = self.pooled_systems(id);
+
+        #[cfg(feature = "trace")]
+        let stage_span = tracing::info_span!("stage", id = id.0);
+
+        self.spawn(spawner, move || {
+            // Systems run on other worker threads, so their spans
+            // are given the stage span as an explicit parent.
+            #[cfg(feature = "trace")]
+            let _span = stage_span.enter();
+
+            let system = |sys_id: &SystemId| unsafe {
+                (
+                    (&mut *systems.0)[sys_id.0].as_mut().unwrap(),
+                    base_ctx.with_id(*sys_id),
+                )
+            };
+
+            // Skippable systems are not started once the dispatch budget is exceeded.
+            // Disabled systems are never started.
+            let skip = |sys_id: &SystemId| {
+                if unsafe { &*disabled.0 }.contains(sys_id.0) {
+                    return true;
+                }
 
-        rayon::spawn(move || {
-            unsafe {
-                (&*stage.0)
-                    .par_iter()
-                    .map(|sys_id| (sys_id, (&mut *systems.0)[sys_id.0].as_mut().unwrap()))
-                    .for_each(|(sys_id, sys)| {
-                        let ctx = SystemCtx {
-                            id: *sys_id,
-                            sender: sender.clone(),
-                            bump: Arc::clone(&bump),
-                        };
-
-                        sys.execute_raw(&*resources.0, ctx, &*world.0);
-                    });
+                let skip = match skip_deadline {
+                    Some(deadline) => {
+                        unsafe { &*skippable.0 }.contains(sys_id.0) && Instant::now() >= deadline
+                    }
+                    None => false,
+                };
+                if skip {
+                    skipped.fetch_add(1, Ordering::Relaxed);
+                }
+                skip
+            };
+
+            let run_system = |sys_id: &SystemId| unsafe {
+                let (sys, ctx) = system(sys_id);
+                if skip(sys_id) {
+                    return sys.name();
+                }
+                #[cfg(feature = "trace")]
+                let _span =
+                    tracing::info_span!(parent: &stage_span, "system", name = sys.name()).entered();
+                base_ctx.deadlines.start(*sys_id);
+                busy.time(|| sys.execute_raw(&*resources.0, ctx, &*world.0));
+                base_ctx.deadlines.finish(*sys_id);
+                sys.name()
+            };
+
+            let profile_system = |sys_id: &SystemId| unsafe {
+                let (sys, ctx) = system(sys_id);
+                if skip(sys_id) {
+                    return ProfileSpan::new(sys.name(), Duration::default(), vec![]);
+                }
+                #[cfg(feature = "trace")]
+                let _span =
+                    tracing::info_span!(parent: &stage_span, "system", name = sys.name()).entered();
+
+                let start = Instant::now();
+                base_ctx.deadlines.start(*sys_id);
+                let phases = sys.execute_profiled(&*resources.0, ctx, &*world.0);
+                base_ctx.deadlines.finish(*sys_id);
+                let duration = start.elapsed();
+                busy.add(duration);
+
+                let phases = phases
+                    .into_iter()
+                    .map(|(name, duration)| ProfileSpan::new(name, duration, vec![]))
+                    .collect();
+                ProfileSpan::new(sys.name(), duration, phases)
+            };
+
+            let stage = unsafe { &*stage.0 };
+
+            if canonical || (serial && profile.is_none()) {
+                let mut ids = stage.to_vec();
+                // Serial stages keep their order, which respects ordering constraints.
+                if !serial {
+                    ids.sort_by_key(|id| id.0);
+                }
+                ids.iter().for_each(|sys_id| {
+                    run_system(sys_id);
+                });
+            } else if let Some(profile) = profile {
+                let start = Instant::now();
+                let systems = if serial {
+                    stage.iter().map(profile_system).collect()
+                } else {
+                    par_map_pooled(stage, &pooled, profile_system)
+                };
+                let span = ProfileSpan::new(format!("stage {}", id.0), start.elapsed(), systems);
+                profile.lock().push((id, span));
+            } else {
+                match long_stage_threshold {
+                    Some(threshold) => {
+                        let start = Instant::now();
+                        let slowest = par_map_pooled(stage, &pooled, |sys_id| {
+                            let start = Instant::now();
+                            let name = run_system(sys_id);
+                            (start.elapsed(), name)
+                        })
+                        .into_iter()
+                        .max_by_key(|(elapsed, _)| *elapsed);
+                        let elapsed = start.elapsed();
+
+                        if elapsed > threshold {
+                            warn_long_stage(id, elapsed, threshold, slowest);
+                        }
+                    }
+                    None => {
+                        par_map_pooled(stage, &pooled, |sys_id| {
+                            run_system(sys_id);
+                        });
+                    }
+                }
             }
 
             // TODO: events, oneshot
-            sender.send(TaskMessage::StageComplete(id)).unwrap();
+            sender
+                .send(TaskMessage::StageComplete(id), Priority::Normal)
+                .unwrap();
         });
     }
 
-    fn dispatch_system(&mut self, id: SystemId, world: &World) {
+    fn dispatch_system(&mut self, id: SystemId, world: &World, spawner: Spawner) {
         let resources = SharedRawPtr(&self.resources as *const Resources);
         let world = SharedRawPtr(world as *const World);
 
@@ -680,17 +2549,37 @@ impl Scheduler {
 
         let ctx = self.create_system_ctx(id);
 
+        #[cfg(feature = "trace")]
+        let span =
+            tracing::info_span!("system", name = self.systems[id.0].as_ref().unwrap().name());
+
         let sender = self.sender.clone();
-        rayon::spawn(move || {
+        let busy = Arc::clone(&self.busy);
+
+        let pool = self.system_pools.get(&id.0).cloned();
+        let spawner = match &pool {
+            Some(pool) => Spawner::Pool(pool),
+            None => spawner,
+        };
+
+        self.spawn(spawner, move || {
+            #[cfg(feature = "trace")]
+            let _span = span.enter();
+
             unsafe {
                 // Safety: the world is not dropped while the system
                 // executes, since `execute` will not return until
                 // all systems have completed.
-                (&mut *system.0).execute_raw(&*resources.0, ctx, &*world.0);
+                let deadlines = Arc::clone(&ctx.deadlines);
+                deadlines.start(id);
+                busy.time(|| (&mut *system.0).execute_raw(&*resources.0, ctx, &*world.0));
+                deadlines.finish(id);
             }
 
             // TODO: events
-            sender.send(TaskMessage::SystemComplete(id)).unwrap();
+            sender
+                .send(TaskMessage::SystemComplete(id), Priority::Normal)
+                .unwrap();
         });
     }
 
@@ -700,6 +2589,7 @@ impl Scheduler {
         ptr: *const (),
         len: usize,
         world: &mut World,
+        spawner: Spawner,
     ) {
         let handler_ids =
             SharedRawPtr(&self.end_of_tick_handlers[id.0] as *const SmallVec<[SystemId; 4]>);
@@ -710,9 +2600,10 @@ impl Scheduler {
         let ptr = SharedRawPtr(ptr);
         let world = SharedRawPtr(world as *const World);
 
-        let bump = Arc::clone(&self.bump);
+        let base_ctx = self.create_system_ctx(SystemId::default());
+        let busy = Arc::clone(&self.busy);
 
-        rayon::spawn(move || {
+        self.spawn(spawner, move || {
             // Safety: see dispatch_system().
             unsafe {
                 (&*handler_ids.0)
@@ -721,16 +2612,16 @@ impl Scheduler {
                     .for_each(|(handler_id, handler)| {
                         debug_assert_eq!(handler.event_id(), id);
 
-                        let ctx = SystemCtx {
-                            id: *handler_id,
-                            sender: sender.clone(),
-                            bump: Arc::clone(&bump),
-                        };
+                        let ctx = base_ctx.with_id(*handler_id);
 
-                        handler.handle_raw_batch(ptr.0, len, &*resources.0, ctx, &*world.0);
+                        busy.time(|| {
+                            handler.handle_raw_batch(ptr.0, len, &*resources.0, ctx, &*world.0)
+                        });
                     });
 
-                sender.send(TaskMessage::EventHandlingComplete(id)).unwrap();
+                sender
+                    .send(TaskMessage::EventHandlingComplete(id), Priority::Normal)
+                    .unwrap();
             }
         });
     }
@@ -740,31 +2631,198 @@ impl Scheduler {
             sender: self.sender.clone(),
             id,
             bump: Arc::clone(&self.bump),
+            detached: Arc::clone(&self.detached),
+            world_generation: Arc::clone(&self.world_generation),
+            deadlines: Arc::clone(&self.deadlines),
+            frame_clock: Arc::clone(&self.frame_clock),
+            last_dispatch: Arc::clone(&self.last_dispatch),
+            frame_context: Arc::clone(&self.frame_context),
+            system_types: Arc::clone(&self.system_types),
+            system_pools: Arc::clone(&self.system_pools),
+        }
+    }
+}
+
+impl Display for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        DebugLabel(self.debug_label).fmt(f)
+    }
+}
+
+/// Moves the resources lent by `Scheduler::execute_with_resources()`
+/// back when dropped, including when a dispatch panics.
+struct ReturnResources<'a> {
+    scheduler: &'a mut Scheduler,
+    additional: &'a mut Resources,
+    ids: Vec<ResourceId>,
+}
+
+impl<'a> Drop for ReturnResources<'a> {
+    fn drop(&mut self) {
+        for id in &self.ids {
+            if let Some(resource) = self.scheduler.resources.take_boxed(*id) {
+                self.additional.insert_boxed(*id, resource);
+            }
+        }
+        self.scheduler
+            .stale_resources
+            .extend(self.ids.iter().copied());
+    }
+}
+
+/// Waits for running systems and resets the dispatch state
+/// when an `execute_async()` future is dropped before it completes.
+///
+/// Running systems hold pointers into the world and the resources,
+/// so the dispatch must not be abandoned while any of them is running.
+#[cfg(feature = "async")]
+struct AbandonDispatch<'a> {
+    scheduler: &'a mut Scheduler,
+    completed: bool,
+}
+
+#[cfg(feature = "async")]
+impl<'a> Drop for AbandonDispatch<'a> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+
+        let scheduler = &mut *self.scheduler;
+        scheduler.wait_for_all(Spawner::Global);
+        scheduler.task_queue.clear();
+        scheduler.unstarted.clear();
+        scheduler.yield_when_blocked = false;
+        scheduler.blocked = false;
+    }
+}
+
+/// Formats a scheduler's debug label for messages.
+struct DebugLabel(Option<&'static str>);
+
+impl Display for DebugLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(label) => write!(f, "scheduler \"{}\"", label),
+            None => f.write_str("scheduler"),
+        }
+    }
+}
+
+/// Maps each system of `stage` in parallel, running the systems in
+/// `pooled` on their assigned thread pools and the rest on the current one.
+///
+/// Results are not in stage order when any system is pooled.
+fn par_map_pooled<R: Send>(
+    stage: &[SystemId],
+    pooled: &[(Arc<rayon::ThreadPool>, Vec<SystemId>)],
+    f: impl Fn(&SystemId) -> R + Sync,
+) -> Vec<R> {
+    let f = &f;
+    if pooled.is_empty() {
+        return stage.par_iter().map(f).collect();
+    }
+
+    let results = Mutex::new(Vec::with_capacity(stage.len()));
+    let results_ref = &results;
+    rayon::scope(|scope| {
+        for (pool, ids) in pooled {
+            scope.spawn(move |_| {
+                let mapped: Vec<R> = pool.install(|| ids.par_iter().map(f).collect());
+                results_ref.lock().extend(mapped);
+            });
+        }
+
+        let rest: Vec<R> = stage
+            .par_iter()
+            .filter(|id| !pooled.iter().any(|(_, ids)| ids.contains(id)))
+            .map(f)
+            .collect();
+        results_ref.lock().extend(rest);
+    });
+    results.into_inner()
+}
+
+/// Maps the `system_type_id()` of each of the given systems to its ID.
+///
+/// If several systems have the same type, the one with the lowest ID is used.
+fn system_types_of(systems: &[Option<Box<DynSystem>>]) -> HashMap<TypeId, SystemId> {
+    let mut types = HashMap::new();
+    for system in systems.iter().flatten() {
+        if let Some(type_id) = system.system_type_id() {
+            types.entry(type_id).or_insert_with(|| system.id());
+        }
+    }
+    types
+}
+
+/// Removes and returns the writes released early
+/// through `ScopedWrite` by any of the given systems.
+fn take_released_writes(
+    released_writes: &mut Vec<(SystemId, ResourceId)>,
+    systems: &[SystemId],
+) -> SmallVec<[ResourceId; 4]> {
+    let mut taken = SmallVec::new();
+    if released_writes.is_empty() {
+        return taken;
+    }
+
+    released_writes.retain(|(system, resource)| {
+        if systems.contains(system) {
+            taken.push(*resource);
+            false
+        } else {
+            true
+        }
+    });
+    taken
+}
+
+/// Logs a warning for a stage which took longer than the
+/// threshold set by `SchedulerBuilder::warn_on_long_stage()`.
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+fn warn_long_stage(
+    id: StageId,
+    elapsed: Duration,
+    threshold: Duration,
+    slowest: Option<(Duration, &'static str)>,
+) {
+    #[cfg(feature = "log")]
+    {
+        if let Some((slowest_elapsed, slowest_name)) = slowest {
+            log::warn!(
+                "stage {} took {:?}, exceeding the threshold of {:?}; slowest system: {} ({:?})",
+                id.0,
+                elapsed,
+                threshold,
+                slowest_name,
+                slowest_elapsed
+            );
         }
     }
 }
 
-/// Attempts to acquire resources for a task, returning `Err` if
-/// there was a conflict and `Ok` if successful.
+/// Attempts to acquire resources for a task, returning `Err` with
+/// the conflicting resource if there was a conflict and `Ok` if successful.
 fn try_obtain_resources(
     reads: &ResourceVec,
     writes: &ResourceVec,
     reads_held: &mut [u32],
     writes_held: &mut BitSet,
-) -> Result<(), ()> {
+) -> Result<(), ResourceId> {
     // First, go through resources and confirm that there are no conflicting
     // accessors.
     // Since both read and write dependencies will only conflict with another resource
     // access when there is another write access, we can interpret them in the same way.
     for resource in reads.iter().chain(writes) {
         if writes_held.contains(resource.0) {
-            return Err(()); // Conflict
+            return Err(*resource); // Conflict
         }
     }
     // Write resources will also conflict with existing read ones.
     for resource in writes {
         if reads_held[resource.0] > 0 {
-            return Err(()); // Conflict
+            return Err(*resource); // Conflict
         }
     }
 
@@ -809,9 +2867,67 @@ fn writes_for_task<'a>(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::resource_id_for;
 
     #[test]
     fn check_scheduler_traits() {
         static_assertions::assert_impl_all!(Scheduler: Send, Sync);
     }
+
+    #[test]
+    fn systems_blocking_on_resource() {
+        struct Held;
+        struct Other;
+
+        let mut scheduler =
+            unsafe { Scheduler::new(vec![], vec![], vec![], vec![], Resources::new()) };
+        let (a, b) = (SystemId(0), SystemId(1));
+        let (held, other) = (resource_id_for::<Held>(), resource_id_for::<Other>());
+
+        let len = SYSTEM_ID_MAPPINGS.lock().len().max(2);
+        scheduler.system_reads.resize(len, smallvec![]);
+        scheduler.system_writes.resize(len, smallvec![]);
+        scheduler.system_writes[a.0].push(held);
+        scheduler.system_reads[b.0].push(other);
+
+        assert!(scheduler.systems_blocking_on_resource(held).is_empty());
+
+        scheduler.running_systems.insert(a.0);
+        scheduler.running_systems.insert(b.0);
+        scheduler.writes_held.insert(held.0);
+
+        assert_eq!(scheduler.systems_blocking_on_resource(held), vec![a]);
+        assert!(scheduler.systems_blocking_on_resource(other).is_empty());
+    }
+    #[test]
+    fn force_initialize() {
+        struct Counter(u32);
+
+        struct Increment;
+
+        impl crate::System for Increment {
+            type SystemData = crate::Write<Counter>;
+
+            fn run(&mut self, counter: <Self::SystemData as crate::SystemData>::Output) {
+                counter.0 += 1;
+            }
+        }
+
+        let mut resources = Resources::new();
+        resources.insert(Counter(0));
+        let scheduler = unsafe { Scheduler::new(vec![], vec![], vec![], vec![], Resources::new()) };
+        let world = World::new();
+
+        let mut system = crate::CachedSystem::new(Increment, "increment");
+        assert!(!system.data_is_initialized());
+
+        let ctx = scheduler.create_system_ctx(system.id());
+        system.force_initialize(&mut resources, ctx.clone(), &world);
+        assert!(system.data_is_initialized());
+
+        unsafe {
+            system.execute_raw(&resources, ctx, &world);
+        }
+        assert_eq!(resources.get::<Counter>().0, 1);
+    }
 }
@@ -0,0 +1,56 @@
+//! Callbacks which observe events outside of systems.
+
+use crate::{Event, EventId};
+use std::any::Any;
+use std::slice;
+
+/// An observer registered through `Scheduler::add_observer()`,
+/// type-erased over the event type. It is called with a pointer
+/// to a slice of events and the length of the slice.
+pub(crate) type Observer = Box<dyn Fn(*const (), usize) + Send + Sync>;
+
+/// Wraps `observer` so that it is called for each event in a slice.
+pub(crate) fn observer<E: Event>(observer: impl Fn(&E) + Send + Sync + 'static) -> Observer {
+    Box::new(move |ptr, len| {
+        // Safety: the scheduler only passes slices of events
+        // whose ID matches the type of the observer.
+        let events = unsafe { slice::from_raw_parts(ptr as *const E, len) };
+        events.iter().for_each(&observer);
+    })
+}
+
+/// Handler registered through `SchedulerBuilder::with_unhandled_event_handler()`.
+pub(crate) type UnhandledEventHandler = Box<dyn Fn(EventId, &dyn Any) + Send + Sync>;
+
+/// Calls a function with each event in a type-erased slice
+/// of events, passed as a pointer and a length.
+///
+/// # Safety
+/// The pointer must point to a slice of events of the type
+/// for which the function was instantiated.
+pub(crate) type VisitEvents = unsafe fn(*const (), usize, &mut dyn FnMut(&dyn Any));
+
+/// Implementation of `VisitEvents` for events of type `E`.
+pub(crate) unsafe fn visit_events<E: Event>(
+    ptr: *const (),
+    len: usize,
+    visit: &mut dyn FnMut(&dyn Any),
+) {
+    slice::from_raw_parts(ptr as *const E, len)
+        .iter()
+        .for_each(|event| visit(event));
+}
+
+/// A slice of events triggered by a running task,
+/// waiting to be passed to observers.
+#[derive(Debug)]
+pub(crate) struct PendingEvents {
+    pub(crate) id: EventId,
+    pub(crate) ptr: *const (),
+    pub(crate) len: usize,
+}
+
+// Safety: the events are allocated in the bump allocator,
+// which is not reset until the dispatch completes.
+unsafe impl Send for PendingEvents {}
+unsafe impl Sync for PendingEvents {}
@@ -0,0 +1,265 @@
+//! A channel whose receiver always dequeues the highest-priority
+//! message which is available, used for communication between
+//! running tasks and the scheduler.
+
+use crossbeam::channel::{
+    Receiver, RecvError, RecvTimeoutError, Select, SendError, Sender, TryRecvError,
+};
+use std::time::{Duration, Instant};
+#[cfg(feature = "async")]
+use {
+    parking_lot::Mutex,
+    std::future::Future,
+    std::pin::Pin,
+    std::sync::Arc,
+    std::task::{Context, Poll, Waker},
+};
+
+/// Priority of a message sent through a priority channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(dead_code)] // `Low` is not used yet.
+pub(crate) enum Priority {
+    High = 0,
+    Normal = 1,
+    Low = 2,
+}
+
+/// Number of priority levels.
+const LEVELS: usize = 3;
+
+/// Creates a priority channel which can hold `capacity`
+/// messages of each priority.
+pub(crate) fn bounded<T>(capacity: usize) -> (PrioritySender<T>, PriorityReceiver<T>) {
+    let (high_sender, high_receiver) = crossbeam::bounded(capacity);
+    let (normal_sender, normal_receiver) = crossbeam::bounded(capacity);
+    let (low_sender, low_receiver) = crossbeam::bounded(capacity);
+    #[cfg(feature = "async")]
+    let waker = Arc::new(Mutex::new(None));
+
+    (
+        PrioritySender {
+            senders: [high_sender, normal_sender, low_sender],
+            #[cfg(feature = "async")]
+            waker: Arc::clone(&waker),
+        },
+        PriorityReceiver {
+            receivers: [high_receiver, normal_receiver, low_receiver],
+            #[cfg(feature = "async")]
+            waker,
+        },
+    )
+}
+
+/// The sending end of a priority channel.
+pub(crate) struct PrioritySender<T> {
+    /// Sender for each priority level, indexed by the `Priority`.
+    senders: [Sender<T>; LEVELS],
+    /// Waker of the task awaiting `PriorityReceiver::recv_async()`, if any.
+    #[cfg(feature = "async")]
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl<T> Clone for PrioritySender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            senders: self.senders.clone(),
+            #[cfg(feature = "async")]
+            waker: Arc::clone(&self.waker),
+        }
+    }
+}
+
+impl<T> PrioritySender<T> {
+    /// Sends a message with the given priority, blocking
+    /// if the channel for that priority is full.
+    pub fn send(&self, msg: T, priority: Priority) -> Result<(), SendError<T>> {
+        self.senders[priority as usize].send(msg)?;
+
+        #[cfg(feature = "async")]
+        {
+            if let Some(waker) = self.waker.lock().take() {
+                waker.wake();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The receiving end of a priority channel.
+pub(crate) struct PriorityReceiver<T> {
+    /// Receiver for each priority level, indexed by the `Priority`.
+    receivers: [Receiver<T>; LEVELS],
+    /// Waker of the task awaiting `recv_async()`, if any,
+    /// which is woken by the next message sent.
+    #[cfg(feature = "async")]
+    waker: Arc<Mutex<Option<Waker>>>,
+}
+
+impl<T> PriorityReceiver<T> {
+    /// Returns the highest-priority message which is
+    /// available without blocking.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut disconnected = 0;
+
+        for receiver in &self.receivers {
+            match receiver.try_recv() {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Empty) => (),
+                Err(TryRecvError::Disconnected) => disconnected += 1,
+            }
+        }
+
+        if disconnected == LEVELS {
+            Err(TryRecvError::Disconnected)
+        } else {
+            Err(TryRecvError::Empty)
+        }
+    }
+
+    /// Blocks until a message is available, returning the
+    /// highest-priority message.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Disconnected) => return Err(RecvError),
+                Err(TryRecvError::Empty) => {
+                    // Wait until any level has a message, then
+                    // check the levels again in priority order.
+                    let mut select = Select::new();
+                    for receiver in &self.receivers {
+                        select.recv(receiver);
+                    }
+                    select.ready();
+                }
+            }
+        }
+    }
+
+    /// Blocks until a message is available or `timeout` has
+    /// elapsed, returning the highest-priority message.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {
+                    let mut select = Select::new();
+                    for receiver in &self.receivers {
+                        select.recv(receiver);
+                    }
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if select.ready_timeout(remaining).is_err() {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns a future which resolves to the highest-priority
+    /// message once one is available, without blocking.
+    #[cfg(feature = "async")]
+    pub fn recv_async(&self) -> RecvFuture<'_, T> {
+        RecvFuture { receiver: self }
+    }
+}
+
+/// Future returned by `PriorityReceiver::recv_async()`.
+#[cfg(feature = "async")]
+pub(crate) struct RecvFuture<'a, T> {
+    receiver: &'a PriorityReceiver<T>,
+}
+
+#[cfg(feature = "async")]
+impl<'a, T> Future for RecvFuture<'a, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let receiver = self.receiver;
+
+        // The second check catches messages sent
+        // before the waker was registered.
+        for _ in 0..2 {
+            match receiver.try_recv() {
+                Ok(msg) => return Poll::Ready(Ok(msg)),
+                Err(TryRecvError::Disconnected) => return Poll::Ready(Err(RecvError)),
+                Err(TryRecvError::Empty) => {
+                    *receiver.waker.lock() = Some(cx.waker().clone());
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highest_priority_first() {
+        let (sender, receiver) = bounded(4);
+
+        sender.send("low", Priority::Low).unwrap();
+        sender.send("normal", Priority::Normal).unwrap();
+        sender.send("high", Priority::High).unwrap();
+        sender.send("normal 2", Priority::Normal).unwrap();
+
+        assert_eq!(receiver.recv(), Ok("high"));
+        assert_eq!(receiver.recv(), Ok("normal"));
+        assert_eq!(receiver.recv(), Ok("normal 2"));
+        assert_eq!(receiver.recv(), Ok("low"));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+
+        drop(sender);
+        assert_eq!(receiver.recv(), Err(RecvError));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn recv_async_wakes() {
+        use std::task::Wake;
+
+        struct Flag(std::sync::atomic::AtomicBool);
+
+        impl Wake for Flag {
+            fn wake(self: Arc<Self>) {
+                self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        let (sender, receiver) = bounded(4);
+        let flag = Arc::new(Flag(Default::default()));
+        let waker = Waker::from(Arc::clone(&flag));
+        let mut cx = Context::from_waker(&waker);
+
+        let mut future = receiver.recv_async();
+        assert_eq!(Pin::new(&mut future).poll(&mut cx), Poll::Pending);
+
+        sender.send("normal", Priority::Normal).unwrap();
+        assert!(flag.0.load(std::sync::atomic::Ordering::SeqCst));
+        assert_eq!(
+            Pin::new(&mut future).poll(&mut cx),
+            Poll::Ready(Ok("normal"))
+        );
+    }
+
+    #[test]
+    fn recv_timeout() {
+        let (sender, receiver) = bounded(4);
+
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        sender.send("low", Priority::Low).unwrap();
+        sender.send("high", Priority::High).unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_millis(1)), Ok("high"));
+        assert_eq!(receiver.recv_timeout(Duration::from_millis(1)), Ok("low"));
+    }
+}
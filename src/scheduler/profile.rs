@@ -0,0 +1,70 @@
+//! Span trees recorded by `Scheduler::profile_dispatch()`.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+/// A span of time recorded while profiling a dispatch.
+///
+/// The root span covers the whole dispatch. Its children are the
+/// stages, whose children are the systems in that stage (which run in
+/// parallel). Each system span in turn has a child for each phase of
+/// the system's execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileSpan {
+    /// Name of this span.
+    pub name: String,
+    /// Total time spent in this span, including its children.
+    pub duration: Duration,
+    /// Spans nested within this span.
+    pub children: Vec<ProfileSpan>,
+}
+
+impl ProfileSpan {
+    pub(crate) fn new(name: impl Into<String>, duration: Duration, children: Vec<Self>) -> Self {
+        Self {
+            name: name.into(),
+            duration,
+            children,
+        }
+    }
+
+    /// Returns the depth of this span tree. A span
+    /// without children has a depth of 1.
+    pub fn depth(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(ProfileSpan::depth)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Formats this span tree in the folded stack format consumed by
+    /// `inferno` and `cargo flamegraph`.
+    ///
+    /// Each line contains the path to a span, separated by semicolons,
+    /// followed by the time spent in that span but not its children,
+    /// in microseconds.
+    pub fn to_folded(&self) -> String {
+        let mut folded = String::new();
+        self.write_folded("", &mut folded);
+        folded
+    }
+
+    fn write_folded(&self, prefix: &str, folded: &mut String) {
+        let path = if prefix.is_empty() {
+            self.name.replace(';', ":")
+        } else {
+            format!("{};{}", prefix, self.name.replace(';', ":"))
+        };
+
+        let children: Duration = self.children.iter().map(|child| child.duration).sum();
+        let self_time = self.duration.checked_sub(children).unwrap_or_default();
+
+        writeln!(folded, "{} {}", path, self_time.as_micros()).unwrap();
+
+        for child in &self.children {
+            child.write_folded(&path, folded);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Hooks which temporarily replace a resource while a stage runs.
+
+use crate::resources::Resource;
+use crate::{resource_id_for, ResourceId, Scheduler};
+use std::marker::PhantomData;
+use std::mem;
+
+/// A hook registered through `SchedulerBuilder::with_resource_scope()`.
+pub(crate) trait RawResourceScope: Send + Sync {
+    /// Returns the ID of the resource which this hook replaces.
+    fn resource_id(&self) -> ResourceId;
+
+    /// Runs the hook. `run_stage` executes the stage or
+    /// task and blocks until it has completed.
+    fn run(&mut self, scheduler: &mut Scheduler, run_stage: &mut dyn FnMut(&mut Scheduler));
+}
+
+pub(crate) struct ResourceScope<T, F> {
+    scope_fn: F,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T, F> ResourceScope<T, F> {
+    pub(crate) fn new(scope_fn: F) -> Self {
+        Self {
+            scope_fn,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, F> RawResourceScope for ResourceScope<T, F>
+where
+    T: Resource + Default,
+    F: FnMut(T, &mut dyn FnMut(T) -> T) -> T + Send + Sync,
+{
+    fn resource_id(&self) -> ResourceId {
+        resource_id_for::<T>()
+    }
+
+    fn run(&mut self, scheduler: &mut Scheduler, run_stage: &mut dyn FnMut(&mut Scheduler)) {
+        // Values are swapped in place rather than by replacing the resource,
+        // since systems hold pointers to it.
+        let old = mem::take(scheduler.resources.get_mut::<T>());
+
+        let restored = (self.scope_fn)(old, &mut |new| {
+            *scheduler.resources.get_mut::<T>() = new;
+            run_stage(scheduler);
+            mem::take(scheduler.resources.get_mut::<T>())
+        });
+
+        *scheduler.resources.get_mut::<T>() = restored;
+    }
+}
@@ -0,0 +1,68 @@
+//! Statistics collected by the scheduler.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counters describing the work done by a `Scheduler`,
+/// as returned by `Scheduler::stats()`.
+///
+/// Warmup dispatches run by `SchedulerBuilder::with_warmup()`
+/// are only counted in `warmup_dispatches`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchStats {
+    /// Number of calls to `execute()` which have completed.
+    pub dispatches: u64,
+    /// Number of warmup dispatches run while building the scheduler.
+    pub warmup_dispatches: u64,
+    /// Total number of system and event handler executions
+    /// across all dispatches.
+    pub system_executions: u64,
+    /// Number of skippable systems which were not run because
+    /// the dispatch budget was exceeded.
+    pub skipped_systems: u64,
+}
+
+/// Accesses to a resource by the systems of a `Scheduler`,
+/// as returned by `Scheduler::resource_access_stats()`.
+///
+/// This is intended for finding resources which limit parallelism
+/// and may be worth splitting into finer resources.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceAccessStats {
+    /// Number of systems and event handlers which read the resource.
+    pub readers: usize,
+    /// Number of systems and event handlers which write the resource.
+    pub writers: usize,
+    /// Number of times a task could not be started because
+    /// another running task held a conflicting access to the resource.
+    ///
+    /// Warmup dispatches are not counted.
+    pub contentions: u64,
+}
+
+/// Total time spent running systems and event handlers
+/// during a dispatch, summed across threads.
+#[derive(Default)]
+pub(crate) struct BusyTime {
+    nanos: AtomicU64,
+}
+
+impl BusyTime {
+    /// Runs `f`, adding the time it takes to the total.
+    pub(crate) fn time<R>(&self, f: impl FnOnce() -> R) -> R {
+        let start = Instant::now();
+        let result = f();
+        self.add(start.elapsed());
+        result
+    }
+
+    pub(crate) fn add(&self, duration: Duration) {
+        self.nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the total and resets it to zero.
+    pub(crate) fn take(&self) -> Duration {
+        Duration::from_nanos(self.nanos.swap(0, Ordering::Relaxed))
+    }
+}
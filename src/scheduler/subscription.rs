@@ -0,0 +1,122 @@
+//! Snapshots of resources published at the end of each dispatch
+//! for readers outside the scheduler.
+
+use crate::resources::Resource;
+use crate::Resources;
+use crossbeam::epoch::{self, Atomic, Owned};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Slot holding the latest published value of a resource.
+struct Slot<T> {
+    value: Atomic<T>,
+}
+
+impl<T> Slot<T> {
+    fn publish(&self, value: T) {
+        let guard = epoch::pin();
+        let old = self.value.swap(Owned::new(value), Ordering::AcqRel, &guard);
+        // Safety: the old value is no longer reachable through
+        // the slot, and readers pinned before the swap are
+        // waited on before it is destroyed.
+        unsafe {
+            guard.defer_destroy(old);
+        }
+    }
+}
+
+impl<T> Drop for Slot<T> {
+    fn drop(&mut self) {
+        // Safety: no handles remain, so there are no other
+        // threads which can access the value.
+        unsafe {
+            let value = self.value.load(Ordering::Acquire, epoch::unprotected());
+            if !value.is_null() {
+                drop(value.into_owned());
+            }
+        }
+    }
+}
+
+/// A handle returned by `Scheduler::subscribe_read()` which
+/// reads the value of a resource as of the end of the last dispatch.
+///
+/// Reads never block and never block the scheduler.
+pub struct ReadHandle<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Clone for ReadHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slot: Arc::clone(&self.slot),
+        }
+    }
+}
+
+impl<T> ReadHandle<T> {
+    /// Calls `f` with the latest published value.
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let guard = epoch::pin();
+        let value = self.slot.value.load(Ordering::Acquire, &guard);
+        // Safety: the slot always holds a value, and it is not
+        // destroyed while this thread is pinned.
+        f(unsafe { value.deref() })
+    }
+
+    /// Returns a clone of the latest published value.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.read(T::clone)
+    }
+}
+
+/// A subscription held by the scheduler, type-erased
+/// over the resource type.
+pub(crate) trait RawSubscription: Send + Sync {
+    /// Publishes the current value of the resource.
+    fn publish(&self, resources: &Resources);
+
+    /// Returns whether all handles to this subscription
+    /// have been dropped.
+    fn is_abandoned(&self) -> bool;
+}
+
+pub(crate) struct Subscription<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Subscription<T>
+where
+    T: Resource + Clone,
+{
+    /// Creates a subscription with the current value
+    /// of the resource, returning it along with a handle.
+    pub(crate) fn new(resources: &Resources) -> (Self, ReadHandle<T>) {
+        let slot = Arc::new(Slot {
+            value: Atomic::new(resources.get::<T>().clone()),
+        });
+
+        (
+            Self {
+                slot: Arc::clone(&slot),
+            },
+            ReadHandle { slot },
+        )
+    }
+}
+
+impl<T> RawSubscription for Subscription<T>
+where
+    T: Resource + Clone,
+{
+    fn publish(&self, resources: &Resources) {
+        self.slot.publish(resources.get::<T>().clone());
+    }
+
+    fn is_abandoned(&self) -> bool {
+        Arc::strong_count(&self.slot) == 1
+    }
+}
@@ -0,0 +1,24 @@
+//! Tracing of accesses to individual resources, used to diagnose
+//! systems which access resources they did not declare.
+
+use crate::{ResourceId, SystemId};
+
+/// Whether a traced resource was acquired or released by the scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceAction {
+    Acquire,
+    Release,
+}
+
+/// An acquisition or release of a resource enabled through
+/// `Scheduler::trace_resource()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceTraceEntry {
+    /// The traced resource.
+    pub resource: ResourceId,
+    pub action: TraceAction,
+    /// Whether the resource is accessed mutably.
+    pub write: bool,
+    /// Systems or event handlers of the task which accessed the resource.
+    pub systems: Vec<SystemId>,
+}
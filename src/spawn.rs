@@ -0,0 +1,168 @@
+//! Bulk spawning of entities from within systems.
+
+use crate::{
+    resource_id_for, MacroData, ResourceId, Resources, SystemCtx, SystemData, SystemDataOutput,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::{IntoComponentSource, World};
+use parking_lot::Mutex;
+use std::mem;
+
+type SpawnBatch = Box<dyn FnOnce(&mut World) + Send>;
+
+/// Batches of entities queued by `EntityReserve`, which
+/// are inserted into the world at the end of each dispatch.
+#[derive(Default)]
+pub(crate) struct SpawnQueue {
+    batches: Mutex<Vec<SpawnBatch>>,
+}
+
+impl SpawnQueue {
+    /// Inserts all queued batches into `world`.
+    pub(crate) fn commit(&mut self, world: &mut World) {
+        for batch in self.batches.get_mut().drain(..) {
+            batch(world);
+        }
+    }
+}
+
+/// Allows a system to spawn entities with the components `C`,
+/// typically a tuple of components, in bulk.
+///
+/// Systems only have shared access to the world, so entities are
+/// queued rather than inserted immediately. Call `reserve()` with
+/// the number of entities about to be spawned to allocate space for
+/// them up front, then `spawn()` each of them. Each system's entities
+/// are inserted into the world as a single batch at the end of the
+/// dispatch, once all systems have completed. Reserved space which
+/// was not used is released at that point.
+///
+/// Queued entities do not conflict with other accesses, so any
+/// number of systems using this may run concurrently.
+// Safety: this contains a raw pointer which must remain valid.
+pub struct EntityReserve<C>
+where
+    C: Send + Sync + 'static,
+    Vec<C>: IntoComponentSource,
+{
+    queue: *const SpawnQueue,
+    pending: Vec<C>,
+}
+
+impl<C> EntityReserve<C>
+where
+    C: Send + Sync + 'static,
+    Vec<C>: IntoComponentSource,
+{
+    /// Reserves space for at least `additional` more entities.
+    pub fn reserve(&mut self, additional: usize) {
+        self.pending.reserve(additional);
+    }
+
+    /// Queues an entity with the given components for spawning.
+    pub fn spawn(&mut self, components: C) {
+        self.pending.push(components);
+    }
+
+    /// Queues an entity for each of the given components for spawning.
+    pub fn spawn_batch(&mut self, components: impl IntoIterator<Item = C>) {
+        self.pending.extend(components);
+    }
+
+    /// Returns the number of entities queued by this system
+    /// during the current run.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns whether no entities have been queued by
+    /// this system during the current run.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees,
+// and the queue is synchronized.
+unsafe impl<C> Send for EntityReserve<C>
+where
+    C: Send + Sync + 'static,
+    Vec<C>: IntoComponentSource,
+{
+}
+unsafe impl<C> Sync for EntityReserve<C>
+where
+    C: Send + Sync + 'static,
+    Vec<C>: IntoComponentSource,
+{
+}
+
+impl<'a, C> SystemData<'a> for EntityReserve<C>
+where
+    C: Send + Sync + 'static,
+    Vec<C>: IntoComponentSource,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        resources.insert_if_absent(SpawnQueue::default());
+
+        Self {
+            queue: resources.get_unchecked(resource_id_for::<SpawnQueue>()) as *const SpawnQueue,
+            pending: vec![],
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![resource_id_for::<SpawnQueue>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+
+    fn after_execution(&mut self) {
+        // Taking the buffer also releases any unused reserved space.
+        let pending = mem::take(&mut self.pending);
+        if pending.is_empty() {
+            return;
+        }
+
+        let batch: SpawnBatch = Box::new(move |world: &mut World| {
+            world.insert((), pending);
+        });
+        unsafe { &*self.queue }.batches.lock().push(batch);
+    }
+}
+
+impl<'a, C> SystemDataOutput<'a> for &'a mut EntityReserve<C>
+where
+    C: Send + Sync + 'static,
+    Vec<C>: IntoComponentSource,
+{
+    type SystemData = EntityReserve<C>;
+}
+
+impl<C> MacroData for &'static mut EntityReserve<C>
+where
+    C: Send + Sync + 'static,
+    Vec<C>: IntoComponentSource,
+{
+    type SystemData = EntityReserve<C>;
+}
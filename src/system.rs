@@ -1,15 +1,21 @@
+use crate::context::{FrameClock, FrameContextSlot, LastDispatch};
 use crate::resources::Resource;
-use crate::scheduler::TaskMessage;
+use crate::scheduler::{Priority, PrioritySender, TaskMessage};
+use crate::timeout::{Deadlines, TimedOut};
 use crate::{mappings::Mappings, resource_id_for, ResourceId, Resources, TryDefault};
 use bumpalo::Bump;
-use crossbeam::Sender;
+use hashbrown::HashMap;
 use lazy_static::lazy_static;
 use legion::storage::ComponentTypeId;
 use legion::world::World;
-use parking_lot::Mutex;
-use std::any::TypeId;
+use parking_lot::{Condvar, Mutex, RwLock};
+use std::any::{Any, TypeId};
+use std::fmt::{self, Debug, Display};
+use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thread_local::ThreadLocal;
 
 /// Unique ID of a system, allocated consecutively for use as indices into vectors.
@@ -22,6 +28,18 @@ impl From<usize> for SystemId {
     }
 }
 
+/// Formats the system as `System(name)`, where `name` is the name of its
+/// type. Systems allocated through `alloc_system_id()`, whose names are
+/// not known, are formatted as `System(#id)`.
+impl Display for SystemId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match SYSTEM_ID_MAPPINGS.lock().name(self.0) {
+            Some(name) => write!(f, "System({})", name),
+            None => write!(f, "System(#{})", self.0),
+        }
+    }
+}
+
 lazy_static! {
     /// Mappings from `TypeId`s to `SystemId`s.
     pub static ref SYSTEM_ID_MAPPINGS: Mutex<Mappings<TypeId, SystemId>> = Mutex::new(Mappings::new());
@@ -29,7 +47,24 @@ lazy_static! {
 
 /// Returns the system ID corresponding to the given type.
 pub fn system_id_for<T: 'static>() -> SystemId {
-    SYSTEM_ID_MAPPINGS.lock().get_or_alloc(TypeId::of::<T>())
+    SYSTEM_ID_MAPPINGS
+        .lock()
+        .get_or_alloc_named(TypeId::of::<T>(), std::any::type_name::<T>())
+}
+
+/// Allocates a new, unique system ID, such as for
+/// a system which implements `RawSystem` directly.
+pub fn alloc_system_id() -> SystemId {
+    SYSTEM_ID_MAPPINGS.lock().alloc()
+}
+
+/// Returns the resources read and written by the system type `S`,
+/// in that order, without constructing the system.
+pub fn access_of<S: System>() -> (Vec<ResourceId>, Vec<ResourceId>) {
+    (
+        <S::SystemData as SystemData>::resource_reads(),
+        <S::SystemData as SystemData>::resource_writes(),
+    )
 }
 
 /// A raw system, either a normal or one-shottable one.
@@ -61,6 +96,127 @@ pub trait RawSystem: Send + Sync {
     /// # Safety
     /// The system must not access any resources not indicated by `resource_reads()` and `resource_writes()`.
     unsafe fn execute_raw(&mut self, resources: &Resources, ctx: SystemCtx, world: &World);
+
+    /// Runs this system like `execute_raw()`, returning the name and duration
+    /// of each phase of the execution. This is used for profiling.
+    ///
+    /// The default implementation reports the whole execution as a single `run` phase.
+    ///
+    /// # Safety
+    /// See `execute_raw()`.
+    unsafe fn execute_profiled(
+        &mut self,
+        resources: &Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) -> Vec<(&'static str, Duration)> {
+        let start = Instant::now();
+        self.execute_raw(resources, ctx, world);
+        vec![("run", start.elapsed())]
+    }
+
+    /// Returns whether this system has finished its work and should
+    /// be removed from the schedule at the end of the current dispatch.
+    fn is_defunct(&self) -> bool {
+        false
+    }
+
+    /// Returns whether this system conflicts with `other`, in which case
+    /// the two systems are never run in parallel.
+    ///
+    /// The default implementation compares the resource and component
+    /// accesses of both systems. Systems may override this to declare
+    /// conflicts which are not captured by their accesses. Two systems
+    /// conflict if either one reports a conflict with the other.
+    fn conflicts_with(&self, other: &dyn RawSystem) -> bool {
+        accesses_conflict(self, other)
+    }
+
+    /// Returns resources which this system appears to modify
+    /// despite only reading them. See `System::suspected_writes()`.
+    ///
+    /// The default implementation returns an empty vector.
+    fn suspected_writes(&self) -> Vec<ResourceId> {
+        vec![]
+    }
+
+    /// Returns the `TypeId` of the type implementing the system,
+    /// if it has one. This is used to refer to systems in the edges
+    /// passed to `SchedulerBuilder::from_system_graph()`.
+    ///
+    /// The default implementation returns `None`.
+    fn system_type_id(&self) -> Option<TypeId> {
+        None
+    }
+
+    /// Converts this system into the value of the type implementing it,
+    /// whose `TypeId` is returned by `system_type_id()`. This is used by
+    /// `Scheduler::take_system()`.
+    ///
+    /// The default implementation returns `None`.
+    fn take_any(self: Box<Self>) -> Option<Box<dyn Any + Send>> {
+        None
+    }
+
+    /// Takes the system data loaded by `init()`, so that it can be passed
+    /// to the system replacing this one through `restore_data()`. This is
+    /// used by `Scheduler::hot_reload_systems()`.
+    ///
+    /// The default implementation returns `None`.
+    fn take_data(&mut self) -> Option<Box<dyn Any + Send>> {
+        None
+    }
+
+    /// Adopts system data taken from the system which this one replaces,
+    /// in place of initializing it. Returns the data back if it is not
+    /// compatible with this system.
+    ///
+    /// The default implementation returns the data back.
+    fn restore_data(&mut self, data: Box<dyn Any + Send>) -> Result<(), Box<dyn Any + Send>> {
+        Err(data)
+    }
+
+    /// Returns a copy of this system with the same ID which has
+    /// not yet been initialized.
+    ///
+    /// # Panics
+    /// The default implementation panics, since not all systems can be
+    /// cloned. `CachedSystem` implements this for systems which implement
+    /// `Clone`.
+    fn clone_dyn(&self) -> Box<dyn RawSystem> {
+        panic!("system `{}` cannot be cloned", self.name());
+    }
+}
+
+/// Returns whether two systems conflict on any resource or component access.
+pub(crate) fn accesses_conflict<A, B>(a: &A, b: &B) -> bool
+where
+    A: RawSystem + ?Sized,
+    B: RawSystem + ?Sized,
+{
+    slices_conflict(
+        a.resource_reads(),
+        a.resource_writes(),
+        b.resource_reads(),
+        b.resource_writes(),
+    ) || slices_conflict(
+        a.component_reads(),
+        a.component_writes(),
+        b.component_reads(),
+        b.component_writes(),
+    )
+}
+
+pub(crate) fn slices_conflict<T: PartialEq>(
+    a_reads: &[T],
+    a_writes: &[T],
+    b_reads: &[T],
+    b_writes: &[T],
+) -> bool {
+    a_writes
+        .iter()
+        .any(|access| b_reads.contains(access) || b_writes.contains(access))
+        || b_writes.iter().any(|access| a_reads.contains(access))
 }
 
 // High-level system API
@@ -70,6 +226,64 @@ pub trait System: Send + Sync + 'static {
     type SystemData: for<'a> SystemData<'a>;
 
     fn run(&mut self, data: <Self::SystemData as SystemData>::Output);
+
+    /// Returns whether this system has finished its work and should
+    /// be removed from the schedule at the end of the current dispatch.
+    ///
+    /// The default implementation always returns `false`.
+    fn is_defunct(&self) -> bool {
+        false
+    }
+
+    /// Returns resources which this system appears to modify
+    /// despite only reading them, as detected by `#[system]`.
+    ///
+    /// The default implementation returns an empty vector.
+    fn suspected_writes(&self) -> Vec<ResourceId> {
+        vec![]
+    }
+}
+
+/// A system which runs a `FnOnce` closure on its first execution
+/// and then removes itself from the schedule.
+///
+/// This is useful for initialization logic which consumes
+/// large captured state.
+pub struct FnOnceSystem<D, F> {
+    f: Option<F>,
+    _phantom: PhantomData<fn() -> D>,
+}
+
+impl<D, F> FnOnceSystem<D, F>
+where
+    D: for<'a> SystemData<'a>,
+    F: for<'a> FnOnce(<D as SystemData<'a>>::Output) + Send + Sync + 'static,
+{
+    /// Creates a new `FnOnceSystem` which runs `f` with system data `D`.
+    pub fn new(f: F) -> Self {
+        Self {
+            f: Some(f),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<D, F> System for FnOnceSystem<D, F>
+where
+    D: for<'a> SystemData<'a> + 'static,
+    F: for<'a> FnOnce(<D as SystemData<'a>>::Output) + Send + Sync + 'static,
+{
+    type SystemData = D;
+
+    fn run(&mut self, data: <Self::SystemData as SystemData>::Output) {
+        if let Some(f) = self.f.take() {
+            f(data);
+        }
+    }
+
+    fn is_defunct(&self) -> bool {
+        self.f.is_none()
+    }
 }
 
 pub struct CachedSystem<S: System> {
@@ -92,7 +306,7 @@ pub struct CachedSystem<S: System> {
 impl<S: System + 'static> CachedSystem<S> {
     pub fn new(inner: S, name: &'static str) -> Self {
         Self {
-            id: SYSTEM_ID_MAPPINGS.lock().alloc(),
+            id: SYSTEM_ID_MAPPINGS.lock().alloc_named(name),
             resource_reads: S::SystemData::resource_reads(),
             resource_writes: S::SystemData::resource_writes(),
             component_reads: S::SystemData::component_reads(),
@@ -102,6 +316,22 @@ impl<S: System + 'static> CachedSystem<S> {
             name,
         }
     }
+
+    /// Returns whether the system data has been loaded,
+    /// which happens when the system is initialized.
+    pub fn data_is_initialized(&self) -> bool {
+        self.data.is_some()
+    }
+
+    /// Initializes the system if it has not yet been initialized.
+    ///
+    /// Unlike `RawSystem::init()`, this does not reload
+    /// the system data if it has already been loaded.
+    pub fn force_initialize(&mut self, resources: &mut Resources, ctx: SystemCtx, world: &World) {
+        if !self.data_is_initialized() {
+            self.init(resources, ctx, world);
+        }
+    }
 }
 
 impl<S: System> RawSystem for CachedSystem<S> {
@@ -142,16 +372,284 @@ impl<S: System> RawSystem for CachedSystem<S> {
 
         data.after_execution();
     }
+
+    unsafe fn execute_profiled(
+        &mut self,
+        _resources: &Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Vec<(&'static str, Duration)> {
+        let data = self.data.as_mut().unwrap();
+
+        let start = Instant::now();
+        let output = data.before_execution();
+        let prepare = start.elapsed();
+
+        let start = Instant::now();
+        self.inner.run(output);
+        let run = start.elapsed();
+
+        let start = Instant::now();
+        data.after_execution();
+        let flush = start.elapsed();
+
+        vec![("prepare", prepare), ("run", run), ("flush", flush)]
+    }
+
+    fn is_defunct(&self) -> bool {
+        self.inner.is_defunct()
+    }
+
+    fn suspected_writes(&self) -> Vec<ResourceId> {
+        self.inner.suspected_writes()
+    }
+
+    fn system_type_id(&self) -> Option<TypeId> {
+        Some(TypeId::of::<S>())
+    }
+
+    fn take_any(self: Box<Self>) -> Option<Box<dyn Any + Send>> {
+        Some(Box::new(self.inner))
+    }
+
+    fn take_data(&mut self) -> Option<Box<dyn Any + Send>> {
+        self.data
+            .take()
+            .map(|data| Box::new(data) as Box<dyn Any + Send>)
+    }
+
+    fn restore_data(&mut self, data: Box<dyn Any + Send>) -> Result<(), Box<dyn Any + Send>> {
+        self.data = Some(*data.downcast::<S::SystemData>()?);
+        Ok(())
+    }
+
+    default fn clone_dyn(&self) -> Box<dyn RawSystem> {
+        panic!("system `{}` cannot be cloned", self.name);
+    }
+}
+
+impl<S: System + Clone> RawSystem for CachedSystem<S> {
+    fn clone_dyn(&self) -> Box<dyn RawSystem> {
+        Box::new(CachedSystem {
+            inner: self.inner.clone(),
+            id: self.id,
+            resource_reads: self.resource_reads.clone(),
+            resource_writes: self.resource_writes.clone(),
+            component_reads: self.component_reads.clone(),
+            component_writes: self.component_writes.clone(),
+            data: None,
+            name: self.name,
+        })
+    }
 }
 
 /// Context of a running system, immutable across runs.
 #[derive(Clone)]
 pub struct SystemCtx {
     /// Sender to the scheduler.
-    pub(crate) sender: Sender<TaskMessage>,
+    pub(crate) sender: PrioritySender<TaskMessage>,
     /// ID of this system.
     pub(crate) id: SystemId,
     pub(crate) bump: Arc<ThreadLocal<Bump>>,
+    /// Detached tasks spawned by systems of the scheduler.
+    pub(crate) detached: Arc<DetachedTasks>,
+    /// Generation counter of the world, shared with the scheduler.
+    pub(crate) world_generation: Arc<AtomicU64>,
+    /// Time budgets of the systems of the scheduler.
+    pub(crate) deadlines: Arc<Deadlines>,
+    /// Frame number and delta time, shared with the scheduler.
+    pub(crate) frame_clock: Arc<FrameClock>,
+    /// Stats of the last completed dispatch, shared with the scheduler.
+    pub(crate) last_dispatch: Arc<LastDispatch>,
+    /// Context of the current dispatch, shared with the scheduler.
+    pub(crate) frame_context: Arc<FrameContextSlot>,
+    /// Scheduled systems by their `system_type_id()`, shared with the scheduler.
+    pub(crate) system_types: Arc<RwLock<HashMap<TypeId, SystemId>>>,
+    /// Thread pools assigned through `SchedulerBuilder::with_label_pool()`,
+    /// keyed by `SystemId`, shared with the scheduler.
+    pub(crate) system_pools: Arc<HashMap<usize, Arc<rayon::ThreadPool>>>,
+}
+
+impl SystemCtx {
+    /// Returns a copy of this context for the system with the given ID.
+    pub(crate) fn with_id(&self, id: SystemId) -> Self {
+        Self { id, ..self.clone() }
+    }
+
+    /// Returns the generation of the world, which is incremented
+    /// by `Scheduler::bump_world_generation()`.
+    ///
+    /// Systems can compare this with the value seen on their
+    /// previous run to detect that the world has changed.
+    pub fn world_generation(&self) -> u64 {
+        self.world_generation.load(Ordering::Acquire)
+    }
+
+    /// Returns the time remaining before this system exceeds its budget
+    /// for the current run, as set in the `TimeoutBudget` resource, or
+    /// `None` if it has no budget.
+    pub fn remaining_budget(&self) -> Option<Duration> {
+        self.deadlines.remaining(self.id)
+    }
+
+    /// Returns `Err(TimedOut)` if this system has exceeded its budget
+    /// for the current run, in which case it should abort its work.
+    ///
+    /// Systems without a budget never time out.
+    pub fn check_timeout(&self) -> Result<(), TimedOut> {
+        match self.remaining_budget() {
+            Some(remaining) if remaining == Duration::from_secs(0) => Err(TimedOut),
+            _ => Ok(()),
+        }
+    }
+
+    /// Spawns a task which may outlive the current dispatch on the
+    /// thread pool the system runs on: the pool assigned to it through
+    /// `SchedulerBuilder::with_label_pool()`, or otherwise the pool
+    /// the scheduler spawns its tasks on.
+    ///
+    /// Since the task must be `'static`, it cannot borrow any resources
+    /// or the world. `Scheduler::shutdown()` waits for all detached
+    /// tasks to complete.
+    pub fn spawn_detached(&self, task: impl FnOnce() + Send + 'static) {
+        self.detached.start();
+        let guard = DetachedTaskGuard(Arc::clone(&self.detached));
+        let task = move || {
+            task();
+            drop(guard);
+        };
+
+        match self.system_pools.get(&self.id.0) {
+            Some(pool) => pool.spawn(task),
+            None => rayon::spawn(task),
+        }
+    }
+
+    /// Schedules the system of type `S` to run once more during the
+    /// current dispatch, after this system has sent the request.
+    ///
+    /// `S` is the type implementing the system, as returned by
+    /// `RawSystem::system_type_id()`, and the system must have been
+    /// added to the scheduler. Like any other task, the oneshot run waits
+    /// for the resources it accesses, and for the system itself if it is
+    /// still running. Scheduling a system more than once runs it once
+    /// for each request. Disabled systems are not run.
+    pub fn schedule_oneshot<S: 'static>(&self) -> Result<(), SystemNotScheduled> {
+        let id = self
+            .system_types
+            .read()
+            .get(&TypeId::of::<S>())
+            .copied()
+            .ok_or(SystemNotScheduled {
+                type_name: std::any::type_name::<S>(),
+            })?;
+
+        self.sender
+            .send(TaskMessage::ScheduleOneshot(id), Priority::Normal)
+            .unwrap();
+        Ok(())
+    }
+}
+
+/// Error returned by `SystemCtx::schedule_oneshot()`
+/// when no system of the given type is scheduled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SystemNotScheduled {
+    type_name: &'static str,
+}
+
+impl SystemNotScheduled {
+    /// Returns the name of the type of the system.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl Display for SystemNotScheduled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "system {} is not scheduled", self.type_name)
+    }
+}
+
+impl std::error::Error for SystemNotScheduled {}
+
+/// Tracks the number of detached tasks which are still running.
+#[derive(Default)]
+pub(crate) struct DetachedTasks {
+    running: Mutex<usize>,
+    condvar: Condvar,
+}
+
+impl DetachedTasks {
+    fn start(&self) {
+        *self.running.lock() += 1;
+    }
+
+    fn finish(&self) {
+        let mut running = self.running.lock();
+        *running -= 1;
+        if *running == 0 {
+            self.condvar.notify_all();
+        }
+    }
+
+    /// Blocks until all detached tasks have completed.
+    pub(crate) fn wait(&self) {
+        let mut running = self.running.lock();
+        while *running > 0 {
+            self.condvar.wait(&mut running);
+        }
+    }
+}
+
+/// Marks a detached task as finished when dropped,
+/// even if the task panics.
+struct DetachedTaskGuard(Arc<DetachedTasks>);
+
+impl Drop for DetachedTaskGuard {
+    fn drop(&mut self) {
+        self.0.finish();
+    }
+}
+
+impl<'a> SystemData<'a> for SystemCtx {
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        _resources: &mut Resources,
+        ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        ctx
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a> SystemDataOutput<'a> for &'a SystemCtx {
+    type SystemData = SystemCtx;
+}
+
+impl MacroData for &'static SystemCtx {
+    type SystemData = SystemCtx;
 }
 
 /// A system data type. This could include queries, event triggers, `PreparedWorld`, resource
@@ -200,6 +698,16 @@ pub trait SystemData<'a>: Send + Sync + Sized + 'a {
     ///
     /// The default implementation of this function is a no-op.
     fn after_execution(&mut self) {}
+
+    /// Returns the phase in which `after_execution()` is called when
+    /// this `SystemData` is part of a tuple or a `#[derive(SystemData)]`
+    /// struct. Lower phases are flushed first. `SystemData` with the same
+    /// phase is flushed in declaration order.
+    ///
+    /// The default phase is 0. Use `FlushPhase` to override it.
+    fn flush_phase() -> i32 {
+        0
+    }
 }
 
 /// Output of a `SystemData`.
@@ -240,6 +748,83 @@ impl<'a> SystemDataOutput<'a> for () {
     type SystemData = Self;
 }
 
+/// A type-level marker which can be passed to systems without accessing
+/// any data, e.g. to distinguish the members of a generic system family.
+///
+/// Unlike `PhantomData<T>`, which implements `SystemData` as well,
+/// `Marker<T>` is `Send` and `Sync` regardless of `T`.
+pub struct Marker<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for Marker<T> {
+    fn default() -> Self {
+        Marker(PhantomData)
+    }
+}
+
+impl<T> Clone for Marker<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Marker<T> {}
+
+macro_rules! impl_marker_data {
+    ($marker:ident, $($bounds:tt)*) => {
+        impl<'a, T> SystemData<'a> for $marker<T>
+        where
+            T: $($bounds)*
+        {
+            type Output = Self;
+
+            unsafe fn load_from_resources(
+                _resources: &mut Resources,
+                _ctx: SystemCtx,
+                _world: &World,
+            ) -> Self {
+                Default::default()
+            }
+
+            fn resource_reads() -> Vec<ResourceId> {
+                vec![]
+            }
+
+            fn resource_writes() -> Vec<ResourceId> {
+                vec![]
+            }
+
+            fn component_reads() -> Vec<ComponentTypeId> {
+                vec![]
+            }
+
+            fn component_writes() -> Vec<ComponentTypeId> {
+                vec![]
+            }
+
+            fn before_execution(&'a mut self) -> Self::Output {
+                Default::default()
+            }
+        }
+
+        impl<'a, T> SystemDataOutput<'a> for $marker<T>
+        where
+            T: $($bounds)*
+        {
+            type SystemData = Self;
+        }
+
+        impl<T> MacroData for $marker<T>
+        where
+            T: $($bounds)*
+        {
+            type SystemData = Self;
+        }
+    };
+}
+
+impl_marker_data!(PhantomData, Send + Sync + 'static);
+impl_marker_data!(Marker, 'static);
+
 /// Specifies a read requirement for a resource.
 // Safety: this contains a raw pointer which must remain valid.
 pub struct Read<T>
@@ -260,6 +845,15 @@ where
     }
 }
 
+impl<T> Debug for Read<T>
+where
+    T: Resource + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
 // Safety: raw pointers are valid as per the scheduler guarantees.
 unsafe impl<T: Send + Resource> Send for Read<T> {}
 unsafe impl<T: Send + Sync + Resource> Sync for Read<T> {}
@@ -313,12 +907,50 @@ where
 }
 
 /// Specifies a write requirement for a resource.
+///
+/// The version of the resource is incremented after each run in which
+/// the system accessed it mutably, whether or not the value changed.
+/// `TrackedWrite<T>` only increments it when the value changes.
 // Safety: this contains a raw pointer which must remain valid.
 pub struct Write<T>
 where
     T: Resource,
 {
     ptr: *mut T,
+    /// Version counter of the resource, incremented after
+    /// each run in which the resource was mutably accessed.
+    version: *const AtomicU64,
+    modified: bool,
+    /// The `ResourceEvents<T>` resource, or null if it does not exist.
+    events: *const ResourceEvents<T>,
+}
+
+impl<T> Write<T>
+where
+    T: Resource,
+{
+    /// Returns whether the resource has been mutably accessed during
+    /// this run of the system, since the last call to `mark_clean()`.
+    pub fn is_dirty(&self) -> bool {
+        self.modified
+    }
+
+    /// Resets the dirty flag, such as after a mutable access which did
+    /// not actually change the resource. If the flag is not set again
+    /// before the end of the run, the version of the resource is not
+    /// incremented, so systems reading it through `Changed<T>` do not
+    /// see a change.
+    pub fn mark_clean(&mut self) {
+        self.modified = false;
+    }
+
+    fn load_events(resources: &Resources) -> *const ResourceEvents<T> {
+        if resources.contains::<ResourceEvents<T>>() {
+            resources.get::<ResourceEvents<T>>() as *const _
+        } else {
+            std::ptr::null()
+        }
+    }
 }
 
 impl<T> Deref for Write<T>
@@ -332,11 +964,21 @@ where
     }
 }
 
+impl<T> Debug for Write<T>
+where
+    T: Resource + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
 impl<T> DerefMut for Write<T>
 where
     T: Resource,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.modified = true;
         unsafe { &mut *self.ptr }
     }
 }
@@ -360,8 +1002,12 @@ where
             resources.insert_if_absent(default);
         }
 
+        let id = resource_id_for::<T>();
         Self {
-            ptr: resources.get_mut_unchecked(resource_id_for::<T>()) as *mut T,
+            ptr: resources.get_mut_unchecked(id) as *mut T,
+            version: resources.version_counter(id) as *const _,
+            modified: false,
+            events: Self::load_events(resources),
         }
     }
 
@@ -374,6 +1020,7 @@ where
         if let Some(default) = T::try_default() {
             resources.insert_if_absent(default);
         }
+        self.events = Self::load_events(resources);
     }
 
     fn resource_reads() -> Vec<ResourceId> {
@@ -395,6 +1042,16 @@ where
     fn before_execution(&'a mut self) -> Self::Output {
         self
     }
+
+    fn after_execution(&mut self) {
+        if self.modified {
+            let version = unsafe { &*self.version }.fetch_add(1, Ordering::AcqRel) + 1;
+            if !self.events.is_null() {
+                unsafe { &*self.events }.push(ResourceModified::new(version));
+            }
+            self.modified = false;
+        }
+    }
 }
 
 impl<'a, T> SystemDataOutput<'a> for &'a mut Write<T>
@@ -404,12 +1061,1097 @@ where
     type SystemData = Write<T>;
 }
 
-// `system` macro implementation details.
-// This is used to allow for custom SystemData impls
-// which don't go through `Read` and `Write`.
+/// Event recorded in `ResourceEvents<T>` when a system
+/// has modified the resource `T` through `Write<T>`.
+pub struct ResourceModified<T> {
+    /// Version of the resource after the modification.
+    pub version: u64,
+    _marker: PhantomData<fn() -> T>,
+}
 
-pub trait MacroData: 'static + Send + Sync {
-    type SystemData: for<'a> SystemData<'a>;
+impl<T> ResourceModified<T> {
+    fn new(version: u64) -> Self {
+        Self {
+            version,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Debug for ResourceModified<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResourceModified")
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+/// Resource accumulating a `ResourceModified<T>` event each time
+/// a system modifies the resource `T` through `Write<T>`.
+///
+/// Events are only recorded if this resource exists when the
+/// writing system is initialized, so it should be inserted before
+/// building the scheduler. Systems interested in the changes read
+/// this resource through `Read<ResourceEvents<T>>` and call `drain()`.
+/// Since draining removes the events, only one system should drain them.
+pub struct ResourceEvents<T> {
+    events: Mutex<Vec<ResourceModified<T>>>,
+}
+
+impl<T> Default for ResourceEvents<T> {
+    fn default() -> Self {
+        Self {
+            events: Mutex::new(vec![]),
+        }
+    }
+}
+
+impl<T> ResourceEvents<T> {
+    /// Creates a `ResourceEvents` with no events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&self, event: ResourceModified<T>) {
+        self.events.lock().push(event);
+    }
+
+    /// Removes and returns the events recorded so far, oldest first.
+    pub fn drain(&self) -> Vec<ResourceModified<T>> {
+        std::mem::take(&mut *self.events.lock())
+    }
+}
+
+/// Specifies a write requirement for a resource which is usually
+/// only read.
+///
+/// For scheduling purposes, this is treated the same as `Write<T>`.
+/// However, like `Cow`, the resource is only modified if the system
+/// calls `to_mut()`, which clones the current value. The modified value
+/// is written back to the resource at the end of the system.
+// Safety: this contains a raw pointer which must remain valid.
+pub struct MaybeWrite<T>
+where
+    T: Resource + Clone,
+{
+    ptr: *mut T,
+    /// Version counter of the resource.
+    version: *const AtomicU64,
+    /// The modified value, or `None` if `to_mut()` has not been called.
+    owned: Option<T>,
+}
+
+impl<T> MaybeWrite<T>
+where
+    T: Resource + Clone,
+{
+    /// Returns a mutable reference to the resource, cloning
+    /// it if this is the first mutable access during this run.
+    pub fn to_mut(&mut self) -> &mut T {
+        if self.owned.is_none() {
+            self.owned = Some(unsafe { &*self.ptr }.clone());
+        }
+
+        self.owned.as_mut().unwrap()
+    }
+
+    /// Returns whether `to_mut()` has been called during this run.
+    pub fn is_modified(&self) -> bool {
+        self.owned.is_some()
+    }
+}
+
+impl<T> Deref for MaybeWrite<T>
+where
+    T: Resource + Clone,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        match &self.owned {
+            Some(owned) => owned,
+            None => unsafe { &*self.ptr },
+        }
+    }
+}
+
+impl<T> Debug for MaybeWrite<T>
+where
+    T: Resource + Clone + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + Resource + Clone> Send for MaybeWrite<T> {}
+unsafe impl<T: Send + Sync + Resource + Clone> Sync for MaybeWrite<T> {}
+
+impl<'a, T> SystemData<'a> for MaybeWrite<T>
+where
+    T: Resource + Clone + TryDefault,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        if let Some(default) = T::try_default() {
+            resources.insert_if_absent(default);
+        }
+
+        let id = resource_id_for::<T>();
+        Self {
+            ptr: resources.get_mut_unchecked(id) as *mut T,
+            version: resources.version_counter(id) as *const _,
+            owned: None,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![resource_id_for::<T>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+
+    fn after_execution(&mut self) {
+        if let Some(value) = self.owned.take() {
+            unsafe {
+                *self.ptr = value;
+                (&*self.version).fetch_add(1, Ordering::AcqRel);
+            }
+        }
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for &'a mut MaybeWrite<T>
+where
+    T: Resource + Clone + TryDefault,
+{
+    type SystemData = MaybeWrite<T>;
+}
+
+impl<T> MacroData for &'static mut MaybeWrite<T>
+where
+    T: Resource + Clone + TryDefault,
+{
+    type SystemData = MaybeWrite<T>;
+}
+
+/// Specifies a read requirement for a resource `T`, along with
+/// a cached value `U` derived from it.
+///
+/// The cached value is computed by `get_or_compute()` and reused
+/// until the resource's version changes, i.e. until the resource is
+/// replaced or modified through `Write` or `MaybeWrite`. This avoids
+/// recomputing expensive values derived from slowly-changing resources.
+// Safety: this contains raw pointers which must remain valid.
+pub struct ReadCached<T, U>
+where
+    T: Resource,
+{
+    ptr: *const T,
+    version: *const AtomicU64,
+    /// The cached value and the version of the resource it was computed from.
+    cached: Option<(u64, U)>,
+}
+
+impl<T, U> ReadCached<T, U>
+where
+    T: Resource,
+{
+    /// Returns the cached value, first recomputing it with `compute`
+    /// if the resource has changed since it was last computed.
+    pub fn get_or_compute(&mut self, compute: impl FnOnce(&T) -> U) -> &U {
+        let version = unsafe { &*self.version }.load(Ordering::Acquire);
+
+        match &self.cached {
+            Some((cached_version, _)) if *cached_version == version => (),
+            _ => self.cached = Some((version, compute(unsafe { &*self.ptr }))),
+        }
+
+        &self.cached.as_ref().unwrap().1
+    }
+}
+
+impl<T, U> Deref for ReadCached<T, U>
+where
+    T: Resource,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + Resource, U: Send> Send for ReadCached<T, U> {}
+unsafe impl<T: Send + Sync + Resource, U: Sync> Sync for ReadCached<T, U> {}
+
+impl<'a, T, U> SystemData<'a> for ReadCached<T, U>
+where
+    T: Resource + TryDefault,
+    U: Send + Sync + 'static,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        if let Some(default) = T::try_default() {
+            resources.insert_if_absent(default);
+        }
+
+        let id = resource_id_for::<T>();
+        Self {
+            ptr: resources.get_unchecked(id) as *const T,
+            version: resources.version_counter(id) as *const _,
+            cached: None,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![resource_id_for::<T>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+}
+
+impl<'a, T, U> SystemDataOutput<'a> for &'a mut ReadCached<T, U>
+where
+    T: Resource + TryDefault,
+    U: Send + Sync + 'static,
+{
+    type SystemData = ReadCached<T, U>;
+}
+
+impl<T, U> MacroData for &'static mut ReadCached<T, U>
+where
+    T: Resource + TryDefault,
+    U: Send + Sync + 'static,
+{
+    type SystemData = ReadCached<T, U>;
+}
+
+/// Specifies a write requirement for a resource of type `Arc<Mutex<T>>`
+/// which is shared with threads outside the scheduler.
+///
+/// The mutex is locked before the system runs and unlocked after it
+/// completes, so the system has exclusive access to the value while
+/// other threads can lock it between runs. For scheduling purposes,
+/// this is treated the same as `Write<Arc<Mutex<T>>>`, so systems
+/// never contend with each other for the mutex.
+///
+/// If the system panics during its run, the mutex stays locked
+/// until the system runs again or is dropped.
+// Safety: this contains raw pointers which must remain valid.
+pub struct MutexWrite<T>
+where
+    T: Send + Sync + 'static,
+{
+    mutex: *const Mutex<T>,
+    /// Pointer to the value while the mutex is locked, or null.
+    ptr: *mut T,
+    version: *const AtomicU64,
+    modified: bool,
+}
+
+impl<T> MutexWrite<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn unlock(&mut self) {
+        if !self.ptr.is_null() {
+            self.ptr = std::ptr::null_mut();
+            // Safety: the guard was forgotten in `before_execution()`.
+            unsafe { (&*self.mutex).force_unlock() };
+        }
+    }
+}
+
+impl<T> Deref for MutexWrite<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> DerefMut for MutexWrite<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.modified = true;
+        unsafe { &mut *self.ptr }
+    }
+}
+
+impl<T> Drop for MutexWrite<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn drop(&mut self) {
+        // A system which panicked during its run never reached
+        // `after_execution()`, so it still holds the lock.
+        self.unlock();
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees,
+// and the lock is only held for the duration of a run. Shared
+// references hand out `&T`, so `T` must be `Sync` as well.
+unsafe impl<T: Send + Sync + 'static> Send for MutexWrite<T> {}
+unsafe impl<T: Send + Sync + 'static> Sync for MutexWrite<T> {}
+
+impl<'a, T> SystemData<'a> for MutexWrite<T>
+where
+    T: Send + Sync + 'static,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        let id = resource_id_for::<Arc<Mutex<T>>>();
+        Self {
+            mutex: &**resources.get_unchecked::<Arc<Mutex<T>>>(id) as *const Mutex<T>,
+            ptr: std::ptr::null_mut(),
+            version: resources.version_counter(id) as *const _,
+            modified: false,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![resource_id_for::<Arc<Mutex<T>>>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        // Release the lock if a previous run panicked while holding it,
+        // since this would otherwise deadlock.
+        self.unlock();
+        let mut guard = unsafe { &*self.mutex }.lock();
+        self.ptr = &mut *guard as *mut T;
+        // The lock is released in `after_execution()`.
+        std::mem::forget(guard);
+        self
+    }
+
+    fn after_execution(&mut self) {
+        self.unlock();
+        if self.modified {
+            unsafe { &*self.version }.fetch_add(1, Ordering::AcqRel);
+            self.modified = false;
+        }
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for &'a mut MutexWrite<T>
+where
+    T: Send + Sync + 'static,
+{
+    type SystemData = MutexWrite<T>;
+}
+
+impl<T> MacroData for &'static mut MutexWrite<T>
+where
+    T: Send + Sync + 'static,
+{
+    type SystemData = MutexWrite<T>;
+}
+
+/// Specifies a write requirement for a resource which
+/// the system can release before it completes.
+///
+/// The system is passed a `ScopedWriteGuard<T>` giving mutable access to
+/// the resource. Dropping the guard releases the write, so tasks waiting
+/// on the resource can start while the system continues with unrelated
+/// work. Since the guard owns the borrow, the resource cannot be
+/// accessed once it is dropped. If the system never drops the guard,
+/// the write is released when the system completes, as for `Write<T>`.
+// Safety: this contains a raw pointer which must remain valid.
+pub struct ScopedWrite<T>
+where
+    T: Resource,
+{
+    ptr: *mut T,
+    version: *const AtomicU64,
+    sender: PrioritySender<TaskMessage>,
+    system: SystemId,
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + Resource> Send for ScopedWrite<T> {}
+unsafe impl<T: Send + Sync + Resource> Sync for ScopedWrite<T> {}
+
+impl<'a, T> SystemData<'a> for ScopedWrite<T>
+where
+    T: Resource + TryDefault,
+{
+    type Output = ScopedWriteGuard<'a, T>;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        if let Some(default) = T::try_default() {
+            resources.insert_if_absent(default);
+        }
+
+        let id = resource_id_for::<T>();
+        Self {
+            ptr: resources.get_mut_unchecked(id) as *mut T,
+            version: resources.version_counter(id) as *const _,
+            sender: ctx.sender,
+            system: ctx.id,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![resource_id_for::<T>()]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        ScopedWriteGuard {
+            write: self,
+            modified: false,
+        }
+    }
+}
+
+/// Mutable access to a resource required through `ScopedWrite<T>`,
+/// which releases the write when dropped.
+pub struct ScopedWriteGuard<'a, T>
+where
+    T: Resource,
+{
+    write: &'a mut ScopedWrite<T>,
+    modified: bool,
+}
+
+impl<'a, T> Deref for ScopedWriteGuard<'a, T>
+where
+    T: Resource,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.write.ptr }
+    }
+}
+
+impl<'a, T> DerefMut for ScopedWriteGuard<'a, T>
+where
+    T: Resource,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.modified = true;
+        unsafe { &mut *self.write.ptr }
+    }
+}
+
+impl<'a, T> Drop for ScopedWriteGuard<'a, T>
+where
+    T: Resource,
+{
+    fn drop(&mut self) {
+        // The version is bumped before the write is released,
+        // so the next task to access the resource sees the change.
+        if self.modified {
+            unsafe { &*self.write.version }.fetch_add(1, Ordering::AcqRel);
+        }
+
+        self.write
+            .sender
+            .send(
+                TaskMessage::ReleaseWrite(self.write.system, resource_id_for::<T>()),
+                Priority::High,
+            )
+            .unwrap();
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for ScopedWriteGuard<'a, T>
+where
+    T: Resource + TryDefault,
+{
+    type SystemData = ScopedWrite<T>;
+}
+
+impl<T> MacroData for ScopedWriteGuard<'static, T>
+where
+    T: Resource + TryDefault,
+{
+    type SystemData = ScopedWrite<T>;
+}
+
+/// A condition on a resource, used by `ResourceFilter`.
+///
+/// Closures cannot be named in a `SystemData` type, so conditions
+/// are implemented on (usually zero-sized) marker types.
+pub trait ResourcePredicate<T>: 'static {
+    /// Returns whether the resource passes the condition.
+    fn test(value: &T) -> bool;
+}
+
+/// Specifies a read requirement for a resource `T` which
+/// is only exposed to the system if it passes the condition `F`.
+///
+/// The condition is evaluated before each run of the system, and
+/// `get()` returns `None` if it does not pass. The system is run
+/// regardless, so it should return early in that case. For scheduling
+/// purposes, this is treated the same as `Read<T>`.
+///
+/// # Example
+/// ```ignore
+/// struct IsEnabled;
+///
+/// impl ResourcePredicate<PhysicsEnabled> for IsEnabled {
+///     fn test(enabled: &PhysicsEnabled) -> bool {
+///         enabled.0
+///     }
+/// }
+///
+/// type SystemData = (ResourceFilter<PhysicsEnabled, IsEnabled>, Write<Physics>);
+/// ```
+// Safety: this contains a raw pointer which must remain valid.
+pub struct ResourceFilter<T, F>
+where
+    T: Resource,
+{
+    ptr: *const T,
+    /// Whether the resource passed the condition before this run.
+    passed: bool,
+    _predicate: PhantomData<fn() -> F>,
+}
+
+impl<T, F> ResourceFilter<T, F>
+where
+    T: Resource,
+{
+    /// Returns the resource if it passed the condition
+    /// before this run of the system, or `None` otherwise.
+    pub fn get(&self) -> Option<&T> {
+        if self.passed {
+            Some(unsafe { &*self.ptr })
+        } else {
+            None
+        }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + Resource, F> Send for ResourceFilter<T, F> {}
+unsafe impl<T: Send + Sync + Resource, F> Sync for ResourceFilter<T, F> {}
+
+impl<'a, T, F> SystemData<'a> for ResourceFilter<T, F>
+where
+    T: Resource + TryDefault,
+    F: ResourcePredicate<T>,
+{
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        if let Some(default) = T::try_default() {
+            resources.insert_if_absent(default);
+        }
+
+        Self {
+            ptr: resources.get_unchecked(resource_id_for::<T>()) as *const T,
+            passed: false,
+            _predicate: PhantomData,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![resource_id_for::<T>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.passed = F::test(unsafe { &*self.ptr });
+        self
+    }
+}
+
+impl<'a, T, F> SystemDataOutput<'a> for &'a ResourceFilter<T, F>
+where
+    T: Resource + TryDefault,
+    F: ResourcePredicate<T>,
+{
+    type SystemData = ResourceFilter<T, F>;
+}
+
+impl<T, F> MacroData for &'static ResourceFilter<T, F>
+where
+    T: Resource + TryDefault,
+    F: ResourcePredicate<T>,
+{
+    type SystemData = ResourceFilter<T, F>;
+}
+
+/// Error returned by `TryRead<T>` when the resource `T` does not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceMissing {
+    type_name: &'static str,
+}
+
+impl ResourceMissing {
+    /// Returns the name of the type of the missing resource.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl Display for ResourceMissing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "resource {} is missing", self.type_name)
+    }
+}
+
+impl std::error::Error for ResourceMissing {}
+
+/// Specifies a read requirement for a resource which may not exist.
+///
+/// Systems receive a `Result` which is `Err` if the resource did not
+/// exist when the system was loaded, naming the missing type so that
+/// the system can report which dependency was absent. Unlike `Read<T>`,
+/// no default value is inserted. For scheduling purposes, this is
+/// treated the same as `Read<T>`.
+// Safety: this contains a raw pointer which must remain valid.
+pub struct TryRead<T>
+where
+    T: Resource,
+{
+    /// The resource, or null if it does not exist.
+    ptr: *const T,
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + Resource> Send for TryRead<T> {}
+unsafe impl<T: Send + Sync + Resource> Sync for TryRead<T> {}
+
+impl<'a, T> SystemData<'a> for TryRead<T>
+where
+    T: Resource,
+{
+    type Output = Result<&'a T, ResourceMissing>;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        let ptr = if resources.contains::<T>() {
+            resources.get_unchecked(resource_id_for::<T>()) as *const T
+        } else {
+            std::ptr::null()
+        };
+
+        Self { ptr }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![resource_id_for::<T>()]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        if self.ptr.is_null() {
+            Err(ResourceMissing {
+                type_name: std::any::type_name::<T>(),
+            })
+        } else {
+            Ok(unsafe { &*self.ptr })
+        }
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for Result<&'a T, ResourceMissing>
+where
+    T: Resource,
+{
+    type SystemData = TryRead<T>;
+}
+
+impl<T> MacroData for Result<&'static T, ResourceMissing>
+where
+    T: Resource,
+{
+    type SystemData = TryRead<T>;
+}
+
+/// Wraps a `SystemData` to flush it in the given phase rather than
+/// the default phase 0 when it is part of a tuple. This allows
+/// system data whose `after_execution()` side effects depend on each
+/// other to be flushed in a different order than they are declared in.
+///
+/// Systems receive a `FlushPhase` wrapping the output of `D`,
+/// which dereferences to that output.
+///
+/// # Example
+/// ```ignore
+/// // `Commands` is flushed before `Trigger`, despite being declared after it.
+/// type SystemData = (Trigger<Ev>, FlushPhase<Commands, -1>);
+/// ```
+pub struct FlushPhase<D, const PHASE: i32> {
+    inner: D,
+}
+
+impl<D, const PHASE: i32> Deref for FlushPhase<D, PHASE> {
+    type Target = D;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<D, const PHASE: i32> DerefMut for FlushPhase<D, PHASE> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<'a, D, const PHASE: i32> SystemData<'a> for FlushPhase<D, PHASE>
+where
+    D: SystemData<'a>,
+{
+    type Output = FlushPhase<D::Output, PHASE>;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) -> Self {
+        Self {
+            inner: D::load_from_resources(resources, ctx, world),
+        }
+    }
+
+    fn init(
+        &mut self,
+        resources: &mut Resources,
+        component_reads: &[ComponentTypeId],
+        component_writes: &[ComponentTypeId],
+    ) {
+        self.inner
+            .init(resources, component_reads, component_writes);
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        D::resource_reads()
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        D::resource_writes()
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        D::component_reads()
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        D::component_writes()
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        FlushPhase {
+            inner: self.inner.before_execution(),
+        }
+    }
+
+    fn after_execution(&mut self) {
+        self.inner.after_execution();
+    }
+
+    fn flush_phase() -> i32 {
+        PHASE
+    }
+}
+
+impl<'a, O, const PHASE: i32> SystemDataOutput<'a> for FlushPhase<O, PHASE>
+where
+    O: SystemDataOutput<'a>,
+{
+    type SystemData = FlushPhase<O::SystemData, PHASE>;
+}
+
+/// Wraps a `SystemData` to track whether the resources it accesses
+/// have changed since the system last ran, as determined by the
+/// resources' versions. A resource changes when it is replaced
+/// or modified through `Write` or `MaybeWrite`.
+///
+/// Systems receive a `Changed` wrapping the output of `D`,
+/// which dereferences to that output. On the first run,
+/// `is_changed()` always returns `true`.
+///
+/// `#[system]` functions can request this wrapper by marking
+/// a parameter with `#[changed]`.
+///
+/// # Example
+/// ```ignore
+/// #[system]
+/// fn rebuild_ui(#[changed] health: &Health) {
+///     if health.is_changed() {
+///         // ...
+///     }
+/// }
+/// ```
+pub struct Changed<D> {
+    inner: D,
+    changed: bool,
+    /// Version counters of the accessed resources.
+    versions: Vec<*const AtomicU64>,
+    /// Versions of the accessed resources at the end of the
+    /// last run, or `None` if the system has not run.
+    seen: Option<Vec<u64>>,
+}
+
+impl<D> Changed<D> {
+    /// Returns whether any accessed resource has changed
+    /// since the system last ran.
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    fn current_versions(&self) -> Vec<u64> {
+        self.versions
+            .iter()
+            .map(|version| unsafe { &**version }.load(Ordering::Acquire))
+            .collect()
+    }
+}
+
+impl<D> Deref for Changed<D> {
+    type Target = D;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<D> DerefMut for Changed<D> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<D: Send> Send for Changed<D> {}
+unsafe impl<D: Sync> Sync for Changed<D> {}
+
+impl<'a, D> SystemData<'a> for Changed<D>
+where
+    D: SystemData<'a>,
+{
+    type Output = Changed<D::Output>;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        ctx: SystemCtx,
+        world: &World,
+    ) -> Self {
+        let inner = D::load_from_resources(resources, ctx, world);
+
+        let versions = D::resource_reads()
+            .into_iter()
+            .chain(D::resource_writes())
+            .filter(|id| resources.contains_id(*id))
+            .map(|id| resources.version_counter(id) as *const AtomicU64)
+            .collect();
+
+        Self {
+            inner,
+            changed: true,
+            versions,
+            seen: None,
+        }
+    }
+
+    fn init(
+        &mut self,
+        resources: &mut Resources,
+        component_reads: &[ComponentTypeId],
+        component_writes: &[ComponentTypeId],
+    ) {
+        self.inner
+            .init(resources, component_reads, component_writes);
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        D::resource_reads()
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        D::resource_writes()
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        D::component_reads()
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        D::component_writes()
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.changed = self.seen.as_ref() != Some(&self.current_versions());
+
+        Changed {
+            inner: self.inner.before_execution(),
+            changed: self.changed,
+            versions: vec![],
+            seen: None,
+        }
+    }
+
+    fn after_execution(&mut self) {
+        self.inner.after_execution();
+        // Record versions after the inner data is flushed so that
+        // the system's own writes are not reported on the next run.
+        self.seen = Some(self.current_versions());
+    }
+
+    fn flush_phase() -> i32 {
+        D::flush_phase()
+    }
+}
+
+impl<'a, O> SystemDataOutput<'a> for Changed<O>
+where
+    O: SystemDataOutput<'a>,
+{
+    type SystemData = Changed<O::SystemData>;
+}
+
+impl<D> MacroData for Changed<D>
+where
+    D: for<'a> SystemData<'a> + 'static,
+{
+    type SystemData = Changed<D>;
+}
+
+// `system` macro implementation details.
+// This is used to allow for custom SystemData impls
+// which don't go through `Read` and `Write`.
+
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be passed to a system",
+    label = "not a resource or system data",
+    note = "resources must implement `Send + Sync` and `#[derive(Resource)]` to be passed by reference"
+)]
+pub trait MacroData: 'static + Send + Sync {
+    type SystemData: for<'a> SystemData<'a>;
+}
+
+/// Formats system data passed to systems generated with
+/// `#[system(debug_print)]`, falling back to a placeholder
+/// for types which do not implement `Debug`.
+#[doc(hidden)]
+pub trait MaybeDebug {
+    fn maybe_debug(&self) -> String;
+}
+
+impl<T: ?Sized> MaybeDebug for T {
+    default fn maybe_debug(&self) -> String {
+        String::from("<no Debug implementation>")
+    }
+}
+
+impl<T: ?Sized + Debug> MaybeDebug for T {
+    fn maybe_debug(&self) -> String {
+        format!("{:?}", self)
+    }
 }
 
 // System data tuple impls.
@@ -468,7 +2210,19 @@ macro_rules! impl_data {
             }
 
             fn after_execution(&mut self) {
-                $(self.$idx.after_execution() ;)*
+                let mut phases = [$($ty::flush_phase()),*];
+                phases.sort_unstable();
+
+                for (index, phase) in phases.iter().enumerate() {
+                    if index > 0 && phases[index - 1] == *phase {
+                        continue;
+                    }
+                    $(
+                        if $ty::flush_phase() == *phase {
+                            self.$idx.after_execution();
+                        }
+                    )*
+                }
             }
         }
     }
@@ -0,0 +1,41 @@
+//! Helpers for testing schedules, enabled by the `test-support` feature.
+
+use crate::{Resources, Scheduler, SchedulerBuilder};
+use legion::world::World;
+
+/// Builds a scheduler from `builder` and `resources`, then runs `n`
+/// dispatches on `world`, checking `invariant` after each one.
+///
+/// Returns the scheduler so that it can be inspected further.
+///
+/// # Panics
+/// Panics if `invariant` returns `false`, reporting the number
+/// of the failing dispatch (starting at 1) and the scheduler's
+/// `DispatchStats` at that point.
+pub fn run_and_check<F>(
+    builder: SchedulerBuilder,
+    resources: Resources,
+    world: &mut World,
+    n: usize,
+    invariant: F,
+) -> Scheduler
+where
+    F: Fn(&Resources) -> bool,
+{
+    let mut scheduler = builder.build(resources);
+
+    for dispatch in 1..=n {
+        scheduler.execute(world);
+
+        if !invariant(scheduler.resources()) {
+            panic!(
+                "invariant violated after dispatch {} of {}; stats: {:?}",
+                dispatch,
+                n,
+                scheduler.stats()
+            );
+        }
+    }
+
+    scheduler
+}
@@ -0,0 +1,121 @@
+//! Per-system time budgets, checked cooperatively by systems.
+
+use crate::SystemId;
+use hashbrown::HashMap;
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Resource containing the time budget of each system.
+///
+/// Systems with a budget can call `SystemCtx::check_timeout()` while
+/// running to find out whether they have exceeded it, and abort their
+/// work if so. Systems are never interrupted by the scheduler.
+///
+/// Budgets are read by the scheduler at the start of each dispatch,
+/// so changes take effect on the next dispatch.
+#[derive(Debug, Clone, Default)]
+pub struct TimeoutBudget {
+    budgets: HashMap<SystemId, Duration>,
+}
+
+impl TimeoutBudget {
+    /// Creates a `TimeoutBudget` in which no system has a budget.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the time budget of a system for each of its runs.
+    pub fn set(&mut self, system: SystemId, budget: Duration) {
+        self.budgets.insert(system, budget);
+    }
+
+    /// Removes the time budget of a system.
+    pub fn remove(&mut self, system: SystemId) {
+        self.budgets.remove(&system);
+    }
+
+    /// Returns the time budget of a system, if it has one.
+    pub fn get(&self, system: SystemId) -> Option<Duration> {
+        self.budgets.get(&system).copied()
+    }
+}
+
+/// Error returned by `SystemCtx::check_timeout()` when
+/// a system has exceeded its time budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedOut;
+
+impl Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("system exceeded its time budget")
+    }
+}
+
+impl std::error::Error for TimedOut {}
+
+/// Budgets and deadlines of the systems of a scheduler,
+/// shared with all `SystemCtx`s.
+///
+/// Times are stored as nanoseconds, with 0 meaning none.
+/// Deadlines are relative to `epoch`.
+pub(crate) struct Deadlines {
+    epoch: Instant,
+    /// Budget of each system, indexed by the `SystemId`.
+    budgets: Vec<AtomicU64>,
+    /// Deadline of each running system, indexed by the `SystemId`.
+    deadlines: Vec<AtomicU64>,
+}
+
+impl Deadlines {
+    pub(crate) fn new(num_systems: usize) -> Self {
+        Self {
+            epoch: Instant::now(),
+            budgets: (0..num_systems).map(|_| AtomicU64::new(0)).collect(),
+            deadlines: (0..num_systems).map(|_| AtomicU64::new(0)).collect(),
+        }
+    }
+
+    /// Copies the budgets from `budget`.
+    pub(crate) fn update_budgets(&self, budget: &TimeoutBudget) {
+        for (id, slot) in self.budgets.iter().enumerate() {
+            let nanos = budget
+                .get(SystemId(id))
+                .map(|budget| (budget.as_nanos() as u64).max(1))
+                .unwrap_or(0);
+            slot.store(nanos, Ordering::Relaxed);
+        }
+    }
+
+    /// Sets the deadline of a system which is about to run.
+    pub(crate) fn start(&self, id: SystemId) {
+        if let (Some(budget), Some(deadline)) = (self.budgets.get(id.0), self.deadlines.get(id.0)) {
+            let budget = budget.load(Ordering::Relaxed);
+            if budget != 0 {
+                deadline.store(self.now() + budget, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Clears the deadline of a system which has finished running.
+    pub(crate) fn finish(&self, id: SystemId) {
+        if let Some(deadline) = self.deadlines.get(id.0) {
+            deadline.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the remaining budget of a running system,
+    /// or `None` if it has no budget.
+    pub(crate) fn remaining(&self, id: SystemId) -> Option<Duration> {
+        let deadline = self.deadlines.get(id.0)?.load(Ordering::Relaxed);
+        if deadline == 0 {
+            return None;
+        }
+
+        Some(Duration::from_nanos(deadline.saturating_sub(self.now())))
+    }
+
+    fn now(&self) -> u64 {
+        self.epoch.elapsed().as_nanos() as u64
+    }
+}
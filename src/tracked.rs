@@ -0,0 +1,197 @@
+//! Write access to resources which records whether,
+//! and how, systems changed them.
+
+use crate::resources::Resource;
+use crate::{
+    resource_id_for, MacroData, ResourceId, Resources, SystemCtx, SystemData, SystemDataOutput,
+    TryDefault,
+};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A value which can compute the difference from an older value,
+/// such as the fields which need to be replicated over the network.
+pub trait Diff {
+    /// Type describing the difference between two values.
+    type Delta;
+
+    /// Returns the difference from `old` to `self`.
+    fn diff(&self, old: &Self) -> Self::Delta;
+}
+
+/// Resource recording the changes made to the resource `T`
+/// by systems accessing it through `TrackedWrite<T>`.
+///
+/// Changes accumulate until `clear()` is called, usually by the
+/// system consuming them, such as a replication system.
+pub struct TrackedChanges<T> {
+    /// Value before the first change since the last `clear()`.
+    previous: Option<T>,
+}
+
+impl<T> Default for TrackedChanges<T> {
+    fn default() -> Self {
+        Self { previous: None }
+    }
+}
+
+impl<T> TrackedChanges<T> {
+    /// Returns whether the resource has changed since the last `clear()`.
+    pub fn is_changed(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    /// Returns the value of the resource before the first change
+    /// since the last `clear()`, or `None` if it has not changed.
+    pub fn previous(&self) -> Option<&T> {
+        self.previous.as_ref()
+    }
+
+    /// Returns the difference from the value before the first
+    /// change since the last `clear()` to `current`, or `None`
+    /// if the resource has not changed.
+    pub fn diff(&self, current: &T) -> Option<T::Delta>
+    where
+        T: Diff,
+    {
+        self.previous
+            .as_ref()
+            .map(|previous| current.diff(previous))
+    }
+
+    /// Forgets the changes recorded so far.
+    pub fn clear(&mut self) {
+        self.previous = None;
+    }
+}
+
+/// Specifies a write requirement for a resource whose changes
+/// are recorded in the `TrackedChanges<T>` resource.
+///
+/// The resource is cloned before each run of the system and compared
+/// with its new value afterwards, so this should only be used for
+/// resources whose changes need to be known, such as replicated state.
+///
+/// This declares writes of `T` and `TrackedChanges<T>`.
+// Safety: this contains raw pointers which must remain valid.
+pub struct TrackedWrite<T>
+where
+    T: Resource + Clone + PartialEq,
+{
+    ptr: *mut T,
+    version: *const AtomicU64,
+    changes: *mut TrackedChanges<T>,
+    /// Value of the resource before the current run.
+    before: Option<T>,
+}
+
+impl<T> Deref for TrackedWrite<T>
+where
+    T: Resource + Clone + PartialEq,
+{
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> DerefMut for TrackedWrite<T>
+where
+    T: Resource + Clone + PartialEq,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.ptr }
+    }
+}
+
+// Safety: raw pointers are valid as per the scheduler guarantees.
+unsafe impl<T: Send + Resource + Clone + PartialEq> Send for TrackedWrite<T> {}
+unsafe impl<T: Send + Sync + Resource + Clone + PartialEq> Sync for TrackedWrite<T> {}
+
+impl<'a, T> SystemData<'a> for TrackedWrite<T>
+where
+    T: Resource + Clone + PartialEq + TryDefault,
+{
+    type Output = &'a mut Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        if let Some(default) = T::try_default() {
+            resources.insert_if_absent(default);
+        }
+        resources.insert_if_absent(TrackedChanges::<T>::default());
+
+        let id = resource_id_for::<T>();
+        Self {
+            ptr: resources.get_mut_unchecked(id) as *mut T,
+            version: resources.version_counter(id) as *const _,
+            changes: resources.get_mut_unchecked(resource_id_for::<TrackedChanges<T>>())
+                as *mut TrackedChanges<T>,
+            before: None,
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![
+            resource_id_for::<T>(),
+            resource_id_for::<TrackedChanges<T>>(),
+        ]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self.before = Some(unsafe { &*self.ptr }.clone());
+        self
+    }
+
+    fn after_execution(&mut self) {
+        let before = match self.before.take() {
+            Some(before) => before,
+            None => return,
+        };
+
+        if before == *unsafe { &*self.ptr } {
+            return;
+        }
+
+        unsafe {
+            (&*self.version).fetch_add(1, Ordering::AcqRel);
+
+            let changes = &mut *self.changes;
+            if changes.previous.is_none() {
+                changes.previous = Some(before);
+            }
+        }
+    }
+}
+
+impl<'a, T> SystemDataOutput<'a> for &'a mut TrackedWrite<T>
+where
+    T: Resource + Clone + PartialEq + TryDefault,
+{
+    type SystemData = TrackedWrite<T>;
+}
+
+impl<T> MacroData for &'static mut TrackedWrite<T>
+where
+    T: Resource + Clone + PartialEq + TryDefault,
+{
+    type SystemData = TrackedWrite<T>;
+}
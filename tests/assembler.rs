@@ -0,0 +1,78 @@
+//! Testing of the built-in stage assemblers.
+
+use tonks::{
+    Conflicts, Constraint, ConstraintAlgorithm, GreedyAssembler, MaxParallelismAssembler,
+    PreserveOrderAssembler, StageAssembler, SystemId,
+};
+
+fn assert_conflict_free(stages: &[Vec<SystemId>], conflicts: &Conflicts) {
+    for stage in stages {
+        for (index, a) in stage.iter().enumerate() {
+            for b in &stage[index + 1..] {
+                assert!(!conflicts.conflicts(*a, *b));
+            }
+        }
+    }
+}
+
+#[test]
+fn assemblers() {
+    // a writes a resource which b reads; c conflicts only with b.
+    let (a, b, c) = (SystemId(0), SystemId(1), SystemId(2));
+    let systems = [a, b, c];
+
+    let mut conflicts = Conflicts::new();
+    conflicts.insert(a, b);
+    conflicts.insert(b, c);
+
+    let greedy = GreedyAssembler.assemble(&systems, &conflicts, &[]);
+    let ordered = PreserveOrderAssembler.assemble(&systems, &conflicts, &[]);
+
+    assert_conflict_free(&greedy, &conflicts);
+    assert_conflict_free(&ordered, &conflicts);
+
+    assert_eq!(greedy, vec![vec![a, c], vec![b]]);
+    assert_eq!(ordered, vec![vec![a], vec![b], vec![c]]);
+}
+
+#[test]
+fn constraints() {
+    let (a, b) = (SystemId(0), SystemId(1));
+    let constraints = [Constraint {
+        before: b,
+        after: a,
+    }];
+
+    for assembler in &[
+        &GreedyAssembler as &dyn StageAssembler,
+        &PreserveOrderAssembler,
+        &MaxParallelismAssembler,
+    ] {
+        let stages = assembler.assemble(&[a, b], &Conflicts::new(), &constraints);
+        assert_eq!(stages, vec![vec![b], vec![a]]);
+    }
+}
+
+#[test]
+fn max_parallelism() {
+    // A path a - b - c - d, inserted with both ends first.
+    let (a, b, c, d) = (SystemId(0), SystemId(1), SystemId(2), SystemId(3));
+    let systems = [a, d, b, c];
+
+    let mut conflicts = Conflicts::new();
+    conflicts.insert(a, b);
+    conflicts.insert(b, c);
+    conflicts.insert(c, d);
+
+    let greedy = GreedyAssembler.assemble(&systems, &conflicts, &[]);
+    let colored =
+        ConstraintAlgorithm::MaxParallelism
+            .assembler()
+            .assemble(&systems, &conflicts, &[]);
+
+    assert_conflict_free(&greedy, &conflicts);
+    assert_conflict_free(&colored, &conflicts);
+
+    assert_eq!(greedy.len(), 3);
+    assert_eq!(colored, vec![vec![b, d], vec![c, a]]);
+}
@@ -0,0 +1,119 @@
+//! Testing of `Scheduler::execute_async()`.
+
+#![cfg(feature = "async")]
+
+use legion::world::World;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::Duration;
+use tonks::{Read, Resources, SchedulerBuilder, System, SystemData, Write};
+
+/// Minimal executor which parks the thread while the future is pending.
+fn block_on<F: Future>(mut future: F) -> F::Output {
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    // Safety: the future is never moved after being pinned.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+struct Step(u32);
+struct Total(u32);
+
+struct Accumulate;
+
+impl System for Accumulate {
+    type SystemData = (Read<Step>, Write<Total>);
+
+    fn run(&mut self, (step, total): <Self::SystemData as SystemData>::Output) {
+        total.0 += step.0;
+    }
+}
+
+struct Double;
+
+impl System for Double {
+    type SystemData = Write<Step>;
+
+    fn run(&mut self, step: <Self::SystemData as SystemData>::Output) {
+        step.0 *= 2;
+    }
+}
+
+#[test]
+fn execute_async() {
+    let mut resources = Resources::new();
+    resources.insert(Step(1));
+    resources.insert(Total(0));
+
+    // `Double` conflicts with `Accumulate`, so the dispatch
+    // has to await the first stage before running the second.
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Accumulate)
+        .with(Double)
+        .build(resources);
+    let mut world = World::new();
+
+    block_on(async {
+        for _ in 0..4 {
+            scheduler.execute_async(&mut world).await;
+        }
+    });
+
+    assert_eq!(scheduler.resources().get::<Total>().0, 1 + 2 + 4 + 8);
+    assert_eq!(scheduler.resources().get::<Step>().0, 16);
+}
+
+struct Slow(Arc<AtomicBool>);
+
+impl System for Slow {
+    type SystemData = Write<Total>;
+
+    fn run(&mut self, total: <Self::SystemData as SystemData>::Output) {
+        thread::sleep(Duration::from_millis(50));
+        total.0 += 1;
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn drop_execute_async() {
+    let mut resources = Resources::new();
+    resources.insert(Total(0));
+
+    let completed = Arc::new(AtomicBool::new(false));
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Slow(Arc::clone(&completed)))
+        .build(resources);
+    let mut world = World::new();
+
+    {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = Box::pin(scheduler.execute_async(&mut world));
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        // Dropping the future waits for the running system.
+    }
+    assert!(completed.load(Ordering::SeqCst));
+
+    // The abandoned dispatch leaves the scheduler usable.
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Total>().0, 2);
+}
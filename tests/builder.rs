@@ -0,0 +1,836 @@
+//! Testing of `SchedulerBuilder` APIs.
+
+mod common;
+
+use common::stage_names;
+use legion::world::World;
+use parking_lot::Mutex;
+use std::any::TypeId;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tonks::{
+    resource_id_for, CachedSystem, DuplicatePolicy, EmptyAccessPolicy, RawSystem, Read,
+    ReadOnlyResourcePolicy, ResourceId, Resources, Scheduler, SchedulerBuilder, SysNode, System,
+    SystemData, Write,
+};
+
+struct Present;
+struct Missing;
+
+struct ReadBoth;
+
+impl System for ReadBoth {
+    type SystemData = (Read<Present>, Read<Missing>);
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+}
+
+struct WriteMissing;
+
+impl System for WriteMissing {
+    type SystemData = Write<Missing>;
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+}
+
+#[test]
+fn resource_completeness() {
+    let read_both = CachedSystem::new(ReadBoth, "read_both");
+    let write_missing = CachedSystem::new(WriteMissing, "write_missing");
+    let ids = [read_both.id(), write_missing.id()];
+
+    let mut builder = SchedulerBuilder::new();
+    builder.add_boxed(Box::new(read_both));
+    builder.add_boxed(Box::new(write_missing));
+
+    let mut resources = Resources::new();
+    resources.insert(Present);
+
+    let missing = builder.check_resource_completeness(&resources);
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].resource_id, resource_id_for::<Missing>());
+    assert_eq!(missing[0].required_by_systems, ids);
+
+    resources.insert(Missing);
+    assert!(builder.check_resource_completeness(&resources).is_empty());
+}
+
+struct GamepadState;
+
+struct ReadGamepad;
+
+impl System for ReadGamepad {
+    type SystemData = Read<GamepadState>;
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+}
+
+#[test]
+fn optional_resource_absent() {
+    let mut resources = Resources::new();
+    resources.insert(Missing);
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ReadGamepad)
+        .with(WriteMissing)
+        .with_optional_resource(false, || GamepadState)
+        .build(resources);
+
+    // `ReadGamepad` is disabled, so its missing resource is never fetched.
+    let stages = scheduler.profile_dispatch(&mut World::new());
+    assert_eq!(stages.children.len(), 1);
+    assert_eq!(stages.children[0].children.len(), 1);
+    assert!(!scheduler.resources().contains::<GamepadState>());
+}
+
+#[test]
+fn optional_resource_present() {
+    let mut resources = Resources::new();
+    resources.insert(Missing);
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ReadGamepad)
+        .with(WriteMissing)
+        .with_optional_resource(true, || GamepadState)
+        .build(resources);
+
+    let stages = scheduler.profile_dispatch(&mut World::new());
+    assert_eq!(stages.children.len(), 1);
+    assert_eq!(stages.children[0].children.len(), 2);
+    assert!(scheduler.resources().contains::<GamepadState>());
+}
+
+struct Gravity(f32);
+struct Observed(f32);
+
+struct ObserveGravity;
+
+impl System for ObserveGravity {
+    type SystemData = (Read<Gravity>, Write<Observed>);
+
+    fn run(&mut self, (gravity, observed): <Self::SystemData as SystemData>::Output) {
+        observed.0 = gravity.0;
+    }
+}
+
+#[test]
+fn override_resource() {
+    let mut resources = Resources::new();
+    resources.insert(Gravity(9.81));
+    resources.insert(Observed(0.0));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ObserveGravity)
+        .override_resource(Gravity(1.62))
+        .build(resources);
+
+    scheduler.execute(&mut World::new());
+    assert_eq!(scheduler.resources().get::<Observed>().0, 1.62);
+}
+
+struct Noop;
+
+impl System for Noop {
+    type SystemData = ();
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+}
+
+#[test]
+fn ordering_constraints_only() {
+    let load = CachedSystem::new(Noop, "load");
+    let simulate = CachedSystem::new(Noop, "simulate");
+    let render = CachedSystem::new(Noop, "render");
+    let audio = CachedSystem::new(Noop, "audio");
+
+    let mut builder = SchedulerBuilder::new()
+        .with_after(simulate.id(), load.id())
+        .with_after(render.id(), simulate.id());
+    builder.add_boxed(Box::new(load));
+    builder.add_boxed(Box::new(simulate));
+    builder.add_boxed(Box::new(render));
+    builder.add_boxed(Box::new(audio));
+
+    // Safety: none of the systems access any data.
+    let mut scheduler =
+        unsafe { builder.infer_stages_from_ordering_constraints_only() }.build(Resources::new());
+
+    let stages = stage_names(&mut scheduler, &mut World::new());
+    assert_eq!(stages.len(), 3);
+    assert!(stages[0].contains(&"load".to_owned()));
+    assert!(stages[0].contains(&"audio".to_owned()));
+    assert_eq!(stages[1], vec!["simulate"]);
+    assert_eq!(stages[2], vec!["render"]);
+}
+
+/// Logs the start and end of each run to a log shared with the test,
+/// without accessing any resources.
+struct Record {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl System for Record {
+    type SystemData = ();
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {
+        self.log.lock().push(format!("{} start", self.name));
+        thread::sleep(Duration::from_millis(20));
+        self.log.lock().push(format!("{} end", self.name));
+    }
+}
+
+/// Runs a dispatch on a thread pool with enough threads for
+/// systems which do not conflict to run concurrently.
+fn execute_in_parallel(scheduler: &mut Scheduler) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    pool.install(|| scheduler.execute(&mut World::new()));
+}
+
+#[test]
+fn ordering_constraints_at_runtime() {
+    let log = Arc::new(Mutex::new(vec![]));
+    let load = CachedSystem::new(
+        Record {
+            name: "load",
+            log: Arc::clone(&log),
+        },
+        "load",
+    );
+    let simulate = CachedSystem::new(
+        Record {
+            name: "simulate",
+            log: Arc::clone(&log),
+        },
+        "simulate",
+    );
+
+    let mut builder = SchedulerBuilder::new().with_after(simulate.id(), load.id());
+    builder.add_boxed(Box::new(load));
+    builder.add_boxed(Box::new(simulate));
+    let mut scheduler = builder.build(Resources::new());
+
+    // The systems do not conflict, so only the constraint orders them.
+    execute_in_parallel(&mut scheduler);
+    assert_eq!(
+        *log.lock(),
+        vec!["load start", "load end", "simulate start", "simulate end"]
+    );
+}
+
+#[derive(Default)]
+struct TimeScale(u32);
+#[derive(Default)]
+struct Ticks(u32);
+
+struct ScaledTick;
+
+impl System for ScaledTick {
+    type SystemData = (Read<TimeScale>, Write<Ticks>);
+
+    fn run(&mut self, (scale, ticks): <Self::SystemData as SystemData>::Output) {
+        ticks.0 += scale.0;
+    }
+}
+
+#[test]
+fn resource_scope() {
+    let mut resources = Resources::new();
+    resources.insert(TimeScale(2));
+    resources.insert(Gravity(9.81));
+    resources.insert(Observed(0.0));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ScaledTick)
+        .with(ObserveGravity)
+        .with_resource_scope::<TimeScale, _>(|old, run_stage| {
+            let used = run_stage(TimeScale(old.0 * 10));
+            assert_eq!(used.0, 20);
+            old
+        })
+        .build(resources);
+
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(scheduler.resources().get::<Ticks>().0, 20);
+    assert_eq!(scheduler.resources().get::<TimeScale>().0, 2);
+    assert_eq!(scheduler.resources().get::<Observed>().0, 9.81);
+}
+
+#[test]
+fn resource_scope_oneshot() {
+    let mut resources = Resources::new();
+    resources.insert(TimeScale(2));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with_label("tick", ScaledTick)
+        .with_resource_scope::<TimeScale, _>(|old, run_stage| {
+            run_stage(TimeScale(old.0 * 10));
+            old
+        })
+        .build(resources);
+
+    // Labeled systems run as oneshots, which are scoped like stages.
+    scheduler.execute_labeled("tick", &mut World::new());
+
+    assert_eq!(scheduler.resources().get::<Ticks>().0, 20);
+    assert_eq!(scheduler.resources().get::<TimeScale>().0, 2);
+}
+
+#[derive(Default)]
+struct Config(u32);
+#[derive(Default)]
+struct OutputA(u32);
+#[derive(Default)]
+struct OutputB(u32);
+
+struct ReadConfigA;
+
+impl System for ReadConfigA {
+    type SystemData = (Read<Config>, Write<OutputA>);
+
+    fn run(&mut self, (config, output): <Self::SystemData as SystemData>::Output) {
+        output.0 = config.0;
+    }
+}
+
+struct ReadConfigB;
+
+impl System for ReadConfigB {
+    type SystemData = (Read<Config>, Write<OutputB>);
+
+    fn run(&mut self, (config, output): <Self::SystemData as SystemData>::Output) {
+        output.0 = config.0 + 1;
+    }
+}
+
+#[test]
+fn read_only_policy() {
+    let mut resources = Resources::new();
+    resources.insert(Config(5));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ReadConfigA)
+        .with(ReadConfigB)
+        .with(ReadGamepad)
+        .with_optional_resource(true, || GamepadState)
+        .with_read_only_policy(ReadOnlyResourcePolicy::Immutable)
+        .build(resources);
+
+    scheduler.trace_resource(resource_id_for::<Config>());
+    scheduler.trace_resource(resource_id_for::<OutputA>());
+
+    // All readers of the never-written resource land in one stage.
+    let stages = scheduler.profile_dispatch(&mut World::new());
+    assert_eq!(stages.children.len(), 1);
+    assert_eq!(stages.children[0].children.len(), 3);
+
+    assert_eq!(scheduler.resources().get::<OutputA>().0, 5);
+    assert_eq!(scheduler.resources().get::<OutputB>().0, 6);
+
+    // Reads of `Config` are no longer tracked, while writes still are.
+    let trace = scheduler.take_resource_trace();
+    assert!(trace
+        .iter()
+        .all(|entry| entry.resource == resource_id_for::<OutputA>()));
+    assert_eq!(trace.len(), 2);
+}
+
+#[test]
+fn build_arc() {
+    let mut resources = Resources::new();
+    resources.insert(Gravity(9.81));
+    resources.insert(Observed(0.0));
+
+    let scheduler = SchedulerBuilder::new()
+        .with(ObserveGravity)
+        .build_arc(resources);
+
+    let shared = Arc::clone(&scheduler);
+    thread::spawn(move || shared.lock().execute(&mut World::new()))
+        .join()
+        .unwrap();
+
+    assert_eq!(scheduler.lock().resources().get::<Observed>().0, 9.81);
+}
+
+struct PhysicsPlugin;
+
+#[test]
+fn system_if_resource() {
+    let observed = |with_plugin: bool| {
+        let mut resources = Resources::new();
+        resources.insert(Gravity(9.81));
+        resources.insert(Observed(0.0));
+        if with_plugin {
+            resources.insert(PhysicsPlugin);
+        }
+
+        let mut scheduler = SchedulerBuilder::new()
+            .with_system_if_resource::<PhysicsPlugin, _>(ObserveGravity)
+            .build(resources);
+        scheduler.execute(&mut World::new());
+
+        let observed = scheduler.resources().get::<Observed>().0;
+        observed
+    };
+
+    assert_eq!(observed(false), 0.0);
+    assert_eq!(observed(true), 9.81);
+}
+
+#[derive(Default)]
+struct Runs(Vec<&'static str>);
+
+struct NearAi;
+
+impl System for NearAi {
+    type SystemData = Write<Runs>;
+
+    fn run(&mut self, runs: <Self::SystemData as SystemData>::Output) {
+        runs.0.push("near");
+    }
+}
+
+struct FarAi;
+
+impl System for FarAi {
+    type SystemData = Write<Runs>;
+
+    fn run(&mut self, runs: <Self::SystemData as SystemData>::Output) {
+        runs.0.push("far");
+    }
+}
+
+#[test]
+fn stage_rate() {
+    // Both systems write `Runs`, so they end up in separate stages.
+    let mut scheduler = SchedulerBuilder::new()
+        .with(NearAi)
+        .with(FarAi)
+        .with_stage_rate(1, 2)
+        .build(Resources::new());
+
+    let mut world = World::new();
+    for _ in 0..4 {
+        scheduler.execute(&mut world);
+    }
+
+    assert_eq!(
+        scheduler.resources().get::<Runs>().0,
+        vec!["near", "far", "near", "near", "far", "near"]
+    );
+}
+
+#[test]
+fn late_systems() {
+    struct ExportMetrics;
+
+    impl System for ExportMetrics {
+        type SystemData = Read<Ticks>;
+
+        fn run(&mut self, _ticks: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    struct ResetTicks;
+
+    impl System for ResetTicks {
+        type SystemData = Write<Ticks>;
+
+        fn run(&mut self, ticks: <Self::SystemData as SystemData>::Output) {
+            ticks.0 = 0;
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(TimeScale(1));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with_late_system(Noop)
+        .with_late_system(ExportMetrics)
+        .with_late_system(ResetTicks)
+        .with(ScaledTick)
+        .build(resources);
+
+    let stages = stage_names(&mut scheduler, &mut World::new());
+
+    // Non-conflicting late systems share the first late stage.
+    assert_eq!(
+        stages,
+        vec![
+            vec!["ScaledTick"],
+            vec!["Noop", "ExportMetrics"],
+            vec!["ResetTicks"],
+        ]
+    );
+    assert_eq!(scheduler.resources().get::<Ticks>().0, 0);
+}
+
+#[test]
+fn late_systems_at_runtime() {
+    let log = Arc::new(Mutex::new(vec![]));
+    let record = |name| Record {
+        name,
+        log: Arc::clone(&log),
+    };
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with_late_system(record("export"))
+        .with(record("simulate"))
+        .build(Resources::new());
+
+    // The systems do not conflict, so only being late orders them.
+    execute_in_parallel(&mut scheduler);
+    assert_eq!(
+        *log.lock(),
+        vec![
+            "simulate start",
+            "simulate end",
+            "export start",
+            "export end"
+        ]
+    );
+}
+
+#[test]
+#[should_panic(expected = "systems declare no resource or component accesses")]
+fn deny_empty_access() {
+    SchedulerBuilder::new()
+        .with(ScaledTick)
+        .with(Noop)
+        .with_empty_access_policy(EmptyAccessPolicy::Deny)
+        .build(Resources::new());
+}
+
+#[test]
+fn allow_marked_empty_access() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ScaledTick)
+        .with_empty_access(Noop)
+        .with_empty_access_policy(EmptyAccessPolicy::Deny)
+        .build(Resources::new());
+    scheduler.execute(&mut World::new());
+}
+
+#[test]
+fn from_system_graph() {
+    #[derive(Default)]
+    struct Frames(u32);
+
+    struct CountFrames;
+
+    impl System for CountFrames {
+        type SystemData = Write<Frames>;
+
+        fn run(&mut self, frames: <Self::SystemData as SystemData>::Output) {
+            frames.0 += 1;
+        }
+    }
+
+    let node = |system: Box<dyn RawSystem>, writes: Vec<ResourceId>| SysNode {
+        system,
+        reads: vec![],
+        writes,
+    };
+    let nodes = vec![
+        node(
+            Box::new(CachedSystem::new(ScaledTick, "ScaledTick")),
+            vec![],
+        ),
+        // The declared write makes `CountFrames` conflict with `ScaledTick`.
+        node(
+            Box::new(CachedSystem::new(CountFrames, "CountFrames")),
+            vec![resource_id_for::<Ticks>()],
+        ),
+        node(Box::new(CachedSystem::new(Noop, "Noop")), vec![]),
+    ];
+    let edges = vec![(TypeId::of::<CountFrames>(), TypeId::of::<Noop>())];
+
+    let mut resources = Resources::new();
+    resources.insert(TimeScale(1));
+    let mut scheduler = SchedulerBuilder::from_system_graph(nodes, edges).build(resources);
+
+    let stages = stage_names(&mut scheduler, &mut World::new());
+    assert_eq!(
+        stages,
+        vec![vec!["ScaledTick"], vec!["CountFrames"], vec!["Noop"]]
+    );
+    assert_eq!(scheduler.resources().get::<Frames>().0, 1);
+}
+
+#[test]
+fn from_system_graph_at_runtime() {
+    struct Load(Record);
+    struct Simulate(Record);
+
+    impl System for Load {
+        type SystemData = ();
+
+        fn run(&mut self, data: <Self::SystemData as SystemData>::Output) {
+            self.0.run(data);
+        }
+    }
+
+    impl System for Simulate {
+        type SystemData = ();
+
+        fn run(&mut self, data: <Self::SystemData as SystemData>::Output) {
+            self.0.run(data);
+        }
+    }
+
+    let log = Arc::new(Mutex::new(vec![]));
+    let record = |name| Record {
+        name,
+        log: Arc::clone(&log),
+    };
+    let node = |system: Box<dyn RawSystem>| SysNode {
+        system,
+        reads: vec![],
+        writes: vec![],
+    };
+    let nodes = vec![
+        node(Box::new(CachedSystem::new(
+            Simulate(record("simulate")),
+            "Simulate",
+        ))),
+        node(Box::new(CachedSystem::new(Load(record("load")), "Load"))),
+    ];
+    let edges = vec![(TypeId::of::<Load>(), TypeId::of::<Simulate>())];
+    let mut scheduler = SchedulerBuilder::from_system_graph(nodes, edges).build(Resources::new());
+
+    // The systems do not conflict, so only the edge orders them.
+    execute_in_parallel(&mut scheduler);
+    assert_eq!(
+        *log.lock(),
+        vec!["load start", "load end", "simulate start", "simulate end"]
+    );
+}
+
+#[test]
+fn debug_label() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with_debug_label("physics")
+        .build(Resources::new());
+    assert_eq!(scheduler.to_string(), "scheduler \"physics\"");
+
+    let span = scheduler.profile_dispatch(&mut World::new());
+    assert_eq!(span.name, "dispatch physics");
+
+    let unlabeled = SchedulerBuilder::new().build(Resources::new());
+    assert_eq!(unlabeled.to_string(), "scheduler");
+}
+
+#[test]
+#[should_panic(expected = "scheduler \"physics\": no system has the label \"save\"")]
+fn debug_label_in_panics() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with_debug_label("physics")
+        .build(Resources::new());
+    scheduler.execute_labeled("save", &mut World::new());
+}
+
+#[test]
+fn system_barrier_after() {
+    let mut resources = Resources::new();
+    resources.insert(TimeScale(1));
+
+    // Without the barrier, `Noop` would share a stage with `ScaledTick`.
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ScaledTick)
+        .with_system_barrier_after::<ScaledTick>()
+        .with(Noop)
+        .build(resources);
+
+    let stages = stage_names(&mut scheduler, &mut World::new());
+    assert_eq!(stages, vec![vec!["ScaledTick"], vec!["Noop"]]);
+}
+
+#[test]
+fn system_barrier_after_at_runtime() {
+    let log = Arc::new(Mutex::new(vec![]));
+    let record = |name| Record {
+        name,
+        log: Arc::clone(&log),
+    };
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(record("sync"))
+        .with_system_barrier_after::<Record>()
+        .with(record("simulate"))
+        .build(Resources::new());
+
+    // The systems do not conflict, so only the barrier orders them.
+    execute_in_parallel(&mut scheduler);
+    assert_eq!(
+        *log.lock(),
+        vec!["sync start", "sync end", "simulate start", "simulate end"]
+    );
+}
+
+#[test]
+#[should_panic(expected = "has been added")]
+fn system_barrier_after_missing_system() {
+    SchedulerBuilder::new()
+        .with(Noop)
+        .with_system_barrier_after::<ScaledTick>();
+}
+
+#[test]
+fn profile_stage_assignments() {
+    let builder = SchedulerBuilder::new()
+        .with(ScaledTick)
+        .with(Noop)
+        .with(ScaledTick);
+    let report = builder.profile_stage_assignments(&Resources::new());
+    let lines: Vec<&str> = report.lines().collect();
+
+    let scaled_tick = std::any::type_name::<ScaledTick>();
+    assert_eq!(
+        lines,
+        vec![
+            format!("System {} was placed in stage 0.", scaled_tick),
+            format!("System {} was placed in stage 0.", std::any::type_name::<Noop>()),
+            format!(
+                "System {} was placed in stage 1 because it conflicts with systems {} on resource {:?}.",
+                scaled_tick,
+                scaled_tick,
+                resource_id_for::<Ticks>()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn ordered_group() {
+    struct First;
+    struct Second;
+    struct Third;
+
+    impl System for First {
+        type SystemData = Read<TimeScale>;
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    impl System for Second {
+        type SystemData = Read<TimeScale>;
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    impl System for Third {
+        type SystemData = Read<TimeScale>;
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(TimeScale(1));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with_ordered_group(|group| group.with(First).with(Second).with(Third))
+        .with(Noop)
+        .build(resources);
+
+    let stages = stage_names(&mut scheduler, &mut World::new());
+
+    // `Noop` is not part of the group, so it runs alongside `First`.
+    assert_eq!(
+        stages,
+        vec![vec!["First", "Noop"], vec!["Second"], vec!["Third"]]
+    );
+}
+
+#[test]
+fn ordered_group_at_runtime() {
+    let log = Arc::new(Mutex::new(vec![]));
+    let record = |name| Record {
+        name,
+        log: Arc::clone(&log),
+    };
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with_ordered_group(|group| group.with(record("first")).with(record("second")))
+        .build(Resources::new());
+
+    // The systems do not conflict, so only the group orders them.
+    execute_in_parallel(&mut scheduler);
+    assert_eq!(
+        *log.lock(),
+        vec!["first start", "first end", "second start", "second end"]
+    );
+}
+
+#[test]
+fn stage_limit() {
+    struct Record(u32);
+
+    impl System for Record {
+        type SystemData = Write<Vec<u32>>;
+
+        fn run(&mut self, data: <Self::SystemData as SystemData>::Output) {
+            data.push(self.0);
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(Vec::<u32>::new());
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Record(1))
+        .with(Record(2))
+        .with(Record(3))
+        .with(Record(4))
+        .with_stage_limit(2)
+        .build(resources);
+
+    let profile = scheduler.profile_dispatch(&mut World::new());
+    let stage_sizes: Vec<usize> = profile
+        .children
+        .iter()
+        .map(|stage| stage.children.len())
+        .collect();
+    assert_eq!(stage_sizes, vec![2, 2]);
+
+    // Conflicting systems in a merged stage run serially in order.
+    scheduler.execute(&mut World::new());
+    assert_eq!(
+        *scheduler.resources().get::<Vec<u32>>(),
+        vec![1, 2, 3, 4, 1, 2, 3, 4]
+    );
+}
+
+#[test]
+fn duplicate_resource_policy() {
+    let resources = || {
+        let mut resources = Resources::new();
+        resources.insert(TimeScale(1));
+        resources
+    };
+
+    let error = SchedulerBuilder::new()
+        .with_optional_resource(true, || TimeScale(2))
+        .on_duplicate_resource(DuplicatePolicy::Error)
+        .try_build(resources())
+        .err()
+        .unwrap();
+    assert_eq!(error.id(), resource_id_for::<TimeScale>());
+    assert_eq!(error.type_name(), std::any::type_name::<TimeScale>());
+    assert!(error.to_string().contains("inserted more than once"));
+
+    let scheduler = SchedulerBuilder::new()
+        .with_optional_resource(true, || TimeScale(2))
+        .on_duplicate_resource(DuplicatePolicy::KeepExisting)
+        .build(resources());
+    assert_eq!(scheduler.resources().get::<TimeScale>().0, 1);
+
+    let scheduler = SchedulerBuilder::new()
+        .with_optional_resource(true, || TimeScale(2))
+        .build(resources());
+    assert_eq!(scheduler.resources().get::<TimeScale>().0, 2);
+}
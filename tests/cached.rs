@@ -0,0 +1,56 @@
+//! Testing of `Cached` resources.
+
+use legion::world::World;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use tonks::{Cached, Resources, SchedulerBuilder, System, SystemData, Write};
+
+struct Positions(Vec<u32>);
+
+/// Stand-in for a spatial index built from `Positions`.
+struct SpatialIndex(u32);
+
+#[derive(Default)]
+struct Observed(u32);
+
+struct ObserveIndex;
+
+impl System for ObserveIndex {
+    type SystemData = (Cached<SpatialIndex, (Positions,)>, Write<Observed>);
+
+    fn run(&mut self, (index, observed): <Self::SystemData as SystemData>::Output) {
+        observed.0 = index.0;
+    }
+}
+
+#[test]
+fn recomputes_on_dependency_change() {
+    let computations = Arc::new(AtomicU32::new(0));
+
+    let mut resources = Resources::new();
+    resources.insert(Positions(vec![1, 2]));
+
+    let counter = Arc::clone(&computations);
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ObserveIndex)
+        .with_cached_resource::<SpatialIndex, (Positions,), _>(move |positions: &Positions| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            SpatialIndex(positions.0.iter().sum())
+        })
+        .build(resources);
+
+    let mut world = World::new();
+
+    // Always computed on first access, then skipped while
+    // the positions are unchanged.
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    assert_eq!(computations.load(Ordering::Relaxed), 1);
+    assert_eq!(scheduler.resources().get::<Observed>().0, 3);
+
+    scheduler.replace_resource(Positions(vec![1, 2, 3]));
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    assert_eq!(computations.load(Ordering::Relaxed), 2);
+    assert_eq!(scheduler.resources().get::<Observed>().0, 6);
+}
@@ -0,0 +1,21 @@
+//! Helpers shared by the integration tests.
+
+use legion::world::World;
+use tonks::Scheduler;
+
+/// Runs a profiled dispatch and returns the names of the systems in
+/// each stage, without their module paths, in the order they ran.
+pub fn stage_names(scheduler: &mut Scheduler, world: &mut World) -> Vec<Vec<String>> {
+    scheduler
+        .profile_dispatch(world)
+        .children
+        .iter()
+        .map(|stage| {
+            stage
+                .children
+                .iter()
+                .map(|system| system.name.rsplit("::").next().unwrap().to_owned())
+                .collect()
+        })
+        .collect()
+}
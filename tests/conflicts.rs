@@ -1,6 +1,11 @@
 //! Check various conflicts involving resource access.
 
-use tonks::{EventHandler, EventsBuilder, Read, SchedulerBuilder, System, SystemData, Write};
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use tonks::{
+    system_id_for, CachedSystem, EventHandler, EventsBuilder, RawSystem, Read, ResourceId,
+    Resources, SchedulerBuilder, System, SystemCtx, SystemData, SystemId, Write,
+};
 
 #[derive(Default)]
 struct Resource1(u32);
@@ -66,3 +71,69 @@ fn read_and_write() {
 fn read_and_write_handler() {
     let _ = EventsBuilder::new().with(ReadAndWrite);
 }
+
+/// A raw system which declares a conflict with another
+/// system without sharing any resource accesses.
+struct ScriptSystem {
+    id: SystemId,
+    conflicts_with: SystemId,
+}
+
+impl RawSystem for ScriptSystem {
+    fn id(&self) -> SystemId {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        "script"
+    }
+
+    fn resource_reads(&self) -> &[ResourceId] {
+        &[]
+    }
+
+    fn resource_writes(&self) -> &[ResourceId] {
+        &[]
+    }
+
+    fn component_reads(&self) -> &[ComponentTypeId] {
+        &[]
+    }
+
+    fn component_writes(&self) -> &[ComponentTypeId] {
+        &[]
+    }
+
+    fn init(&mut self, _resources: &mut Resources, _ctx: SystemCtx, _world: &World) {}
+
+    unsafe fn execute_raw(&mut self, _resources: &Resources, _ctx: SystemCtx, _world: &World) {}
+
+    fn conflicts_with(&self, other: &dyn RawSystem) -> bool {
+        other.id() == self.conflicts_with
+    }
+}
+
+struct ReadResource1;
+
+impl System for ReadResource1 {
+    type SystemData = Read<Resource1>;
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+}
+
+#[test]
+fn custom_conflict() {
+    let conflictee = CachedSystem::new(ReadResource1, "conflictee");
+    let script = ScriptSystem {
+        id: system_id_for::<ScriptSystem>(),
+        conflicts_with: conflictee.id(),
+    };
+
+    let mut builder = SchedulerBuilder::new();
+    builder.add_boxed(Box::new(conflictee));
+    builder.add_boxed(Box::new(script));
+    let mut scheduler = builder.build(Resources::new());
+
+    let root = scheduler.profile_dispatch(&mut World::new());
+    assert_eq!(root.children.len(), 2);
+}
@@ -0,0 +1,14 @@
+#[test]
+fn derive_system_data() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/derive_system_data.rs");
+}
+
+// The registry submission generated by `#[system]` refers to `inventory`,
+// which UI test crates do not depend on.
+#[cfg(not(feature = "system-registry"))]
+#[test]
+fn system_write_not_sync() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/system_write_not_sync.rs");
+}
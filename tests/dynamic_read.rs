@@ -0,0 +1,94 @@
+//! Testing of `DynamicRead`.
+
+mod common;
+
+use common::stage_names;
+use legion::world::World;
+use tonks::{
+    resource_id_for, DynamicRead, Read, ResourceId, Resources, SchedulerBuilder, System,
+    SystemData, Write,
+};
+
+struct Front(Vec<u32>);
+struct Back(Vec<u32>);
+
+/// Names the buffer which is currently presented.
+struct Presented(ResourceId);
+
+#[derive(Default)]
+struct Sum(u32);
+
+struct SumPresented;
+
+impl System for SumPresented {
+    type SystemData = (Read<Presented>, DynamicRead<(Front, Back)>, Write<Sum>);
+
+    fn run(&mut self, (presented, buffers, sum): <Self::SystemData as SystemData>::Output) {
+        let buffer = buffers.get_dyn(presented.0).unwrap();
+        sum.0 = match buffer.downcast_ref::<Front>() {
+            Some(front) => front.0.iter().sum(),
+            None => buffer.downcast_ref::<Back>().unwrap().0.iter().sum(),
+        };
+    }
+}
+
+struct Swap;
+
+impl System for Swap {
+    type SystemData = Write<Presented>;
+
+    fn run(&mut self, presented: <Self::SystemData as SystemData>::Output) {
+        presented.0 = if presented.0 == resource_id_for::<Front>() {
+            resource_id_for::<Back>()
+        } else {
+            resource_id_for::<Front>()
+        };
+    }
+}
+
+struct FillBack;
+
+impl System for FillBack {
+    type SystemData = Write<Back>;
+
+    fn run(&mut self, back: <Self::SystemData as SystemData>::Output) {
+        back.0.push(100);
+    }
+}
+
+#[test]
+fn selected_at_runtime() {
+    let mut resources = Resources::new();
+    resources.insert(Front(vec![1, 2, 3]));
+    resources.insert(Back(vec![]));
+    resources.insert(Presented(resource_id_for::<Front>()));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(SumPresented)
+        .with(Swap)
+        .with(FillBack)
+        .build(resources);
+    let mut world = World::new();
+
+    // `FillBack` writes a candidate, so it must not share
+    // a stage with `SumPresented`, even while `Front` is selected.
+    let stages = stage_names(&mut scheduler, &mut world);
+    assert_eq!(stages, vec![vec!["SumPresented"], vec!["Swap", "FillBack"]]);
+    assert_eq!(scheduler.resources().get::<Sum>().0, 6);
+
+    // `Back` is selected now, and was filled after the previous sum.
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Sum>().0, 100);
+}
+
+#[test]
+fn candidates() {
+    struct Other;
+
+    assert!(DynamicRead::<(Front, Back)>::is_candidate(
+        resource_id_for::<Back>()
+    ));
+    assert!(!DynamicRead::<(Front, Back)>::is_candidate(
+        resource_id_for::<Other>()
+    ));
+}
@@ -0,0 +1,78 @@
+#[macro_use]
+extern crate tonks;
+
+use legion::entity::Entity;
+use legion::query::{Read, Write};
+use legion::world::World;
+use tonks::{EntityRead, PreparedWorld, Query, Resources, SchedulerBuilder};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Age(u32);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Name(&'static str);
+#[derive(Resource)]
+struct E(Entity);
+
+#[test]
+fn basic() {
+    #[system]
+    fn sys(ages: &EntityRead<Age>, e: &E) {
+        assert_eq!(*ages.get(e.0).unwrap(), Age(10));
+        assert!(ages.has(e.0));
+    }
+
+    let mut world = World::new();
+    let entity = world.insert((), [(Age(10), 2)].iter().copied())[0];
+
+    let mut resources = Resources::new();
+    resources.insert(E(entity));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+
+    scheduler.execute(&mut world);
+}
+
+#[test]
+fn missing_component() {
+    #[system]
+    fn sys(names: &EntityRead<Name>, e: &E) {
+        assert!(names.get(e.0).is_none());
+    }
+
+    let mut world = World::new();
+    let entity = world.insert((), [(Age(10), 2)].iter().copied())[0];
+
+    let mut resources = Resources::new();
+    resources.insert(E(entity));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+
+    scheduler.execute(&mut world);
+}
+
+#[test]
+fn conflicts_with_component_writes() {
+    #[system]
+    fn read_ages(_ages: &EntityRead<Age>) {}
+
+    #[system]
+    fn read_ages_query(_query: &mut Query<Read<Age>>, _world: &mut PreparedWorld) {}
+
+    #[system]
+    fn write_ages(_query: &mut Query<Write<Age>>, _world: &mut PreparedWorld) {}
+
+    let mut world = World::new();
+
+    // Reads of the same component can run in parallel.
+    let mut scheduler = SchedulerBuilder::new()
+        .with(read_ages)
+        .with(read_ages_query)
+        .build(Resources::new());
+    assert_eq!(scheduler.profile_dispatch(&mut world).children.len(), 1);
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(read_ages)
+        .with(write_ages)
+        .build(Resources::new());
+    assert_eq!(scheduler.profile_dispatch(&mut world).children.len(), 2);
+}
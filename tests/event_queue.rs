@@ -0,0 +1,135 @@
+//! Testing of `EventWriter` and `EventReader`.
+
+use legion::world::World;
+use tonks::{
+    EventReader, EventRetention, EventWriter, Resource, Resources, SchedulerBuilder, System,
+    SystemData, Write,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Hit(u32);
+
+#[derive(Default)]
+struct Next(u32);
+
+struct Shoot;
+
+impl System for Shoot {
+    type SystemData = (EventWriter<Hit>, Write<Next>);
+
+    fn run(&mut self, (writer, next): <Self::SystemData as SystemData>::Output) {
+        writer.send(Hit(next.0));
+        writer.send_batch(vec![Hit(next.0 + 1)]);
+        next.0 += 2;
+    }
+}
+
+#[derive(Default, Resource)]
+struct Damage(Vec<u32>);
+
+#[tonks::system]
+fn apply_damage(reader: &mut EventReader<Hit>, damage: &mut Damage) {
+    damage.0.extend(reader.iter().map(|hit| hit.0));
+}
+
+#[derive(Default, Resource)]
+struct Sounds(Vec<u32>);
+
+#[tonks::system]
+fn play_sounds(reader: &mut EventReader<Hit>, sounds: &mut Sounds) {
+    assert_eq!(reader.len(), 2);
+    sounds.0.extend(reader.iter().map(|hit| hit.0));
+    assert!(reader.is_empty());
+}
+
+#[test]
+fn every_reader_sees_every_event() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Shoot)
+        .with(apply_damage)
+        .with(play_sounds)
+        .build(Resources::new());
+    let mut world = World::new();
+
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+    }
+
+    // Readers run in the stage after the writer, so they
+    // see the events sent during the same dispatch.
+    let expected = vec![0, 1, 2, 3, 4, 5];
+    assert_eq!(scheduler.resources().get::<Damage>().0, expected);
+    assert_eq!(scheduler.resources().get::<Sounds>().0, expected);
+}
+
+#[derive(Default)]
+struct Seen(Vec<u32>);
+
+struct ReadEvery(u32);
+
+impl System for ReadEvery {
+    type SystemData = (EventReader<Hit>, Write<Seen>, Write<Next>);
+
+    fn run(&mut self, (reader, seen, next): <Self::SystemData as SystemData>::Output) {
+        // Only read in every third dispatch.
+        next.0 += 1;
+        if next.0 % self.0 == 0 {
+            seen.0.extend(reader.iter().map(|hit| hit.0));
+        }
+    }
+}
+
+#[test]
+fn unread_events_are_dropped() {
+    struct ShootOnce;
+
+    impl System for ShootOnce {
+        type SystemData = EventWriter<Hit>;
+
+        fn run(&mut self, writer: <Self::SystemData as SystemData>::Output) {
+            writer.send_batch((0..4).map(Hit));
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ShootOnce)
+        .with(ReadEvery(3))
+        .with_event_retention::<Hit>(EventRetention::Capacity(3))
+        .build(Resources::new());
+    let mut world = World::new();
+
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+    }
+
+    // Only the last three events are retained.
+    assert_eq!(scheduler.resources().get::<Seen>().0, vec![1, 2, 3]);
+}
+
+#[test]
+fn events_expire_after_dispatches() {
+    struct ShootFirst;
+
+    impl System for ShootFirst {
+        type SystemData = (EventWriter<Hit>, Write<Next>);
+
+        fn run(&mut self, (writer, next): <Self::SystemData as SystemData>::Output) {
+            writer.send(Hit(next.0));
+        }
+    }
+
+    // Events of the previous two dispatches are retained.
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ShootFirst)
+        .with(ReadEvery(3))
+        .build(Resources::new());
+    let mut world = World::new();
+
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+    }
+
+    // `Next` is incremented by the reader after each dispatch's event,
+    // so the events are 0, 1 and 2, of which 0 has expired.
+    assert_eq!(scheduler.resources().get::<Seen>().0, vec![1, 2]);
+}
@@ -1,10 +1,13 @@
 use hashbrown::HashMap;
 use legion::world::World;
+use parking_lot::Mutex;
 use std::iter;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use tonks::{
-    resource_id_for, EventHandler, EventsBuilder, Read, Resources, System, SystemData, Trigger,
-    Write,
+    event_id_for, resource_id_for, CachedEventHandler, DynamicTaskRecord, DynamicTrigger,
+    EventBatch, EventHandler, EventsBuilder, RawEventHandler, Read, Resources, SchedulerBuilder,
+    System, SystemData, Trigger, Write,
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -263,3 +266,216 @@ fn recursive_trigger() {
         assert_eq!(count, 8);
     }
 }
+
+#[test]
+fn event_batch() {
+    struct Sys1;
+
+    impl System for Sys1 {
+        type SystemData = Trigger<Ev>;
+
+        fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+            trigger.trigger_batched([Ev(1), Ev(2)].iter().copied());
+        }
+    }
+
+    struct Sys2;
+
+    impl System for Sys2 {
+        type SystemData = Trigger<Ev>;
+
+        fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+            trigger.trigger(Ev(3));
+        }
+    }
+
+    #[derive(Default)]
+    struct Batches(Vec<Vec<Ev>>);
+
+    struct Receiver;
+
+    impl System for Receiver {
+        type SystemData = (EventBatch<Ev>, Write<Batches>);
+
+        fn run(&mut self, (batch, batches): <Self::SystemData as SystemData>::Output) {
+            let mut events = batch.to_vec();
+            assert_eq!(events.len(), batch.len());
+            events.sort_by_key(|event| event.0);
+            batches.0.push(events);
+        }
+    }
+
+    struct Handler;
+
+    impl EventHandler<Ev> for Handler {
+        type HandlerData = Write<HashMap<Ev, usize>>;
+
+        fn handle(&mut self, event: &Ev, count: &mut <Self::HandlerData as SystemData>::Output) {
+            *count.entry(*event).or_insert(0) += 1;
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(HashMap::<Ev, usize>::default());
+
+    let mut scheduler = EventsBuilder::new()
+        .with(Handler)
+        .finish()
+        .with(Sys1)
+        .with(Sys2)
+        .with(Receiver)
+        .build(resources);
+
+    // The triggering systems share a stage; the receiver runs after both.
+    let root = scheduler.profile_dispatch(&mut World::new());
+    assert_eq!(root.children.len(), 2);
+    assert_eq!(root.children[0].children.len(), 2);
+
+    scheduler.execute(&mut World::new());
+
+    let batches = &scheduler.resources().get::<Batches>().0;
+    assert_eq!(batches.len(), 2);
+    for batch in batches {
+        assert_eq!(batch, &[Ev(1), Ev(2), Ev(3)]);
+    }
+
+    // Events are still passed to event handlers.
+    let counts = scheduler.resources().get::<HashMap<Ev, usize>>();
+    for ev in [Ev(1), Ev(2), Ev(3)].iter() {
+        assert_eq!(counts[ev], 2);
+    }
+}
+
+#[test]
+fn observer() {
+    struct Sys;
+
+    impl System for Sys {
+        type SystemData = Trigger<Ev>;
+
+        fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+            trigger.trigger(Ev(1));
+            trigger.trigger_batched([Ev(2), Ev(3)].iter().copied());
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new().with(Sys).build(Resources::new());
+
+    let sum = Arc::new(AtomicUsize::new(0));
+    let observed = Arc::clone(&sum);
+    scheduler.add_observer(move |ev: &Ev| {
+        observed.fetch_add(ev.0 as usize, Ordering::Relaxed);
+    });
+
+    scheduler.execute(&mut World::new());
+    assert_eq!(sum.load(Ordering::Relaxed), 6);
+
+    scheduler.execute(&mut World::new());
+    assert_eq!(sum.load(Ordering::Relaxed), 12);
+}
+
+#[test]
+fn unhandled_event_handler() {
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Unhandled(u32);
+
+    struct Sys;
+
+    impl System for Sys {
+        type SystemData = Trigger<Unhandled>;
+
+        fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+            trigger.trigger(Unhandled(7));
+        }
+    }
+
+    let seen = Arc::new(Mutex::new(vec![]));
+    let recorded = Arc::clone(&seen);
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Sys)
+        .with_unhandled_event_handler(move |id, event| {
+            recorded
+                .lock()
+                .push((id, *event.downcast_ref::<Unhandled>().unwrap()));
+        })
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+    assert_eq!(
+        *seen.lock(),
+        vec![(event_id_for::<Unhandled>(), Unhandled(7))]
+    );
+}
+
+#[test]
+fn last_dynamic_tasks() {
+    struct Sys;
+
+    impl System for Sys {
+        type SystemData = Trigger<Ev>;
+
+        fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+            trigger.trigger(Ev(1));
+        }
+    }
+
+    struct Handler;
+
+    impl EventHandler<Ev> for Handler {
+        type HandlerData = ();
+
+        fn handle(&mut self, _event: &Ev, _data: &mut <Self::HandlerData as SystemData>::Output) {}
+    }
+
+    let handler = CachedEventHandler::new(Handler, "Handler");
+    let handler_id = handler.id();
+
+    let mut events = EventsBuilder::new();
+    events.add_boxed(Box::new(handler));
+    let mut scheduler = events.finish().with(Sys).build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+    assert_eq!(
+        scheduler.last_dynamic_tasks(),
+        &[DynamicTaskRecord {
+            system: handler_id,
+            trigger: DynamicTrigger::Event(event_id_for::<Ev>()),
+            index: 0,
+        }]
+    );
+}
+
+#[test]
+fn emit_order() {
+    #[derive(Default)]
+    struct Handled(Vec<u32>);
+
+    #[tonks::system]
+    fn emit(trigger: &mut Trigger<Ev>) {
+        trigger.emit(Ev(1));
+        trigger.emit_batch((2..5).map(Ev));
+        trigger.emit(Ev(5));
+    }
+
+    struct Record;
+
+    impl EventHandler<Ev> for Record {
+        type HandlerData = Write<Handled>;
+
+        fn handle(&mut self, event: &Ev, handled: &mut <Self::HandlerData as SystemData>::Output) {
+            handled.0.push(event.0);
+        }
+    }
+
+    let mut scheduler = EventsBuilder::new()
+        .with(Record)
+        .finish()
+        .with(emit)
+        .build(Resources::default());
+
+    scheduler.execute(&mut World::new());
+    assert_eq!(
+        scheduler.resources().get::<Handled>().0,
+        vec![1, 2, 3, 4, 5]
+    );
+}
@@ -0,0 +1,116 @@
+//! Testing of the order in which system data is flushed.
+
+use legion::storage::ComponentTypeId;
+use legion::world::World;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tonks::{
+    FlushPhase, ResourceId, Resources, SchedulerBuilder, System, SystemCtx, SystemData,
+    SystemDataOutput,
+};
+
+/// Log of flushed system data, shared by all `Flush`es.
+#[derive(Default)]
+struct FlushLog(Arc<Mutex<Vec<&'static str>>>);
+
+/// System data which records its name when flushed.
+struct Flush<N: Name> {
+    log: Arc<Mutex<Vec<&'static str>>>,
+    _name: N,
+}
+
+trait Name: Default + Send + Sync + 'static {
+    const NAME: &'static str;
+}
+
+#[derive(Default)]
+struct Commands;
+
+impl Name for Commands {
+    const NAME: &'static str = "commands";
+}
+
+#[derive(Default)]
+struct Events;
+
+impl Name for Events {
+    const NAME: &'static str = "events";
+}
+
+impl<'a, N: Name> SystemData<'a> for Flush<N> {
+    type Output = &'a Self;
+
+    unsafe fn load_from_resources(
+        resources: &mut Resources,
+        _ctx: SystemCtx,
+        _world: &World,
+    ) -> Self {
+        resources.insert_if_absent(FlushLog::default());
+        Self {
+            log: Arc::clone(&resources.get::<FlushLog>().0),
+            _name: N::default(),
+        }
+    }
+
+    fn resource_reads() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn resource_writes() -> Vec<ResourceId> {
+        vec![]
+    }
+
+    fn component_reads() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn component_writes() -> Vec<ComponentTypeId> {
+        vec![]
+    }
+
+    fn before_execution(&'a mut self) -> Self::Output {
+        self
+    }
+
+    fn after_execution(&mut self) {
+        self.log.lock().push(N::NAME);
+    }
+}
+
+impl<'a, N: Name> SystemDataOutput<'a> for &'a Flush<N> {
+    type SystemData = Flush<N>;
+}
+
+struct DeclarationOrder;
+
+impl System for DeclarationOrder {
+    type SystemData = (Flush<Events>, Flush<Commands>);
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+}
+
+struct ExplicitOrder;
+
+impl System for ExplicitOrder {
+    type SystemData = (Flush<Events>, FlushPhase<Flush<Commands>, -1>);
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {}
+}
+
+fn flush_order<S: System>(system: S) -> Vec<&'static str> {
+    let mut scheduler = SchedulerBuilder::new().with(system).build(Resources::new());
+    scheduler.execute(&mut World::new());
+
+    let log = scheduler.resources().get::<FlushLog>().0.lock().clone();
+    log
+}
+
+#[test]
+fn declaration_order() {
+    assert_eq!(flush_order(DeclarationOrder), vec!["events", "commands"]);
+}
+
+#[test]
+fn flush_phase() {
+    assert_eq!(flush_order(ExplicitOrder), vec!["commands", "events"]);
+}
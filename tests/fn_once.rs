@@ -0,0 +1,48 @@
+//! Testing of `FnOnceSystem`.
+
+use legion::world::World;
+use tonks::{FnOnceSystem, Read, Resources, SchedulerBuilder, System, SystemData, Write};
+
+#[derive(Default)]
+struct Total(u64);
+
+#[derive(Default)]
+struct Observed(Vec<u64>);
+
+struct Observe;
+
+impl System for Observe {
+    type SystemData = (Read<Total>, Write<Observed>);
+
+    fn run(&mut self, (total, observed): <Self::SystemData as SystemData>::Output) {
+        observed.0.push(total.0);
+    }
+}
+
+#[test]
+fn runs_once_and_removes_itself() {
+    let initial: Vec<u64> = (1..=100).collect();
+
+    let init = FnOnceSystem::<Write<Total>, _>::new(move |total: &mut Write<Total>| {
+        total.0 = initial.into_iter().sum();
+    });
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(init)
+        .with(Observe)
+        .build(Resources::new());
+
+    let mut world = World::new();
+
+    // The initializer conflicts with `Observe`, so both stages run.
+    assert_eq!(scheduler.profile_dispatch(&mut world).children.len(), 2);
+
+    // The initializer has been removed, leaving only one stage.
+    assert_eq!(scheduler.profile_dispatch(&mut world).children.len(), 1);
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        scheduler.resources().get::<Observed>().0,
+        vec![5050, 5050, 5050]
+    );
+}
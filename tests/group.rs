@@ -0,0 +1,100 @@
+mod common;
+
+use common::stage_names;
+use legion::world::World;
+use tonks::{
+    resource_id_for, Read, Resources, SchedulerBuilder, System, SystemData, Write, WriteGroup,
+};
+
+/// Group of the resources registered by a plugin.
+struct Plugin;
+
+struct Gravity(i32);
+struct Velocity(i32);
+
+struct ResetPlugin;
+
+impl System for ResetPlugin {
+    type SystemData = WriteGroup<Plugin>;
+
+    fn run(&mut self, group: <Self::SystemData as SystemData>::Output) {
+        group.get_mut::<Gravity>().unwrap().0 = 0;
+        group.get_mut::<Velocity>().unwrap().0 = 0;
+    }
+}
+
+struct ReadGravity;
+
+impl System for ReadGravity {
+    type SystemData = Read<Gravity>;
+
+    fn run(&mut self, _gravity: <Self::SystemData as SystemData>::Output) {}
+}
+
+struct Accelerate;
+
+impl System for Accelerate {
+    type SystemData = Write<Velocity>;
+
+    fn run(&mut self, velocity: <Self::SystemData as SystemData>::Output) {
+        velocity.0 += 1;
+    }
+}
+
+#[test]
+fn write_group_conflicts_with_members() {
+    let mut resources = Resources::new();
+    resources.insert(Gravity(-10));
+    resources.insert(Velocity(5));
+    resources.tag::<Gravity, Plugin>();
+    resources.tag::<Velocity, Plugin>();
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ReadGravity)
+        .with(Accelerate)
+        .with(ResetPlugin)
+        .build(resources);
+
+    let stages = stage_names(&mut scheduler, &mut World::new());
+
+    // `ResetPlugin` runs in a separate stage from the systems
+    // accessing the tagged resources individually.
+    assert_eq!(
+        stages,
+        vec![vec!["ReadGravity", "Accelerate"], vec!["ResetPlugin"]]
+    );
+    assert_eq!(scheduler.resources().get::<Gravity>().0, 0);
+    assert_eq!(scheduler.resources().get::<Velocity>().0, 0);
+    assert_eq!(scheduler.resources().version::<Velocity>(), 3);
+}
+
+#[test]
+fn profile_stage_assignments_expands_groups() {
+    let mut resources = Resources::new();
+    resources.insert(Gravity(-10));
+    resources.insert(Velocity(5));
+    resources.tag::<Gravity, Plugin>();
+    resources.tag::<Velocity, Plugin>();
+
+    let report = SchedulerBuilder::new()
+        .with(ReadGravity)
+        .with(ResetPlugin)
+        .profile_stage_assignments(&resources);
+    let lines: Vec<&str> = report.lines().collect();
+
+    assert_eq!(
+        lines,
+        vec![
+            format!(
+                "System {} was placed in stage 0.",
+                std::any::type_name::<ReadGravity>()
+            ),
+            format!(
+                "System {} was placed in stage 1 because it conflicts with systems {} on resource {:?}.",
+                std::any::type_name::<ResetPlugin>(),
+                std::any::type_name::<ReadGravity>(),
+                resource_id_for::<Gravity>()
+            ),
+        ]
+    );
+}
@@ -0,0 +1,64 @@
+use legion::world::World;
+use std::collections::HashMap;
+use tonks::{ReadSet, Resources, SchedulerBuilder, System, SystemData, Write, WriteSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
+struct PlayerId(u32);
+
+#[derive(Debug, PartialEq)]
+struct Score(u32);
+
+#[derive(Default)]
+struct Total(u32);
+
+struct AwardPoints;
+
+impl System for AwardPoints {
+    type SystemData = WriteSet<PlayerId, Score>;
+
+    fn run(&mut self, scores: <Self::SystemData as SystemData>::Output) {
+        match scores.get_mut(&PlayerId(1)) {
+            Some(score) => score.0 += 10,
+            None => {
+                scores.insert(PlayerId(1), Score(0));
+            }
+        }
+    }
+}
+
+struct SumScores;
+
+impl System for SumScores {
+    type SystemData = (ReadSet<PlayerId, Score>, Write<Total>);
+
+    fn run(&mut self, (scores, total): <Self::SystemData as SystemData>::Output) {
+        total.0 = scores.iter().map(|(_, score)| score.0).sum();
+    }
+}
+
+#[test]
+fn keyed_resources() {
+    let mut players = HashMap::new();
+    players.insert(PlayerId(2), Score(5));
+
+    let mut resources = Resources::new();
+    resources.insert(players);
+    let mut scheduler = SchedulerBuilder::new()
+        .with(AwardPoints)
+        .with(SumScores)
+        .build(resources);
+
+    let mut world = World::new();
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+    }
+
+    let scores = scheduler.resources().get::<HashMap<PlayerId, Score>>();
+    assert_eq!(scores.get(&PlayerId(1)), Some(&Score(20)));
+    assert_eq!(scores.get(&PlayerId(2)), Some(&Score(5)));
+    assert_eq!(scheduler.resources().get::<Total>().0, 25);
+    assert_eq!(
+        scheduler.resources().version::<HashMap<PlayerId, Score>>(),
+        4
+    );
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "log")]
+
+use lazy_static::lazy_static;
+use legion::world::World;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use parking_lot::Mutex;
+use std::thread;
+use std::time::Duration;
+use tonks::{Resources, SchedulerBuilder, System, SystemData};
+
+struct CapturingLogger {
+    warnings: Mutex<Vec<String>>,
+}
+
+impl Log for CapturingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() == Level::Warn {
+            self.warnings.lock().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+lazy_static! {
+    static ref LOGGER: CapturingLogger = CapturingLogger {
+        warnings: Mutex::new(vec![]),
+    };
+}
+
+struct SlowSystem;
+
+impl System for SlowSystem {
+    type SystemData = ();
+
+    fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn warns_on_long_stage() {
+    log::set_logger(&*LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Warn);
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(SlowSystem)
+        .warn_on_long_stage(Duration::from_millis(1))
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+
+    let warnings = LOGGER.warnings.lock();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("stage 0"));
+    assert!(warnings[0].contains("SlowSystem"));
+}
@@ -1,5 +1,9 @@
 use legion::world::World;
-use tonks::{EventsBuilder, Resources, Trigger};
+use std::sync::atomic::{AtomicU32, Ordering};
+use tonks::{
+    access_of, resource_id_for, CachedSystem, DependencyWarning, EventsBuilder, Marker, MaybeDebug,
+    RawSystem, Resources, SchedulerBuilder, Trigger,
+};
 
 #[macro_use]
 extern crate tonks;
@@ -42,3 +46,271 @@ fn basic() {
     );
     assert_eq!(scheduler.resources().get::<Resource1>().0, 1_000);
 }
+
+#[test]
+fn access() {
+    #[system]
+    fn sys(_r1: &Resource1, _r2: &mut Resource2) {}
+
+    let (reads, writes) = access_of::<sys>();
+    assert_eq!(reads, vec![resource_id_for::<Resource1>()]);
+    assert_eq!(writes, vec![resource_id_for::<Resource2>()]);
+}
+
+#[test]
+fn marker() {
+    struct Foo;
+
+    #[system]
+    fn with_marker(_marker: Marker<Foo>, r1: &Resource1, r2: &mut Resource2) {
+        r2.0 += r1.0;
+    }
+
+    #[system]
+    fn without_marker(_r1: &Resource1, _r2: &mut Resource2) {}
+
+    assert_eq!(access_of::<with_marker>(), access_of::<without_marker>());
+
+    let mut resources = Resources::new();
+    resources.insert(Resource1(3));
+
+    let mut scheduler = SchedulerBuilder::new().with(with_marker).build(resources);
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(scheduler.resources().get::<Resource2>().0, 3);
+}
+
+#[test]
+fn debug_print() {
+    #[derive(Debug, Default, Resource)]
+    struct Position(u32);
+
+    #[system(debug_print)]
+    fn sys(r1: &Resource1, position: &mut Position) {
+        if position.0 > 0 {
+            return;
+        }
+        position.0 += r1.0;
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(Resource1(4));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+    scheduler.execute(&mut World::new());
+    scheduler.execute(&mut World::new());
+    assert_eq!(scheduler.resources().get::<Position>().0, 4);
+
+    assert_eq!(MaybeDebug::maybe_debug(&Position(4)), "Position(4)");
+    assert_eq!(
+        MaybeDebug::maybe_debug(&Resource1(4)),
+        "<no Debug implementation>"
+    );
+}
+
+#[test]
+fn changed_attribute() {
+    #[derive(Default, Resource)]
+    struct Changes(Vec<u32>);
+
+    #[derive(Default, Resource)]
+    struct Tick(u32);
+
+    #[system]
+    fn observe(#[changed] r1: &Resource1, changes: &mut Changes) {
+        if r1.is_changed() {
+            changes.0.push(r1.0);
+        }
+    }
+
+    #[system]
+    fn bump(r1: &mut Resource1, tick: &mut Tick) {
+        tick.0 += 1;
+        if tick.0 == 2 {
+            r1.0 += 1;
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(Resource1(1));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(observe)
+        .with(bump)
+        .build(resources);
+
+    for _ in 0..4 {
+        scheduler.execute(&mut World::new());
+    }
+
+    // Changes are seen on the first run and after `bump` modifies the resource.
+    assert_eq!(scheduler.resources().get::<Changes>().0, vec![1, 2]);
+}
+
+#[test]
+fn access_names() {
+    #[system]
+    fn sys(_r1: &Resource1, _r2: &mut Resource2, _t: &mut Trigger<Ev>) {}
+
+    const READS: &[&str] = sys::READS;
+    assert_eq!(READS, ["Resource1"]);
+    assert_eq!(sys::WRITES, ["Resource2", "Trigger<Ev>"]);
+}
+
+#[derive(SystemData)]
+pub struct Common {
+    pub r1: tonks::Read<Resource1>,
+    pub r2: tonks::Write<Resource2>,
+}
+
+#[test]
+fn system_data_bundle() {
+    #[system]
+    fn sys(common: &Common, t: &mut Trigger<Ev>) {
+        common.r2.0 += common.r1.0;
+        t.trigger(Ev(common.r2.0));
+    }
+
+    #[system]
+    fn sys2(common: &Common) {
+        common.r2.0 *= 10;
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(Resource1(2));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(sys)
+        .with(sys2)
+        .build(resources);
+
+    // The bundle's write conflicts between both systems.
+    assert_eq!(
+        scheduler.profile_dispatch(&mut World::new()).children.len(),
+        2
+    );
+    assert_eq!(scheduler.resources().get::<Resource2>().0, 20);
+}
+
+#[derive(SystemData)]
+pub struct Value<T>
+where
+    T: Default + Send + Sync + 'static,
+{
+    value: tonks::Write<T>,
+}
+
+#[test]
+fn generic_system_data_bundle() {
+    #[system]
+    fn sys(value: &Value<Resource1>) {
+        value.value.0 += 5;
+    }
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(Resources::new());
+    scheduler.execute(&mut World::new());
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(scheduler.resources().get::<Resource1>().0, 10);
+}
+
+#[derive(SystemData)]
+pub struct Borrowed<'a> {
+    r1: tonks::Read<Resource1>,
+    r2: tonks::Write<Resource2>,
+    marker: Marker<&'a ()>,
+}
+
+#[test]
+fn lifetime_system_data_bundle() {
+    #[system]
+    fn sys(borrowed: &Borrowed<'static>) {
+        borrowed.r2.0 += borrowed.r1.0;
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(Resource1(3));
+
+    let mut scheduler = SchedulerBuilder::new().with(sys).build(resources);
+    scheduler.execute(&mut World::new());
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(scheduler.resources().get::<Resource2>().0, 6);
+}
+
+#[test]
+fn audit_system_dependencies() {
+    #[derive(Default, Resource)]
+    struct Hits(AtomicU32);
+
+    #[system]
+    fn count_hits(hits: &Hits, _r1: &Resource1) {
+        hits.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[system]
+    fn read_hits(hits: &Hits, r2: &mut Resource2) {
+        r2.0 = hits.0.load(Ordering::Relaxed);
+    }
+
+    let counter = CachedSystem::new(count_hits, "count_hits");
+    let id = counter.id();
+
+    let mut builder = SchedulerBuilder::new();
+    builder.add_boxed(Box::new(counter));
+    builder.add(read_hits);
+
+    assert_eq!(
+        builder.audit_system_dependencies(),
+        vec![DependencyWarning::PossibleMissingWrite {
+            system_id: id,
+            suspected_resource: resource_id_for::<Hits>(),
+        }]
+    );
+}
+
+#[test]
+fn unsafe_raw() {
+    #[system(unsafe_raw)]
+    fn raw(r1: &Resource1, r2: &mut Resource2) {
+        if r1.0 == 0 {
+            return;
+        }
+        r2.0 += r1.0;
+    }
+
+    let system = raw::new();
+    assert_eq!(
+        system.resource_reads(),
+        &[resource_id_for::<Resource1>()][..]
+    );
+    assert_eq!(
+        system.resource_writes(),
+        &[resource_id_for::<Resource2>()][..]
+    );
+    assert_ne!(system.id(), raw::new().id());
+
+    let mut resources = Resources::new();
+    resources.insert(Resource1(2));
+    let mut builder = SchedulerBuilder::new();
+    builder.add_boxed(Box::new(system));
+    let mut scheduler = builder.build(resources);
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Resource2>().0, 4);
+
+    #[derive(Default, Resource)]
+    struct Hits(AtomicU32);
+
+    #[system(unsafe_raw)]
+    fn count_hits(hits: &Hits) {
+        hits.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    assert_eq!(
+        count_hits::new().suspected_writes(),
+        vec![resource_id_for::<Hits>()]
+    );
+}
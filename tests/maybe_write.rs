@@ -0,0 +1,44 @@
+use legion::world::World;
+use tonks::{MaybeWrite, Resources, SchedulerBuilder, System, SystemData};
+
+#[derive(Clone, Default)]
+struct Counter(u32);
+
+struct Sys {
+    mutate: bool,
+    expected: u32,
+}
+
+impl System for Sys {
+    type SystemData = MaybeWrite<Counter>;
+
+    fn run(&mut self, counter: <Self::SystemData as SystemData>::Output) {
+        if self.mutate {
+            counter.to_mut().0 += 1;
+            self.expected += 1;
+            assert!(counter.is_modified());
+        } else {
+            assert!(!counter.is_modified());
+        }
+
+        assert_eq!(counter.0, self.expected);
+        self.mutate = !self.mutate;
+    }
+}
+
+#[test]
+fn read_or_clone() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Sys {
+            mutate: false,
+            expected: 0,
+        })
+        .build(Resources::new());
+
+    let mut world = World::new();
+    for _ in 0..4 {
+        scheduler.execute(&mut world);
+    }
+
+    assert_eq!(scheduler.resources().get::<Counter>().0, 2);
+}
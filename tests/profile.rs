@@ -0,0 +1,65 @@
+//! Testing of `Scheduler::profile_dispatch()`.
+
+use legion::world::World;
+use tonks::{Resources, SchedulerBuilder, System, SystemData, Write};
+
+#[derive(Default)]
+struct Counter(u32);
+
+struct IncrementA;
+
+impl System for IncrementA {
+    type SystemData = Write<Counter>;
+
+    fn run(&mut self, counter: <Self::SystemData as SystemData>::Output) {
+        counter.0 += 1;
+    }
+}
+
+struct IncrementB;
+
+impl System for IncrementB {
+    type SystemData = Write<Counter>;
+
+    fn run(&mut self, counter: <Self::SystemData as SystemData>::Output) {
+        counter.0 += 1;
+    }
+}
+
+#[test]
+fn profile_dispatch() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(IncrementA)
+        .with(IncrementB)
+        .build(Resources::new());
+
+    let mut world = World::new();
+    let root = scheduler.profile_dispatch(&mut world);
+
+    assert_eq!(root.name, "dispatch");
+    assert_eq!(root.children.len(), 2);
+    assert_eq!(root.depth(), 4);
+
+    for (index, stage) in root.children.iter().enumerate() {
+        assert_eq!(stage.name, format!("stage {}", index));
+        assert!(stage.duration <= root.duration);
+        assert_eq!(stage.children.len(), 1);
+
+        let system = &stage.children[0];
+        let phases: Vec<_> = system.children.iter().map(|phase| &phase.name).collect();
+        assert_eq!(phases, vec!["prepare", "run", "flush"]);
+    }
+
+    let folded = root.to_folded();
+    // 1 dispatch + 2 stages + 2 systems + 6 phases
+    assert_eq!(folded.lines().count(), 11);
+    assert!(folded
+        .lines()
+        .any(|line| line.starts_with("dispatch;stage 0;") && line.contains("IncrementA")));
+
+    assert_eq!(scheduler.resources().get::<Counter>().0, 2);
+
+    // Profiling does not persist to later dispatches.
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Counter>().0, 4);
+}
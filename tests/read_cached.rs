@@ -0,0 +1,87 @@
+//! Testing of `ReadCached` and resource versions.
+
+use legion::world::World;
+use tonks::{
+    Read, ReadCached, Resources, SchedulerBuilder, System, SystemData, TrackedWrite, Write,
+};
+
+#[derive(Clone, Default, PartialEq)]
+struct Matrix(u64);
+
+/// Whether `Update` should modify the matrix.
+#[derive(Default)]
+struct ShouldUpdate(bool);
+
+#[derive(Default)]
+struct Computations(u32);
+
+#[derive(Default)]
+struct Inverse(u64);
+
+struct Update;
+
+impl System for Update {
+    type SystemData = (Read<ShouldUpdate>, TrackedWrite<Matrix>);
+
+    fn run(&mut self, (should_update, matrix): <Self::SystemData as SystemData>::Output) {
+        // The matrix is always accessed mutably, but only
+        // changed when an update is requested.
+        let matrix: &mut Matrix = matrix;
+        matrix.0 += should_update.0 as u64;
+    }
+}
+
+struct Invert;
+
+impl System for Invert {
+    type SystemData = (ReadCached<Matrix, u64>, Write<Computations>, Write<Inverse>);
+
+    fn run(&mut self, (matrix, computations, inverse): <Self::SystemData as SystemData>::Output) {
+        inverse.0 = *matrix.get_or_compute(|matrix| {
+            computations.0 += 1;
+            matrix.0 * 100
+        });
+    }
+}
+
+#[test]
+fn recomputes_on_change() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Update)
+        .with(Invert)
+        .build(Resources::new());
+
+    let mut world = World::new();
+
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Computations>().0, 1);
+    assert_eq!(scheduler.resources().get::<Inverse>().0, 0);
+
+    let version = scheduler.resources().version::<Matrix>();
+
+    scheduler.replace_resource(ShouldUpdate(true));
+    scheduler.execute(&mut world);
+    scheduler.replace_resource(ShouldUpdate(false));
+    scheduler.execute(&mut world);
+
+    assert_eq!(scheduler.resources().version::<Matrix>(), version + 1);
+    assert_eq!(scheduler.resources().get::<Computations>().0, 2);
+    assert_eq!(scheduler.resources().get::<Inverse>().0, 100);
+}
+
+#[test]
+fn versions() {
+    let mut resources = Resources::new();
+    assert_eq!(resources.version::<Matrix>(), 0);
+
+    resources.insert(Matrix(1));
+    let inserted = resources.version::<Matrix>();
+    assert!(inserted > 0);
+
+    let _ = resources.get::<Matrix>();
+    assert_eq!(resources.version::<Matrix>(), inserted);
+
+    resources.get_mut::<Matrix>().0 = 2;
+    assert!(resources.version::<Matrix>() > inserted);
+}
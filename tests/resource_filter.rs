@@ -0,0 +1,47 @@
+use legion::world::World;
+use tonks::{
+    ResourceFilter, ResourcePredicate, Resources, SchedulerBuilder, System, SystemData, Write,
+};
+
+struct PhysicsEnabled(bool);
+
+#[derive(Default)]
+struct Steps(u32);
+
+struct IsEnabled;
+
+impl ResourcePredicate<PhysicsEnabled> for IsEnabled {
+    fn test(enabled: &PhysicsEnabled) -> bool {
+        enabled.0
+    }
+}
+
+struct Physics;
+
+impl System for Physics {
+    type SystemData = (ResourceFilter<PhysicsEnabled, IsEnabled>, Write<Steps>);
+
+    fn run(&mut self, (enabled, steps): <Self::SystemData as SystemData>::Output) {
+        if enabled.get().is_none() {
+            return;
+        }
+
+        steps.0 += 1;
+    }
+}
+
+#[test]
+fn filter_resource() {
+    let mut resources = Resources::new();
+    resources.insert(PhysicsEnabled(false));
+    let mut scheduler = SchedulerBuilder::new().with(Physics).build(resources);
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Steps>().0, 0);
+
+    scheduler.replace_resource(PhysicsEnabled(true));
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Steps>().0, 2);
+}
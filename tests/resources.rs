@@ -0,0 +1,38 @@
+use tonks::{alloc_system_id, resource_id_for, resource_id_for_dyn, system_id_for, SystemId};
+
+trait Marker: Send + Sync {}
+
+struct Concrete;
+
+impl Marker for Concrete {}
+
+#[test]
+fn dyn_resource_ids() {
+    let dyn_id = resource_id_for_dyn::<dyn Marker>();
+
+    assert_eq!(dyn_id, resource_id_for_dyn::<dyn Marker>());
+    assert_ne!(dyn_id, resource_id_for::<Concrete>());
+    assert_eq!(
+        resource_id_for_dyn::<Concrete>(),
+        resource_id_for::<Concrete>()
+    );
+}
+
+#[test]
+fn display_ids() {
+    assert_eq!(
+        resource_id_for::<Concrete>().to_string(),
+        format!("Resource({})", std::any::type_name::<Concrete>())
+    );
+    assert_eq!(
+        system_id_for::<Concrete>().to_string(),
+        format!("System({})", std::any::type_name::<Concrete>())
+    );
+
+    let unnamed = alloc_system_id();
+    assert_eq!(unnamed.to_string(), format!("System(#{})", unnamed.0));
+    assert_eq!(
+        SystemId(usize::MAX).to_string(),
+        format!("System(#{})", usize::MAX)
+    );
+}
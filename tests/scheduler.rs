@@ -0,0 +1,1707 @@
+//! Testing of `Scheduler` APIs.
+
+use legion::query::IntoQuery;
+use legion::world::World;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tonks::{
+    resource_id_for, CachedSystem, ClonableScheduler, Conflicts, Constraint, Context,
+    DispatchStats, EventHandler, EventsBuilder, FrameContext, MutexWrite, PreparedWorld,
+    ProfileSpan, Query, RawSystem, Read, ReadCached, ResourceEvents, Resources, SchedulerBuilder,
+    SchedulerStats, ScopedWrite, StageAssembler, System, SystemCtx, SystemData, SystemId, TimedOut,
+    TimeoutBudget, TraceAction, Trigger, TryRead, WorldResourceExtractor, Write,
+};
+
+struct DeltaTime(u32);
+
+#[derive(Default)]
+struct Elapsed(u32);
+
+struct Accumulate;
+
+impl System for Accumulate {
+    type SystemData = (Read<DeltaTime>, Write<Elapsed>);
+
+    fn run(&mut self, (delta, elapsed): <Self::SystemData as SystemData>::Output) {
+        elapsed.0 += delta.0;
+    }
+}
+
+#[test]
+fn execute_with_resources() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Accumulate)
+        .build(Resources::new());
+
+    let mut world = World::new();
+
+    for delta in 1..=3 {
+        let mut additional = Resources::new();
+        additional.insert(DeltaTime(delta));
+
+        scheduler.execute_with_resources(&mut additional, &mut world);
+
+        assert!(additional.contains::<DeltaTime>());
+        assert!(!scheduler.resources().contains::<DeltaTime>());
+    }
+
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 1 + 2 + 3);
+}
+
+#[test]
+fn execute_after_execute_with_resources() {
+    #[derive(Default)]
+    struct Deltas(Vec<Option<u32>>);
+
+    struct RecordDelta;
+
+    impl System for RecordDelta {
+        type SystemData = (TryRead<DeltaTime>, Write<Deltas>);
+
+        fn run(&mut self, (delta, deltas): <Self::SystemData as SystemData>::Output) {
+            deltas.0.push(delta.ok().map(|delta| delta.0));
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(RecordDelta)
+        .build(Resources::new());
+    let mut world = World::new();
+
+    for delta in 1..=2 {
+        let mut additional = Resources::new();
+        additional.insert(DeltaTime(delta));
+        scheduler.execute_with_resources(&mut additional, &mut world);
+    }
+
+    // The lent resources have been dropped, so the system
+    // must no longer observe them.
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        scheduler.resources().get::<Deltas>().0,
+        vec![Some(1), Some(2), None]
+    );
+}
+
+#[test]
+fn shutdown_waits_for_detached_tasks() {
+    struct Spawner(Arc<AtomicBool>);
+
+    impl System for Spawner {
+        type SystemData = SystemCtx;
+
+        fn run(&mut self, ctx: <Self::SystemData as SystemData>::Output) {
+            let done = Arc::clone(&self.0);
+            ctx.spawn_detached(move || {
+                thread::sleep(Duration::from_millis(50));
+                done.store(true, Ordering::SeqCst);
+            });
+        }
+    }
+
+    let done = Arc::new(AtomicBool::new(false));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Spawner(Arc::clone(&done)))
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+    scheduler.shutdown();
+
+    assert!(done.load(Ordering::SeqCst));
+}
+
+#[test]
+fn detached_tasks_use_label_pool() {
+    struct SaveAsset(Arc<parking_lot::Mutex<Option<String>>>);
+
+    impl System for SaveAsset {
+        type SystemData = SystemCtx;
+
+        fn run(&mut self, ctx: <Self::SystemData as SystemData>::Output) {
+            let thread_name = Arc::clone(&self.0);
+            ctx.spawn_detached(move || {
+                *thread_name.lock() = thread::current().name().map(str::to_owned);
+            });
+        }
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .thread_name(|i| format!("io-{}", i))
+        .build()
+        .unwrap();
+
+    let thread_name = Arc::new(parking_lot::Mutex::new(None));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with_label("io", SaveAsset(Arc::clone(&thread_name)))
+        .with_label_pool("io", Arc::new(pool))
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+    scheduler.shutdown();
+
+    assert_eq!(thread_name.lock().as_deref(), Some("io-0"));
+}
+
+#[test]
+fn warmup() {
+    #[derive(Clone, Copy)]
+    struct Age;
+
+    #[derive(Default)]
+    struct Runs(Vec<usize>);
+
+    struct CountEntities;
+
+    impl System for CountEntities {
+        type SystemData = (Query<legion::query::Read<Age>>, PreparedWorld, Write<Runs>);
+
+        fn run(&mut self, (query, world, runs): <Self::SystemData as SystemData>::Output) {
+            runs.0.push(query.iter_entities(world).count());
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(CountEntities)
+        .with_warmup(3)
+        .build(Resources::new());
+
+    assert_eq!(scheduler.resources().get::<Runs>().0, vec![0, 0, 0]);
+    assert_eq!(
+        scheduler.stats(),
+        DispatchStats {
+            dispatches: 0,
+            warmup_dispatches: 3,
+            system_executions: 0,
+            skipped_systems: 0,
+        }
+    );
+
+    // Systems see the real world after warmup.
+    let mut world = World::new();
+    world.insert((), [(Age,), (Age,)].iter().copied());
+    scheduler.execute(&mut world);
+
+    assert_eq!(scheduler.resources().get::<Runs>().0, vec![0, 0, 0, 2]);
+    assert_eq!(
+        scheduler.stats(),
+        DispatchStats {
+            dispatches: 1,
+            warmup_dispatches: 3,
+            system_executions: 1,
+            skipped_systems: 0,
+        }
+    );
+}
+
+#[test]
+fn warmup_keeps_system_data() {
+    struct Config(u32);
+
+    struct Computations(u32);
+
+    struct Derive;
+
+    impl System for Derive {
+        type SystemData = (ReadCached<Config, u32>, Write<Computations>);
+
+        fn run(&mut self, (config, computations): <Self::SystemData as SystemData>::Output) {
+            config.get_or_compute(|config| {
+                computations.0 += 1;
+                config.0 * 2
+            });
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(Config(1));
+    resources.insert(Computations(0));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Derive)
+        .with_warmup(2)
+        .build(resources);
+    scheduler.execute(&mut World::new());
+
+    // The value cached during warmup is still valid.
+    assert_eq!(scheduler.resources().get::<Computations>().0, 1);
+}
+
+#[test]
+fn calibrate() {
+    /// Places each system in its own stage, so that
+    /// non-conflicting stages run concurrently.
+    struct OneSystemPerStage;
+
+    impl StageAssembler for OneSystemPerStage {
+        fn assemble(
+            &self,
+            systems: &[SystemId],
+            _conflicts: &Conflicts,
+            _constraints: &[Constraint],
+        ) -> Vec<Vec<SystemId>> {
+            systems.iter().map(|system| vec![*system]).collect()
+        }
+    }
+
+    struct Increment(Arc<AtomicUsize>);
+
+    impl System for Increment {
+        type SystemData = ();
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    let mut builder = SchedulerBuilder::new().with_assembler(Box::new(OneSystemPerStage));
+    for _ in 0..32 {
+        builder.add(Increment(Arc::clone(&counter)));
+    }
+    let mut scheduler = builder.build(Resources::new());
+
+    assert_eq!(scheduler.channel_capacity(), 8);
+    scheduler.calibrate();
+    assert_eq!(scheduler.channel_capacity(), 32);
+    assert_eq!(counter.load(Ordering::SeqCst), 32);
+
+    let mut world = World::new();
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+    }
+    assert_eq!(counter.load(Ordering::SeqCst), 32 * 4);
+}
+
+#[test]
+fn calibrate_with_events() {
+    struct OneSystemPerStage;
+
+    impl StageAssembler for OneSystemPerStage {
+        fn assemble(
+            &self,
+            systems: &[SystemId],
+            _conflicts: &Conflicts,
+            _constraints: &[Constraint],
+        ) -> Vec<Vec<SystemId>> {
+            systems.iter().map(|system| vec![*system]).collect()
+        }
+    }
+
+    struct Ev;
+
+    struct TriggerEv;
+
+    impl System for TriggerEv {
+        type SystemData = Trigger<Ev>;
+
+        fn run(&mut self, trigger: <Self::SystemData as SystemData>::Output) {
+            trigger.trigger(Ev);
+        }
+    }
+
+    struct CountEvents(Arc<AtomicUsize>);
+
+    impl EventHandler<Ev> for CountEvents {
+        type HandlerData = ();
+
+        fn handle(&mut self, _event: &Ev, _data: &mut <Self::HandlerData as SystemData>::Output) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let counter = Arc::new(AtomicUsize::new(0));
+
+    let mut builder = EventsBuilder::new()
+        .with(CountEvents(Arc::clone(&counter)))
+        .finish()
+        .with_assembler(Box::new(OneSystemPerStage));
+    for _ in 0..20 {
+        builder.add(TriggerEv);
+    }
+    let mut scheduler = builder.build(Resources::new());
+
+    scheduler.calibrate();
+    assert_eq!(scheduler.channel_capacity(), 32);
+    assert_eq!(counter.load(Ordering::SeqCst), 20);
+
+    // Systems send their events through the resized channel.
+    let mut world = World::new();
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+    }
+    assert_eq!(counter.load(Ordering::SeqCst), 20 * 4);
+}
+
+#[test]
+fn take_resources() {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(5));
+
+    let mut scheduler = SchedulerBuilder::new().with(Accumulate).build(resources);
+    scheduler.execute(&mut World::new());
+
+    let resources = scheduler.take_resources();
+    assert_eq!(resources.get::<Elapsed>().0, 5);
+
+    // The resources can be reused by another scheduler.
+    let mut scheduler = SchedulerBuilder::new().with(Accumulate).build(resources);
+    scheduler.execute(&mut World::new());
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 10);
+}
+
+#[derive(Default)]
+struct WorldChanges(u32);
+
+struct DetectWorldChanges {
+    last_generation: u64,
+}
+
+impl System for DetectWorldChanges {
+    type SystemData = (SystemCtx, Write<WorldChanges>);
+
+    fn run(&mut self, (ctx, changes): <Self::SystemData as SystemData>::Output) {
+        let generation = ctx.world_generation();
+        if generation != self.last_generation {
+            self.last_generation = generation;
+            changes.0 += 1;
+        }
+    }
+}
+
+#[test]
+fn world_generation() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(DetectWorldChanges { last_generation: 0 })
+        .build(Resources::new());
+    let mut world = World::new();
+
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<WorldChanges>().0, 0);
+
+    world.insert((), vec![(1u32,)]);
+    scheduler.bump_world_generation();
+
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<WorldChanges>().0, 1);
+}
+
+#[test]
+fn execute_within_scope() {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(5));
+
+    let mut scheduler = SchedulerBuilder::new().with(Accumulate).build(resources);
+    let mut world = World::new();
+    let host_work_done = AtomicBool::new(false);
+
+    rayon::scope(|scope| {
+        scope.spawn(|_| host_work_done.store(true, Ordering::SeqCst));
+
+        scheduler.execute_within_scope(scope, &mut world);
+        scheduler.execute_within_scope(scope, &mut world);
+    });
+
+    assert!(host_work_done.load(Ordering::SeqCst));
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 10);
+}
+
+#[test]
+fn trace_resource() {
+    struct ResetElapsed;
+
+    impl System for ResetElapsed {
+        type SystemData = Write<Elapsed>;
+
+        fn run(&mut self, elapsed: <Self::SystemData as SystemData>::Output) {
+            elapsed.0 = 0;
+        }
+    }
+
+    let accumulate = CachedSystem::new(Accumulate, "accumulate");
+    let reset = CachedSystem::new(ResetElapsed, "reset");
+    let ids = [accumulate.id(), reset.id()];
+
+    let mut builder = SchedulerBuilder::new();
+    builder.add_boxed(Box::new(accumulate));
+    builder.add_boxed(Box::new(reset));
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = builder.build(resources);
+
+    scheduler.trace_resource(resource_id_for::<Elapsed>());
+    scheduler.execute(&mut World::new());
+
+    let trace = scheduler.take_resource_trace();
+    let actions: Vec<_> = trace
+        .iter()
+        .map(|entry| (entry.action, entry.systems.clone()))
+        .collect();
+    assert_eq!(
+        actions,
+        vec![
+            (TraceAction::Acquire, vec![ids[0]]),
+            (TraceAction::Release, vec![ids[0]]),
+            (TraceAction::Acquire, vec![ids[1]]),
+            (TraceAction::Release, vec![ids[1]]),
+        ]
+    );
+    assert!(trace.iter().all(|entry| entry.write));
+    assert!(trace
+        .iter()
+        .all(|entry| entry.resource == resource_id_for::<Elapsed>()));
+
+    assert!(scheduler.take_resource_trace().is_empty());
+}
+
+#[test]
+fn replace_resource() {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+
+    let mut scheduler = SchedulerBuilder::new().with(Accumulate).build(resources);
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+
+    let version = scheduler.resources().version::<DeltaTime>();
+    let old = scheduler.replace_resource(DeltaTime(10));
+    assert_eq!(old.map(|delta| delta.0), Some(1));
+    assert_eq!(scheduler.resources().version::<DeltaTime>(), version + 1);
+
+    // Systems which already loaded the resource see the new value.
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 11);
+
+    struct Unused;
+    assert!(scheduler.replace_resource(Unused).is_none());
+    assert!(scheduler.resources().contains::<Unused>());
+}
+
+#[test]
+fn hot_reload_systems() {
+    struct AccumulateTwice;
+
+    impl System for AccumulateTwice {
+        type SystemData = (Read<DeltaTime>, Write<Elapsed>);
+
+        fn run(&mut self, (delta, elapsed): <Self::SystemData as SystemData>::Output) {
+            elapsed.0 += 2 * delta.0;
+        }
+    }
+
+    struct ResetElapsed;
+
+    impl System for ResetElapsed {
+        type SystemData = Write<Elapsed>;
+
+        fn run(&mut self, elapsed: <Self::SystemData as SystemData>::Output) {
+            elapsed.0 = 0;
+        }
+    }
+
+    let accumulate = CachedSystem::new(Accumulate, "accumulate");
+    let id = accumulate.id();
+
+    let mut builder = SchedulerBuilder::new();
+    builder.add_boxed(Box::new(accumulate));
+    builder.add(DetectWorldChanges { last_generation: 0 });
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = builder.build(resources);
+    let mut world = World::new();
+
+    let before = scheduler.profile_dispatch(&mut world);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 1);
+
+    // Replacements must access the same data.
+    let rejected = scheduler.hot_reload_systems(vec![(
+        id,
+        Box::new(CachedSystem::new(ResetElapsed, "reset")) as Box<dyn RawSystem>,
+    )]);
+    assert_eq!(rejected, Err(vec![id]));
+
+    let reloaded = scheduler.hot_reload_systems(vec![(
+        id,
+        Box::new(CachedSystem::new(AccumulateTwice, "accumulate_twice")) as Box<dyn RawSystem>,
+    )]);
+    assert_eq!(reloaded, Ok(()));
+
+    let after = scheduler.profile_dispatch(&mut world);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 1 + 2);
+
+    let layout = |root: &ProfileSpan| -> Vec<usize> {
+        root.children
+            .iter()
+            .map(|stage| stage.children.len())
+            .collect()
+    };
+    assert_eq!(layout(&after), layout(&before));
+    assert!(after
+        .children
+        .iter()
+        .flat_map(|stage| &stage.children)
+        .any(|system| system.name == "accumulate_twice"));
+}
+
+#[test]
+fn hot_reload_keeps_system_data() {
+    struct Config(u32);
+    struct Derived(Vec<u32>);
+
+    type DeriveData = (ReadCached<Config, u32>, Write<Derived>);
+
+    struct Double;
+
+    impl System for Double {
+        type SystemData = DeriveData;
+
+        fn run(&mut self, (config, derived): <Self::SystemData as SystemData>::Output) {
+            derived
+                .0
+                .push(*config.get_or_compute(|config| config.0 * 2));
+        }
+    }
+
+    struct Triple;
+
+    impl System for Triple {
+        type SystemData = DeriveData;
+
+        fn run(&mut self, (config, derived): <Self::SystemData as SystemData>::Output) {
+            derived
+                .0
+                .push(*config.get_or_compute(|config| config.0 * 3));
+        }
+    }
+
+    let double = CachedSystem::new(Double, "double");
+    let id = double.id();
+
+    let mut builder = SchedulerBuilder::new();
+    builder.add_boxed(Box::new(double));
+
+    let mut resources = Resources::new();
+    resources.insert(Config(1));
+    resources.insert(Derived(vec![]));
+    let mut scheduler = builder.build(resources);
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+
+    let reloaded = scheduler.hot_reload_systems(vec![(
+        id,
+        Box::new(CachedSystem::new(Triple, "triple")) as Box<dyn RawSystem>,
+    )]);
+    assert_eq!(reloaded, Ok(()));
+
+    // The value cached by the old system is kept until the resource changes.
+    scheduler.execute(&mut world);
+    scheduler.replace_resource(Config(2));
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Derived>().0, vec![2, 2, 6]);
+}
+
+#[test]
+fn systems_len() {
+    let mut builder = SchedulerBuilder::new();
+    let mut ids = vec![];
+    for _ in 0..4 {
+        let system = CachedSystem::new(Accumulate, "accumulate");
+        ids.push(system.id());
+        builder.add_boxed(Box::new(system));
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = builder.build(resources);
+
+    assert!(ids.iter().all(|id| id.0 < scheduler.systems_len()));
+
+    // Dispatch checks that the per-system vectors cover every system.
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 8);
+}
+
+#[test]
+fn subscribe_read() {
+    #[derive(Clone, Default)]
+    struct Frame {
+        number: u32,
+        doubled: u32,
+    }
+
+    struct AdvanceFrame;
+
+    impl System for AdvanceFrame {
+        type SystemData = Write<Frame>;
+
+        fn run(&mut self, frame: <Self::SystemData as SystemData>::Output) {
+            frame.number += 1;
+            thread::sleep(Duration::from_micros(100));
+            frame.doubled = frame.number * 2;
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(Frame::default());
+    let mut scheduler = SchedulerBuilder::new().with(AdvanceFrame).build(resources);
+
+    let handle = scheduler.subscribe_read::<Frame>();
+    let done = Arc::new(AtomicBool::new(false));
+
+    let reader = {
+        let handle = handle.clone();
+        let done = Arc::clone(&done);
+        thread::spawn(move || {
+            let mut last = 0;
+            while !done.load(Ordering::Acquire) {
+                let frame = handle.get();
+                assert_eq!(frame.doubled, frame.number * 2);
+                assert!(frame.number >= last);
+                last = frame.number;
+            }
+        })
+    };
+
+    let mut world = World::new();
+    for _ in 0..100 {
+        scheduler.execute(&mut world);
+    }
+    done.store(true, Ordering::Release);
+    reader.join().unwrap();
+
+    assert_eq!(handle.read(|frame| frame.number), 100);
+}
+
+#[test]
+fn clonable_scheduler() {
+    #[derive(Clone)]
+    struct CountRuns {
+        runs: u32,
+    }
+
+    impl System for CountRuns {
+        type SystemData = Write<Elapsed>;
+
+        fn run(&mut self, elapsed: <Self::SystemData as SystemData>::Output) {
+            self.runs += 1;
+            elapsed.0 = self.runs;
+        }
+    }
+
+    let template = ClonableScheduler::new().with(CountRuns { runs: 0 });
+
+    let mut first = template.build(Resources::new());
+    let mut second = template.clone().build(Resources::new());
+
+    let mut world = World::new();
+    first.execute(&mut world);
+    first.execute(&mut world);
+    second.execute(&mut world);
+
+    assert_eq!(first.resources().get::<Elapsed>().0, 2);
+    assert_eq!(second.resources().get::<Elapsed>().0, 1);
+}
+
+#[test]
+#[should_panic]
+fn clone_non_clonable_system() {
+    let system = CachedSystem::new(Accumulate, "accumulate");
+    system.clone_dyn();
+}
+
+#[test]
+fn world_resource_extractor() {
+    #[derive(Clone, Copy)]
+    struct Player;
+
+    struct PlayerCount(usize);
+
+    struct PlayerCountExtractor;
+
+    impl WorldResourceExtractor for PlayerCountExtractor {
+        fn extract(world: &World, resources: &mut Resources) {
+            let count = <legion::query::Read<Player>>::query()
+                .iter_immutable(world)
+                .count();
+            if resources.contains::<PlayerCount>() {
+                resources.get_mut::<PlayerCount>().0 = count;
+            } else {
+                resources.insert(PlayerCount(count));
+            }
+        }
+    }
+
+    struct CountPlayers;
+
+    impl System for CountPlayers {
+        type SystemData = (Read<PlayerCount>, Write<Elapsed>);
+
+        fn run(&mut self, (count, elapsed): <Self::SystemData as SystemData>::Output) {
+            elapsed.0 = count.0 as u32;
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(CountPlayers)
+        .register_extractor::<PlayerCountExtractor>()
+        .build(Resources::new());
+
+    let mut world = World::new();
+    world.insert((), vec![(Player,), (Player,)]);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 2);
+
+    world.insert((), vec![(Player,)]);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 3);
+}
+
+#[test]
+fn execute_once_and_teardown() {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(7));
+
+    let scheduler = SchedulerBuilder::new().with(Accumulate).build(resources);
+    let (resources, stats) = scheduler.execute_once_and_teardown(&mut World::new());
+
+    assert_eq!(resources.get::<Elapsed>().0, 7);
+    assert_eq!(stats.dispatches, 1);
+}
+
+#[test]
+fn timeout_budget() {
+    #[derive(Default)]
+    struct Outcome {
+        timed_out: bool,
+        unbudgeted: Option<Duration>,
+    }
+
+    struct Pathfind;
+
+    impl System for Pathfind {
+        type SystemData = (SystemCtx, Write<Outcome>);
+
+        fn run(&mut self, (ctx, outcome): <Self::SystemData as SystemData>::Output) {
+            assert!(ctx.remaining_budget().is_some());
+            while ctx.check_timeout().is_ok() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            outcome.timed_out = ctx.check_timeout() == Err(TimedOut);
+        }
+    }
+
+    struct Unbudgeted;
+
+    impl System for Unbudgeted {
+        type SystemData = (SystemCtx, Write<Outcome>);
+
+        fn run(&mut self, (ctx, outcome): <Self::SystemData as SystemData>::Output) {
+            assert_eq!(ctx.check_timeout(), Ok(()));
+            outcome.unbudgeted = ctx.remaining_budget();
+        }
+    }
+
+    let pathfind = CachedSystem::new(Pathfind, "pathfind");
+    let mut budget = TimeoutBudget::new();
+    budget.set(pathfind.id(), Duration::from_millis(5));
+
+    let mut builder = SchedulerBuilder::new();
+    builder.add_boxed(Box::new(pathfind));
+    builder.add(Unbudgeted);
+
+    let mut resources = Resources::new();
+    resources.insert(budget);
+    let mut scheduler = builder.build(resources);
+    scheduler.execute(&mut World::new());
+
+    let outcome = scheduler.resources().get::<Outcome>();
+    assert!(outcome.timed_out);
+    assert_eq!(outcome.unbudgeted, None);
+}
+
+#[test]
+fn dispatch_budget() {
+    #[derive(Default)]
+    struct Ran(Vec<&'static str>);
+
+    struct Critical;
+
+    impl System for Critical {
+        type SystemData = Write<Ran>;
+
+        fn run(&mut self, ran: <Self::SystemData as SystemData>::Output) {
+            thread::sleep(Duration::from_millis(10));
+            ran.0.push("critical");
+        }
+    }
+
+    struct Cosmetic;
+
+    impl System for Cosmetic {
+        type SystemData = Write<Ran>;
+
+        fn run(&mut self, ran: <Self::SystemData as SystemData>::Output) {
+            ran.0.push("cosmetic");
+        }
+    }
+
+    // Both systems write `Ran`, so `Cosmetic` starts after `Critical`
+    // has already exceeded the budget.
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Critical)
+        .with_skippable(Cosmetic)
+        .with_dispatch_budget(Duration::from_millis(1))
+        .build(Resources::new());
+
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(scheduler.resources().get::<Ran>().0, vec!["critical"]);
+    assert_eq!(scheduler.stats().skipped_systems, 1);
+    assert_eq!(scheduler.stats().system_executions, 1);
+}
+
+#[test]
+fn resource_access_stats() {
+    #[derive(Default)]
+    struct Hot(u32);
+
+    struct WriteHot;
+
+    impl System for WriteHot {
+        type SystemData = Write<Hot>;
+
+        fn run(&mut self, hot: <Self::SystemData as SystemData>::Output) {
+            hot.0 += 1;
+        }
+    }
+
+    struct ReadHot;
+
+    impl System for ReadHot {
+        type SystemData = Read<Hot>;
+
+        fn run(&mut self, _hot: <Self::SystemData as SystemData>::Output) {}
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = SchedulerBuilder::new()
+        .with(WriteHot)
+        .with(WriteHot)
+        .with(ReadHot)
+        .with(ReadHot)
+        .with(ReadHot)
+        .with(Accumulate)
+        .build(resources);
+
+    scheduler.execute(&mut World::new());
+
+    let stats = scheduler.resource_access_stats();
+    let hot = stats[&resource_id_for::<Hot>()];
+    assert_eq!(hot.readers, 3);
+    assert_eq!(hot.writers, 2);
+
+    let elapsed = stats[&resource_id_for::<Elapsed>()];
+    assert_eq!(elapsed.readers, 0);
+    assert_eq!(elapsed.writers, 1);
+    assert_eq!(elapsed.contentions, 0);
+}
+
+#[test]
+fn execute_labeled() {
+    #[derive(Default)]
+    struct Saved(Vec<&'static str>);
+
+    struct SaveGame;
+
+    impl System for SaveGame {
+        type SystemData = (Read<Elapsed>, Write<Saved>);
+
+        fn run(&mut self, (_elapsed, saved): <Self::SystemData as SystemData>::Output) {
+            saved.0.push("game");
+        }
+    }
+
+    struct SaveSettings;
+
+    impl System for SaveSettings {
+        type SystemData = Write<Saved>;
+
+        fn run(&mut self, saved: <Self::SystemData as SystemData>::Output) {
+            saved.0.push("settings");
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Accumulate)
+        .with_label("save", SaveGame)
+        .with_label("save", SaveSettings)
+        .build(resources);
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 1);
+    assert_eq!(scheduler.resources().get::<Saved>().0.len(), 2);
+
+    // Only the labeled systems run, in the order they were added.
+    scheduler.execute_labeled("save", &mut world);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 1);
+    assert_eq!(
+        scheduler.resources().get::<Saved>().0[2..],
+        ["game", "settings"]
+    );
+}
+
+#[test]
+#[should_panic(expected = "no system has the label")]
+fn execute_unknown_label() {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = SchedulerBuilder::new().with(Accumulate).build(resources);
+    scheduler.execute_labeled("save", &mut World::new());
+}
+
+#[test]
+fn mutex_write() {
+    struct Increment;
+
+    impl System for Increment {
+        type SystemData = MutexWrite<u32>;
+
+        fn run(&mut self, counter: <Self::SystemData as SystemData>::Output) {
+            // Without the lock, increments from the other thread would be lost.
+            let value = **counter;
+            thread::sleep(Duration::from_millis(1));
+            **counter = value + 1;
+        }
+    }
+
+    let shared = Arc::new(parking_lot::Mutex::new(0u32));
+
+    let mut resources = Resources::new();
+    resources.insert(Arc::clone(&shared));
+    let mut scheduler = SchedulerBuilder::new().with(Increment).build(resources);
+
+    let external = {
+        let shared = Arc::clone(&shared);
+        thread::spawn(move || {
+            for _ in 0..20 {
+                let mut counter = shared.lock();
+                let value = *counter;
+                thread::sleep(Duration::from_millis(1));
+                *counter = value + 1;
+            }
+        })
+    };
+
+    let mut world = World::new();
+    for _ in 0..20 {
+        scheduler.execute(&mut world);
+    }
+    external.join().unwrap();
+
+    assert_eq!(*shared.lock(), 40);
+}
+
+#[test]
+fn reschedule() {
+    #[derive(Default)]
+    struct Runs(Vec<&'static str>);
+
+    struct Physics;
+
+    impl System for Physics {
+        type SystemData = (Read<DeltaTime>, Write<Runs>);
+
+        fn run(&mut self, (_delta, runs): <Self::SystemData as SystemData>::Output) {
+            runs.0.push("physics");
+        }
+    }
+
+    struct DebugOverlay;
+
+    impl System for DebugOverlay {
+        type SystemData = (Write<Runs>, Write<Elapsed>);
+
+        fn run(&mut self, (runs, _elapsed): <Self::SystemData as SystemData>::Output) {
+            runs.0.push("overlay");
+        }
+    }
+
+    let overlay = CachedSystem::new(DebugOverlay, "overlay");
+    let overlay_id = overlay.id();
+
+    let mut builder = SchedulerBuilder::new().with(Physics).with(Accumulate);
+    builder.add_boxed(Box::new(overlay));
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = builder.build(resources);
+
+    let mut world = World::new();
+    // The overlay conflicts with both other systems.
+    assert_eq!(scheduler.profile_dispatch(&mut world).children.len(), 2);
+
+    scheduler.set_system_enabled(overlay_id, false);
+    assert!(!scheduler.is_system_enabled(overlay_id));
+    assert_eq!(scheduler.profile_dispatch(&mut world).children.len(), 2);
+
+    scheduler.reschedule();
+    assert_eq!(scheduler.profile_dispatch(&mut world).children.len(), 1);
+    assert_eq!(
+        scheduler.resources().get::<Runs>().0,
+        vec!["physics", "overlay", "physics", "physics"]
+    );
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 3);
+
+    // Re-enabled systems only run again after another reschedule.
+    scheduler.set_system_enabled(overlay_id, true);
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Runs>().0.len(), 5);
+    scheduler.reschedule();
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Runs>().0.len(), 7);
+}
+
+#[test]
+fn take_system() {
+    struct CountRuns(u32);
+
+    impl System for CountRuns {
+        type SystemData = Write<Elapsed>;
+
+        fn run(&mut self, _elapsed: <Self::SystemData as SystemData>::Output) {
+            self.0 += 1;
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = SchedulerBuilder::new()
+        .with(CountRuns(0))
+        .with(Accumulate)
+        .build(resources);
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    let system = scheduler.take_system::<CountRuns>().unwrap();
+    assert_eq!(system.0, 2);
+    assert!(scheduler.take_system::<CountRuns>().is_none());
+
+    // The stage which contained only the taken system is removed.
+    assert_eq!(scheduler.profile_dispatch(&mut world).children.len(), 1);
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 3);
+}
+
+#[test]
+fn measure_parallelism() {
+    struct Sleep;
+
+    impl System for Sleep {
+        type SystemData = ();
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Sleep)
+        .with(Sleep)
+        .build(Resources::new());
+    assert_eq!(scheduler.measure_parallelism(), 0.0);
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+
+    let parallelism = scheduler.measure_parallelism();
+    assert!(parallelism > 0.0 && parallelism <= 1.0, "{}", parallelism);
+}
+
+#[test]
+fn context() {
+    #[derive(Default)]
+    struct Frames(Vec<(u64, Duration)>);
+
+    struct RecordFrames;
+
+    impl System for RecordFrames {
+        type SystemData = (Context, Write<Frames>);
+
+        fn run(&mut self, (ctx, frames): <Self::SystemData as SystemData>::Output) {
+            frames.0.push((ctx.frame(), ctx.delta()));
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(RecordFrames)
+        .build(Resources::new());
+
+    let mut world = World::new();
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+        thread::sleep(Duration::from_millis(5));
+    }
+
+    let frames = &scheduler.resources().get::<Frames>().0;
+    assert_eq!(
+        frames.iter().map(|(frame, _)| *frame).collect::<Vec<_>>(),
+        vec![0, 1, 2]
+    );
+    assert_eq!(frames[0].1, Duration::from_secs(0));
+    assert!(frames[1..]
+        .iter()
+        .all(|(_, delta)| *delta >= Duration::from_millis(5)));
+}
+
+#[test]
+fn scheduler_stats() {
+    #[derive(Default)]
+    struct Hot(u32);
+
+    #[derive(Default)]
+    struct Contentions(Vec<u64>);
+
+    struct SlowWrite;
+
+    impl System for SlowWrite {
+        type SystemData = Write<Hot>;
+
+        fn run(&mut self, hot: <Self::SystemData as SystemData>::Output) {
+            thread::sleep(Duration::from_millis(10));
+            hot.0 += 1;
+        }
+    }
+
+    struct FastWrite;
+
+    impl System for FastWrite {
+        type SystemData = Write<Hot>;
+
+        fn run(&mut self, hot: <Self::SystemData as SystemData>::Output) {
+            hot.0 += 1;
+        }
+    }
+
+    struct RecordContentions;
+
+    impl System for RecordContentions {
+        type SystemData = (SchedulerStats, Write<Contentions>);
+
+        fn run(&mut self, (stats, contentions): <Self::SystemData as SystemData>::Output) {
+            contentions.0.push(stats.contentions());
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(SlowWrite)
+        .with(FastWrite)
+        .with(RecordContentions)
+        .build(Resources::new());
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    // `FastWrite` is blocked on `Hot` while `SlowWrite` runs.
+    let contentions = &scheduler.resources().get::<Contentions>().0;
+    assert_eq!(contentions[0], 0);
+    assert!(contentions[1] > 0);
+}
+
+#[test]
+fn stall_timeout() {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Accumulate)
+        .with_stall_timeout(Duration::from_millis(10))
+        .build(resources);
+
+    // The pool's only thread runs the scheduler, so no other
+    // thread can pick up the tasks it spawns.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    let mut world = World::new();
+    pool.install(|| {
+        scheduler.execute(&mut world);
+        scheduler.execute(&mut world);
+    });
+
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 2);
+}
+
+#[test]
+fn resources_changed_since() {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = SchedulerBuilder::new().with(Accumulate).build(resources);
+
+    let snapshot = scheduler.resource_snapshot();
+    assert_eq!(scheduler.resource_snapshot(), snapshot);
+    assert!(scheduler.resources_changed_since(&snapshot).is_empty());
+
+    scheduler.execute(&mut World::new());
+    assert_ne!(scheduler.resource_snapshot(), snapshot);
+    assert_eq!(
+        scheduler.resources_changed_since(&snapshot),
+        vec![resource_id_for::<Elapsed>()]
+    );
+}
+
+#[test]
+fn resource_events() {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    resources.insert(ResourceEvents::<Elapsed>::new());
+    let mut scheduler = SchedulerBuilder::new().with(Accumulate).build(resources);
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+
+    let events = scheduler.resources().get::<ResourceEvents<Elapsed>>();
+    let versions: Vec<u64> = events.drain().iter().map(|event| event.version).collect();
+    assert_eq!(versions.len(), 2);
+    assert_eq!(versions[1], versions[0] + 1);
+    assert_eq!(
+        *versions.last().unwrap(),
+        scheduler.resources().version::<Elapsed>()
+    );
+    assert!(events.drain().is_empty());
+}
+
+#[test]
+fn write_dirty_tracking() {
+    #[derive(Default)]
+    struct Dirty(Vec<(bool, bool)>);
+
+    struct TouchElapsed;
+
+    impl System for TouchElapsed {
+        type SystemData = (Write<Elapsed>, Write<Dirty>);
+
+        fn run(&mut self, (elapsed, dirty): <Self::SystemData as SystemData>::Output) {
+            let before = elapsed.is_dirty();
+            elapsed.0 += 0;
+            let after = elapsed.is_dirty();
+            dirty.0.push((before, after));
+
+            // Adding zero does not change the value.
+            elapsed.mark_clean();
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(TouchElapsed)
+        .build(Resources::new());
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    let version = scheduler.resources().version::<Elapsed>();
+    scheduler.execute(&mut world);
+
+    assert_eq!(
+        scheduler.resources().get::<Dirty>().0,
+        vec![(false, true), (false, true)]
+    );
+    assert_eq!(scheduler.resources().version::<Elapsed>(), version);
+}
+
+#[test]
+fn checkpoint_restore() {
+    #[derive(Clone, Default)]
+    struct Frames(Vec<u64>);
+
+    struct RecordFrames;
+
+    impl System for RecordFrames {
+        type SystemData = (Context, Write<Frames>);
+
+        fn run(&mut self, (ctx, frames): <Self::SystemData as SystemData>::Output) {
+            frames.0.push(ctx.frame());
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(RecordFrames)
+        .with_checkpointed_resource::<Frames>()
+        .build(Resources::new());
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    let checkpoint = scheduler.checkpoint();
+
+    scheduler.execute(&mut world);
+    let expected = scheduler.resources().get::<Frames>().0.clone();
+    assert_eq!(expected, vec![0, 1]);
+
+    scheduler.execute(&mut world);
+    scheduler.restore(&checkpoint);
+    assert_eq!(scheduler.resources().get::<Frames>().0, vec![0]);
+    assert_eq!(scheduler.stats().dispatches, 1);
+
+    scheduler.execute(&mut world);
+    assert_eq!(scheduler.resources().get::<Frames>().0, expected);
+
+    // A new scheduler continues from the restored frame.
+    let mut restored = SchedulerBuilder::new()
+        .with(RecordFrames)
+        .with_checkpointed_resource::<Frames>()
+        .build(Resources::new());
+    restored.restore(&checkpoint);
+    restored.execute(&mut world);
+    assert_eq!(restored.resources().get::<Frames>().0, expected);
+}
+
+#[test]
+fn execute_partial() {
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    struct First;
+
+    impl System for First {
+        type SystemData = Write<Log>;
+
+        fn run(&mut self, log: <Self::SystemData as SystemData>::Output) {
+            log.0.push("first");
+        }
+    }
+
+    struct Second;
+
+    impl System for Second {
+        type SystemData = Write<Log>;
+
+        fn run(&mut self, log: <Self::SystemData as SystemData>::Output) {
+            log.0.push("second");
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(First)
+        .with(Second)
+        .build(Resources::new());
+
+    let mut world = World::new();
+    let remaining = scheduler.execute_partial(0.5, &mut world);
+    assert_eq!(remaining.stages(), 1);
+    assert_eq!(scheduler.resources().get::<Log>().0.len(), 1);
+
+    let remaining = scheduler.execute_partial(0.5, &mut world);
+    assert!(remaining.is_complete());
+    assert_eq!(
+        scheduler.resources().get::<Log>().0,
+        vec!["first", "second"]
+    );
+
+    // The next call starts over.
+    let remaining = scheduler.execute_partial(0.5, &mut world);
+    assert_eq!(remaining.stages(), 1);
+    assert_eq!(scheduler.resources().get::<Log>().0.len(), 3);
+}
+
+#[test]
+fn execute_within_budget() {
+    #[derive(Default)]
+    struct Count(u32);
+
+    struct Increment;
+
+    impl System for Increment {
+        type SystemData = Write<Count>;
+
+        fn run(&mut self, count: <Self::SystemData as SystemData>::Output) {
+            count.0 += 1;
+        }
+    }
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Increment)
+        .with(Increment)
+        .with(Increment)
+        .build(Resources::new());
+    let mut world = World::new();
+
+    // With no budget, each call only runs the stage it is required to.
+    for stage in 0..3 {
+        let completed = scheduler.execute_within_budget(Duration::from_secs(0), &mut world);
+        assert_eq!(
+            completed
+                .stages()
+                .iter()
+                .map(|id| id.index())
+                .collect::<Vec<_>>(),
+            vec![stage]
+        );
+        assert_eq!(completed.remaining(), 2 - stage);
+        assert_eq!(scheduler.resources().get::<Count>().0, stage as u32 + 1);
+    }
+
+    let completed = scheduler.execute_within_budget(Duration::from_secs(60), &mut world);
+    assert_eq!(completed.stages().len(), 3);
+    assert!(completed.is_complete());
+    assert_eq!(scheduler.resources().get::<Count>().0, 6);
+}
+
+#[test]
+fn execute_canonical() {
+    /// Records its index, which increases with its `SystemId`
+    /// since IDs are allocated in the order systems are created.
+    struct Record(usize, Arc<parking_lot::Mutex<Vec<usize>>>);
+
+    impl System for Record {
+        type SystemData = ();
+
+        fn run(&mut self, _data: <Self::SystemData as SystemData>::Output) {
+            self.1.lock().push(self.0);
+        }
+    }
+
+    let log = Arc::new(parking_lot::Mutex::new(vec![]));
+    let mut builder = SchedulerBuilder::new();
+    for index in 0..8 {
+        builder = builder.with(Record(index, Arc::clone(&log)));
+    }
+    let mut scheduler = builder.build(Resources::new());
+
+    let mut world = World::new();
+    for _ in 0..10 {
+        scheduler.execute_canonical(&mut world);
+
+        let indices = std::mem::take(&mut *log.lock());
+        assert_eq!(indices, (0..8).collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn frame_context() {
+    struct Input {
+        frame: u32,
+        clicks: Vec<u32>,
+    }
+
+    #[derive(Default)]
+    struct Frames(Vec<u32>);
+    #[derive(Default)]
+    struct Clicks(Vec<u32>);
+
+    struct RecordFrame;
+
+    impl System for RecordFrame {
+        type SystemData = (FrameContext<Input>, Write<Frames>);
+
+        fn run(&mut self, (input, frames): <Self::SystemData as SystemData>::Output) {
+            frames.0.push(input.frame);
+        }
+    }
+
+    struct RecordClicks;
+
+    impl System for RecordClicks {
+        type SystemData = (FrameContext<Input>, Write<Clicks>);
+
+        fn run(&mut self, (input, clicks): <Self::SystemData as SystemData>::Output) {
+            clicks
+                .0
+                .extend(input.get().map(|input| input.clicks.len() as u32));
+        }
+    }
+
+    let builder = SchedulerBuilder::new().with(RecordFrame).with(RecordClicks);
+    // The context is only read, so both systems share a stage.
+    assert!(builder
+        .profile_stage_assignments(&Resources::new())
+        .lines()
+        .all(|line| line.ends_with("placed in stage 0.")));
+
+    let mut scheduler = builder.build(Resources::new());
+    let mut world = World::new();
+
+    for frame in 1..=2 {
+        scheduler.execute_with_context(
+            Input {
+                frame,
+                clicks: vec![0; frame as usize],
+            },
+            &mut world,
+        );
+    }
+
+    assert_eq!(scheduler.resources().get::<Frames>().0, vec![1, 2]);
+    assert_eq!(scheduler.resources().get::<Clicks>().0, vec![1, 2]);
+
+    // Without a context, `get()` returns `None`.
+    let mut scheduler = SchedulerBuilder::new()
+        .with(RecordClicks)
+        .build(Resources::new());
+    scheduler.execute(&mut world);
+    assert!(scheduler.resources().get::<Clicks>().0.is_empty());
+}
+
+#[test]
+fn label_pools() {
+    struct RecordThread(&'static str);
+
+    impl System for RecordThread {
+        type SystemData = MutexWrite<Vec<(&'static str, String)>>;
+
+        fn run(&mut self, threads: <Self::SystemData as SystemData>::Output) {
+            let name = thread::current().name().unwrap_or_default().to_owned();
+            threads.push((self.0, name));
+        }
+    }
+
+    let pool = |name: &'static str| {
+        Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(2)
+                .thread_name(move |i| format!("{}-{}", name, i))
+                .build()
+                .unwrap(),
+        )
+    };
+
+    let threads = Arc::new(parking_lot::Mutex::new(Vec::<(&str, String)>::new()));
+
+    let mut resources = Resources::new();
+    resources.insert(Arc::clone(&threads));
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with_label("a", RecordThread("a"))
+        .with_label("a", RecordThread("a"))
+        .with_label("b", RecordThread("b"))
+        .with_label_pool("a", pool("pool-a"))
+        .with_label_pool("b", pool("pool-b"))
+        .build(resources);
+
+    let mut world = World::new();
+    for _ in 0..4 {
+        scheduler.execute(&mut world);
+    }
+    scheduler.execute_labeled("b", &mut world);
+
+    let threads = threads.lock();
+    assert_eq!(threads.len(), 13);
+    for (label, thread) in threads.iter() {
+        assert!(
+            thread.starts_with(&format!("pool-{}-", label)),
+            "system labeled {} ran on {}",
+            label,
+            thread
+        );
+    }
+}
+
+#[test]
+fn scoped_write_released_early() {
+    #[derive(Default)]
+    struct Progress {
+        writer_ran: AtomicBool,
+        seen_before_completion: AtomicBool,
+    }
+
+    struct ScopedIncrement;
+
+    impl System for ScopedIncrement {
+        type SystemData = (ScopedWrite<Elapsed>, Read<Progress>);
+
+        fn run(&mut self, (elapsed, progress): <Self::SystemData as SystemData>::Output) {
+            let mut elapsed = elapsed;
+            elapsed.0 += 1;
+            drop(elapsed);
+
+            // Keep running until the queued writer has run.
+            for _ in 0..1000 {
+                if progress.writer_ran.load(Ordering::SeqCst) {
+                    progress
+                        .seen_before_completion
+                        .store(true, Ordering::SeqCst);
+                    return;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    struct Increment;
+
+    impl System for Increment {
+        type SystemData = (Write<Elapsed>, Read<Progress>);
+
+        fn run(&mut self, (elapsed, progress): <Self::SystemData as SystemData>::Output) {
+            elapsed.0 += 10;
+            progress.writer_ran.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(Elapsed::default());
+    resources.insert(Progress::default());
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(ScopedIncrement)
+        .with(Increment)
+        .build(resources);
+
+    // The scheduler blocks a thread while waiting, and the first system
+    // another, so the writer needs a pool with more threads to run on.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+    pool.install(|| scheduler.execute(&mut World::new()));
+
+    let progress = scheduler.resources().get::<Progress>();
+    assert!(progress.seen_before_completion.load(Ordering::SeqCst));
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 11);
+}
+
+#[test]
+fn schedule_oneshot() {
+    struct ScheduleTwice;
+
+    impl System for ScheduleTwice {
+        type SystemData = (SystemCtx, Read<DeltaTime>);
+
+        fn run(&mut self, (ctx, _): <Self::SystemData as SystemData>::Output) {
+            ctx.schedule_oneshot::<Accumulate>().unwrap();
+            ctx.schedule_oneshot::<Accumulate>().unwrap();
+
+            let err = ctx.schedule_oneshot::<DeltaTime>().unwrap_err();
+            assert_eq!(err.type_name(), std::any::type_name::<DeltaTime>());
+        }
+    }
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    resources.insert(Elapsed::default());
+
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Accumulate)
+        .with(ScheduleTwice)
+        .build(resources);
+    scheduler.execute(&mut World::new());
+
+    // Once in its stage and twice as a oneshot.
+    assert_eq!(scheduler.resources().get::<Elapsed>().0, 3);
+}
@@ -0,0 +1,58 @@
+//! Testing of bulk entity spawning through `EntityReserve`.
+
+use legion::query::{IntoQuery, Read};
+use legion::world::World;
+use tonks::{EntityReserve, Resources, SchedulerBuilder, System, SystemData};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Position(u32);
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Velocity(u32);
+
+struct SpawnBatch;
+
+impl System for SpawnBatch {
+    type SystemData = EntityReserve<(Position, Velocity)>;
+
+    fn run(&mut self, spawner: <Self::SystemData as SystemData>::Output) {
+        spawner.reserve(100);
+        for i in 0..100 {
+            spawner.spawn((Position(i), Velocity(1)));
+        }
+        assert_eq!(spawner.len(), 100);
+    }
+}
+
+struct SpawnNone;
+
+impl System for SpawnNone {
+    type SystemData = EntityReserve<(Position,)>;
+
+    fn run(&mut self, spawner: <Self::SystemData as SystemData>::Output) {
+        // Reserved space which is never used is released.
+        spawner.reserve(10);
+        assert!(spawner.is_empty());
+    }
+}
+
+#[test]
+fn bulk_spawn() {
+    let mut world = World::new();
+    let mut scheduler = SchedulerBuilder::new()
+        .with(SpawnBatch)
+        .with(SpawnNone)
+        .build(Resources::new());
+
+    scheduler.execute(&mut world);
+
+    let mut positions: Vec<u32> = Read::<Position>::query()
+        .iter(&mut world)
+        .map(|position| position.0)
+        .collect();
+    positions.sort_unstable();
+    assert_eq!(positions, (0..100).collect::<Vec<_>>());
+    assert_eq!(Read::<Velocity>::query().iter(&mut world).count(), 100);
+
+    scheduler.execute(&mut world);
+    assert_eq!(Read::<Position>::query().iter(&mut world).count(), 200);
+}
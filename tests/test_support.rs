@@ -0,0 +1,62 @@
+//! Testing of the `test-support` helpers.
+
+#![cfg(feature = "test-support")]
+
+use legion::world::World;
+use tonks::test_support::run_and_check;
+use tonks::{Read, Resources, SchedulerBuilder, System, SystemData, Write};
+
+struct DeltaTime(u32);
+
+#[derive(Default)]
+struct Elapsed(u32);
+
+#[derive(Default)]
+struct Frames(u32);
+
+struct Accumulate;
+
+impl System for Accumulate {
+    type SystemData = (Read<DeltaTime>, Write<Elapsed>);
+
+    fn run(&mut self, (delta, elapsed): <Self::SystemData as SystemData>::Output) {
+        elapsed.0 += delta.0;
+    }
+}
+
+struct CountFrames;
+
+impl System for CountFrames {
+    type SystemData = Write<Frames>;
+
+    fn run(&mut self, frames: <Self::SystemData as SystemData>::Output) {
+        frames.0 += 1;
+    }
+}
+
+fn resources() -> Resources {
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(2));
+    resources
+}
+
+#[test]
+fn accumulator_invariant() {
+    let builder = SchedulerBuilder::new().with(Accumulate).with(CountFrames);
+
+    let scheduler = run_and_check(builder, resources(), &mut World::new(), 100, |resources| {
+        resources.get::<Elapsed>().0 == resources.get::<Frames>().0 * 2
+    });
+
+    assert_eq!(scheduler.stats().dispatches, 100);
+}
+
+#[test]
+#[should_panic(expected = "invariant violated after dispatch 4 of 10")]
+fn invariant_violated() {
+    let builder = SchedulerBuilder::new().with(Accumulate);
+
+    run_and_check(builder, resources(), &mut World::new(), 10, |resources| {
+        resources.get::<Elapsed>().0 < 8
+    });
+}
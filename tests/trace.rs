@@ -0,0 +1,124 @@
+//! Testing of `tracing` integration.
+
+#![cfg(feature = "trace")]
+
+use legion::world::World;
+use parking_lot::Mutex;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tonks::{Read, Resources, SchedulerBuilder, System, SystemData, Write};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// Subscriber which records the name of each span,
+/// along with its `name` field if it has one.
+#[derive(Default)]
+struct RecordSpans {
+    next_id: AtomicU64,
+    spans: Arc<Mutex<Vec<String>>>,
+}
+
+struct NameField(Option<String>);
+
+impl Visit for NameField {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn Debug) {}
+}
+
+impl Subscriber for RecordSpans {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes) -> Id {
+        let mut name = NameField(None);
+        span.record(&mut name);
+
+        let recorded = match name.0 {
+            Some(name) => format!("{} {}", span.metadata().name(), name),
+            None => span.metadata().name().to_owned(),
+        };
+        self.spans.lock().push(recorded);
+
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+struct DeltaTime(u32);
+
+#[derive(Default)]
+struct Elapsed(u32);
+
+#[derive(Default)]
+struct Frames(u32);
+
+struct Accumulate;
+
+impl System for Accumulate {
+    type SystemData = (Read<DeltaTime>, Write<Elapsed>);
+
+    fn run(&mut self, (delta, elapsed): <Self::SystemData as SystemData>::Output) {
+        elapsed.0 += delta.0;
+    }
+}
+
+struct CountFrames;
+
+impl System for CountFrames {
+    type SystemData = Write<Frames>;
+
+    fn run(&mut self, frames: <Self::SystemData as SystemData>::Output) {
+        frames.0 += 1;
+    }
+}
+
+#[test]
+fn span_per_system() {
+    let subscriber = RecordSpans::default();
+    let spans = Arc::clone(&subscriber.spans);
+    // Systems run on worker threads, so the subscriber must be global.
+    tracing::subscriber::set_global_default(subscriber).unwrap();
+
+    let mut resources = Resources::new();
+    resources.insert(DeltaTime(1));
+    let mut scheduler = SchedulerBuilder::new()
+        .with(Accumulate)
+        .with(CountFrames)
+        .build(resources);
+
+    let mut world = World::new();
+    for _ in 0..3 {
+        scheduler.execute(&mut world);
+    }
+
+    let spans = spans.lock();
+    let count = |name: &str| spans.iter().filter(|span| *span == name).count();
+
+    assert_eq!(count("dispatch"), 3);
+    assert_eq!(count("stage"), 3);
+    assert_eq!(
+        count(&format!("system {}", std::any::type_name::<Accumulate>())),
+        3
+    );
+    assert_eq!(
+        count(&format!("system {}", std::any::type_name::<CountFrames>())),
+        3
+    );
+}
@@ -0,0 +1,58 @@
+use legion::world::World;
+use tonks::{
+    resource_id_for, Diff, Resources, SchedulerBuilder, System, SystemData, TrackedChanges,
+    TrackedWrite,
+};
+
+#[derive(Clone, Default, PartialEq)]
+struct Score(u32);
+
+impl Diff for Score {
+    type Delta = u32;
+
+    fn diff(&self, old: &Self) -> u32 {
+        self.0 - old.0
+    }
+}
+
+struct AddScore(Vec<u32>);
+
+impl System for AddScore {
+    type SystemData = TrackedWrite<Score>;
+
+    fn run(&mut self, score: <Self::SystemData as SystemData>::Output) {
+        if let Some(points) = self.0.pop() {
+            score.0 += points;
+        }
+    }
+}
+
+#[test]
+fn track_changes() {
+    let mut scheduler = SchedulerBuilder::new()
+        .with(AddScore(vec![5, 3]))
+        .build(Resources::new());
+    assert!(scheduler
+        .resource_access_stats()
+        .contains_key(&resource_id_for::<TrackedChanges<Score>>()));
+
+    let mut world = World::new();
+    scheduler.execute(&mut world);
+    scheduler.execute(&mut world);
+    {
+        let changes = scheduler.resources().get::<TrackedChanges<Score>>();
+        assert!(changes.is_changed());
+        assert_eq!(changes.diff(scheduler.resources().get::<Score>()), Some(8));
+    }
+
+    let mut resources = scheduler.take_resources();
+    resources.get_mut::<TrackedChanges<Score>>().clear();
+    let mut scheduler = SchedulerBuilder::new()
+        .with(AddScore(vec![]))
+        .build(resources);
+
+    scheduler.execute(&mut world);
+    let changes = scheduler.resources().get::<TrackedChanges<Score>>();
+    assert!(!changes.is_changed());
+    assert_eq!(changes.diff(scheduler.resources().get::<Score>()), None);
+}
@@ -0,0 +1,39 @@
+use legion::world::World;
+use tonks::{Resources, SchedulerBuilder, System, SystemData, TryRead, Write};
+
+struct Present(u32);
+struct Absent;
+
+#[derive(Default)]
+struct Outcomes(Vec<Result<u32, String>>);
+
+struct CheckDependencies;
+
+impl System for CheckDependencies {
+    type SystemData = (TryRead<Present>, TryRead<Absent>, Write<Outcomes>);
+
+    fn run(&mut self, (present, absent, outcomes): <Self::SystemData as SystemData>::Output) {
+        outcomes
+            .0
+            .push(present.map(|present| present.0).map_err(|e| e.to_string()));
+        outcomes
+            .0
+            .push(absent.map(|_| 0).map_err(|e| e.type_name().to_owned()));
+    }
+}
+
+#[test]
+fn try_read() {
+    let mut resources = Resources::new();
+    resources.insert(Present(3));
+    let mut scheduler = SchedulerBuilder::new()
+        .with(CheckDependencies)
+        .build(resources);
+
+    scheduler.execute(&mut World::new());
+
+    assert_eq!(
+        scheduler.resources().get::<Outcomes>().0,
+        vec![Ok(3), Err(std::any::type_name::<Absent>().to_owned())]
+    );
+}
@@ -0,0 +1,38 @@
+use tonks::{Marker, Query, Read, SystemData, Write};
+
+#[derive(Default)]
+struct Config;
+#[derive(Default)]
+struct Stats;
+#[derive(Clone, Copy)]
+struct Position;
+
+#[derive(SystemData)]
+pub struct Bundle {
+    pub config: Read<Config>,
+    stats: Write<Stats>,
+    positions: Query<legion::query::Read<Position>>,
+}
+
+#[derive(SystemData)]
+pub struct Generic<T>
+where
+    T: Default + Send + Sync + 'static,
+{
+    pub value: Read<T>,
+}
+
+#[derive(SystemData)]
+pub struct Borrowed<'a> {
+    pub config: Read<Config>,
+    pub stats: Write<Stats>,
+    pub marker: Marker<&'a ()>,
+}
+
+fn assert_system_data<T: for<'a> SystemData<'a>>() {}
+
+fn main() {
+    assert_system_data::<Bundle>();
+    assert_system_data::<Generic<Config>>();
+    assert_system_data::<Borrowed<'static>>();
+}
@@ -0,0 +1,13 @@
+#[macro_use]
+extern crate tonks;
+
+use std::cell::Cell;
+
+struct Counter(Cell<u32>);
+
+#[system]
+fn count(counter: &mut Counter) {
+    counter.0.set(counter.0.get() + 1);
+}
+
+fn main() {}